@@ -0,0 +1,187 @@
+//! 先発グレゴリオ暦の日付計算（UTC 秒 ⇔ 年月日、ISO 週）
+
+pub const SECONDS_PER_DAY: i64 = 86_400;
+
+/// 時差の許容範囲（UTC-14:00 .. UTC+14:00）
+pub const MAX_TZ_OFFSET_MINUTES: i32 = 14 * 60;
+
+pub fn is_leap_year(y: i64) -> bool {
+    (y % 4 == 0 && y % 100 != 0) || y % 400 == 0
+}
+
+pub fn days_in_month(y: i64, m: u32) -> u32 {
+    match m {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(y) => 29,
+        2 => 28,
+        _ => 0,
+    }
+}
+
+/// 1970-01-01 からの通算日数（負も可）
+pub fn days_from_civil(y: i64, m: u32, d: u32) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let m = i64::from(m);
+    let mp = if m > 2 { m - 3 } else { m + 9 };
+    let doy = (153 * mp + 2) / 5 + i64::from(d) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// 通算日数 → (年, 月, 日)
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    (y, m, d)
+}
+
+/// ISO 曜日（月=1 .. 日=7）
+pub fn iso_weekday(days: i64) -> u32 {
+    // 1970-01-01 は木曜
+    ((days + 3).rem_euclid(7) + 1) as u32
+}
+
+/// ISO 年の第 1 週の月曜日（通算日数）
+pub fn iso_year_start(y: i64) -> i64 {
+    let jan4 = days_from_civil(y, 1, 4);
+    jan4 - i64::from(iso_weekday(jan4)) + 1
+}
+
+/// ISO 年の週数（52 または 53）
+pub fn iso_weeks_in_year(y: i64) -> u32 {
+    ((iso_year_start(y + 1) - iso_year_start(y)) / 7) as u32
+}
+
+/// 通算日数 → (ISO 年, ISO 週 1..=53)
+pub fn iso_week(days: i64) -> (i64, u32) {
+    let thursday = days - i64::from(iso_weekday(days)) + 4;
+    let (y, _, _) = civil_from_days(thursday);
+    let week = (thursday - days_from_civil(y, 1, 1)) / 7 + 1;
+    (y, week as u32)
+}
+
+/// UTC 秒を時差（分）を加味したローカルの通算日数に変換
+/// （i64 を超える ts でも符号が反転しないよう i128 で計算する）
+pub fn local_days(ts: u64, tz_offset_minutes: i32) -> i64 {
+    let local = i128::from(ts) + i128::from(tz_offset_minutes) * 60;
+    local.div_euclid(i128::from(SECONDS_PER_DAY)) as i64
+}
+
+/// ローカル日付の範囲 [first_day, end_day) を UTC 秒の閉区間に変換。
+/// 1970 年より前にかかる部分は切り詰め、全体が前なら None。
+pub fn utc_range(first_day: i64, end_day: i64, tz_offset_minutes: i32) -> Option<(u64, u64)> {
+    let offset = i64::from(tz_offset_minutes) * 60;
+    let start = first_day * SECONDS_PER_DAY - offset;
+    let end = end_day * SECONDS_PER_DAY - offset - 1;
+    if end < 0 {
+        return None;
+    }
+    Some((start.max(0) as u64, end as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leap_years() {
+        assert!(is_leap_year(2000));
+        assert!(is_leap_year(2024));
+        assert!(!is_leap_year(1900));
+        assert!(!is_leap_year(2100));
+        assert!(!is_leap_year(2023));
+        assert_eq!(days_in_month(2024, 2), 29);
+        assert_eq!(days_in_month(2000, 2), 29);
+        assert_eq!(days_in_month(1900, 2), 28);
+        assert_eq!(days_in_month(2023, 2), 28);
+        assert_eq!(days_in_month(2023, 13), 0);
+    }
+
+    #[test]
+    fn civil_round_trip() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(
+            civil_from_days(days_from_civil(2100, 3, 1) - 1),
+            (2100, 2, 28)
+        );
+        for days in -800_000..800_000i64 {
+            if days % 97 != 0 {
+                continue;
+            }
+            let (y, m, d) = civil_from_days(days);
+            assert!(d >= 1 && d <= days_in_month(y, m));
+            assert_eq!(days_from_civil(y, m, d), days);
+        }
+    }
+
+    #[test]
+    fn iso_weeks() {
+        // 1970-01-01 は木曜
+        assert_eq!(iso_weekday(0), 4);
+        assert_eq!(iso_weekday(days_from_civil(2024, 12, 30)), 1);
+
+        // 53 週の年（木曜始まりの年と、水曜始まりのうるう年）
+        assert_eq!(iso_weeks_in_year(2015), 53);
+        assert_eq!(iso_weeks_in_year(2020), 53);
+        assert_eq!(iso_weeks_in_year(2026), 53);
+        assert_eq!(iso_weeks_in_year(2021), 52);
+        assert_eq!(iso_weeks_in_year(2024), 52);
+
+        // 年をまたぐ週
+        assert_eq!(iso_week(days_from_civil(2021, 1, 1)), (2020, 53));
+        assert_eq!(iso_week(days_from_civil(2021, 1, 4)), (2021, 1));
+        assert_eq!(iso_week(days_from_civil(2024, 12, 30)), (2025, 1));
+        assert_eq!(iso_week(days_from_civil(2010, 1, 3)), (2009, 53));
+        assert_eq!(iso_week(days_from_civil(2008, 12, 29)), (2009, 1));
+        assert_eq!(iso_week(days_from_civil(2026, 12, 31)), (2026, 53));
+        assert_eq!(iso_year_start(2025), days_from_civil(2024, 12, 30));
+    }
+
+    #[test]
+    fn local_days_with_offsets() {
+        let dec31 = days_from_civil(2024, 12, 31);
+        // 2024-12-31T20:00Z は JST（+09:00）では 2025-01-01
+        let ts = (dec31 * SECONDS_PER_DAY + 20 * 3_600) as u64;
+        assert_eq!(local_days(ts, 0), dec31);
+        assert_eq!(local_days(ts, 9 * 60), dec31 + 1);
+        assert_eq!(civil_from_days(local_days(ts, 9 * 60)), (2025, 1, 1));
+        assert_eq!(iso_week(local_days(ts, 9 * 60)), (2025, 1));
+        // 2025-01-01T05:00Z は UTC-10:00 では 2024-12-31
+        let ts = ((dec31 + 1) * SECONDS_PER_DAY + 5 * 3_600) as u64;
+        assert_eq!(local_days(ts, -10 * 60), dec31);
+        assert_eq!(local_days(ts, MAX_TZ_OFFSET_MINUTES), dec31 + 1);
+        assert_eq!(local_days(0, -60), -1);
+        assert_eq!(local_days(u64::MAX, 0), (u64::MAX / 86_400) as i64);
+    }
+
+    #[test]
+    fn utc_ranges_with_offsets() {
+        let day = days_from_civil(2025, 1, 1);
+        let start = (day * SECONDS_PER_DAY) as u64;
+        assert_eq!(
+            utc_range(day, day + 1, 9 * 60),
+            Some((start - 32_400, start + 86_400 - 32_400 - 1))
+        );
+        assert_eq!(
+            utc_range(day, day + 1, -5 * 60),
+            Some((start + 18_000, start + 86_400 + 18_000 - 1))
+        );
+        // 1970 年より前は切り詰め、全体が前なら None
+        assert_eq!(utc_range(0, 1, 60), Some((0, 86_400 - 3_600 - 1)));
+        assert_eq!(utc_range(-1, 0, -60), Some((0, 3_599)));
+        assert_eq!(utc_range(-2, -1, 0), None);
+    }
+}
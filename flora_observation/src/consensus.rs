@@ -0,0 +1,107 @@
+//! 検証（Verify）の集約: confidence で重み付けした合意 taxon と品質グレード
+
+use crate::state::{ConsensusParams, QualityGrade, StoredRecord, VerificationEntry};
+
+/// 検証者ごとの最新の見解を confidence で重み付けし、
+/// 首位の taxon が閾値以上かつ min_verifiers 人以上に支持されていればその taxon
+pub fn consensus_taxon(
+    verifications: &[VerificationEntry],
+    params: &ConsensusParams,
+) -> Option<String> {
+    // 検証者ごとの最新（後勝ち）
+    let mut latest: Vec<&VerificationEntry> = vec![];
+    for v in verifications {
+        match latest.iter_mut().find(|e| e.verifier == v.verifier) {
+            Some(e) => *e = v,
+            None => latest.push(v),
+        }
+    }
+
+    // taxon ごとの (重み合計, 支持者数)
+    let mut tally: Vec<(&str, u64, u32)> = vec![];
+    let mut total: u64 = 0;
+    for v in &latest {
+        let w = u64::from(v.confidence);
+        total += w;
+        match tally.iter_mut().find(|(t, _, _)| *t == v.taxon_id) {
+            Some(entry) => {
+                entry.1 += w;
+                entry.2 += 1;
+            }
+            None => tally.push((v.taxon_id.as_str(), w, 1)),
+        }
+    }
+    if total == 0 {
+        return None;
+    }
+
+    let (taxon, weight, supporters) = tally.into_iter().max_by_key(|(_, w, _)| *w)?;
+    if supporters < params.min_verifiers {
+        return None;
+    }
+    // weight / total >= threshold_pct / 100
+    if weight * 100 < total * u64::from(params.threshold_pct) {
+        return None;
+    }
+    Some(taxon.to_string())
+}
+
+/// species か位置が無ければ casual、合意があれば research_grade、それ以外は needs_id
+pub fn quality_grade(rec: &StoredRecord, consensus: Option<&str>) -> QualityGrade {
+    if rec.species.is_none() || rec.geohash_prefix.is_empty() {
+        QualityGrade::Casual
+    } else if consensus.is_some() {
+        QualityGrade::ResearchGrade
+    } else {
+        QualityGrade::NeedsId
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::Addr;
+
+    fn v(verifier: &str, taxon: &str, confidence: u8) -> VerificationEntry {
+        VerificationEntry {
+            at: 0,
+            verifier: Addr::unchecked(verifier),
+            taxon_id: taxon.to_string(),
+            confidence,
+        }
+    }
+
+    fn params(threshold_pct: u8, min_verifiers: u32) -> ConsensusParams {
+        ConsensusParams {
+            threshold_pct,
+            min_verifiers,
+        }
+    }
+
+    #[test]
+    fn weighted_majority() {
+        let p = params(67, 2);
+        // 160 / 220 = 72%
+        let vs = [v("a", "t1", 80), v("b", "t1", 80), v("c", "t2", 60)];
+        assert_eq!(consensus_taxon(&vs, &p).as_deref(), Some("t1"));
+        // 160 / 260 = 61%
+        let vs = [v("a", "t1", 80), v("b", "t1", 80), v("c", "t2", 100)];
+        assert_eq!(consensus_taxon(&vs, &p), None);
+        // 重みで勝っても支持者が足りない
+        let vs = [v("a", "t1", 100)];
+        assert_eq!(consensus_taxon(&vs, &p), None);
+        assert_eq!(consensus_taxon(&vs, &params(51, 1)).as_deref(), Some("t1"));
+        assert_eq!(consensus_taxon(&[], &p), None);
+        assert_eq!(consensus_taxon(&[v("a", "t1", 0)], &params(51, 1)), None);
+    }
+
+    #[test]
+    fn latest_opinion_per_verifier_wins() {
+        let p = params(67, 2);
+        let vs = [v("a", "t1", 90), v("b", "t2", 90), v("b", "t1", 70)];
+        assert_eq!(consensus_taxon(&vs, &p).as_deref(), Some("t1"));
+        // 同じ検証者の重複は 1 人として数える
+        let vs = [v("a", "t1", 90), v("a", "t1", 90)];
+        assert_eq!(consensus_taxon(&vs, &p), None);
+    }
+}
@@ -14,4 +14,7 @@ pub enum ContractError {
 
     #[error("Not found")]
     NotFound,
+
+    #[error("No pending admin")]
+    NoPendingAdmin,
 }
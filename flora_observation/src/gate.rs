@@ -0,0 +1,62 @@
+//! cw721 コレクションの保有による投稿制限（Store / StoreBatch）
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, QuerierWrapper, StdResult};
+use serde::Deserialize;
+
+/// Tokens 1 ページあたりの件数
+pub const TOKENS_PAGE: u32 = 30;
+
+/// cw721 の QueryMsg のうち使うものだけ
+#[cw_serde]
+enum Cw721QueryMsg {
+    OwnerOf {
+        token_id: String,
+        include_expired: Option<bool>,
+    },
+    Tokens {
+        owner: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+}
+
+// 応答は必要な項目だけ読む（approvals などは無視）
+#[derive(Deserialize)]
+struct OwnerOfResponse {
+    owner: String,
+}
+
+#[derive(Deserialize)]
+struct TokensResponse {
+    tokens: Vec<String>,
+}
+
+pub fn owner_of(querier: &QuerierWrapper, contract: &Addr, token_id: &str) -> StdResult<String> {
+    let res: OwnerOfResponse = querier.query_wasm_smart(
+        contract,
+        &Cw721QueryMsg::OwnerOf {
+            token_id: token_id.to_string(),
+            include_expired: None,
+        },
+    )?;
+    Ok(res.owner)
+}
+
+/// owner の保有トークン（token_id 順に 1 ページ分）
+pub fn tokens(
+    querier: &QuerierWrapper,
+    contract: &Addr,
+    owner: &Addr,
+    start_after: Option<String>,
+) -> StdResult<Vec<String>> {
+    let res: TokensResponse = querier.query_wasm_smart(
+        contract,
+        &Cw721QueryMsg::Tokens {
+            owner: owner.to_string(),
+            start_after,
+            limit: Some(TOKENS_PAGE),
+        },
+    )?;
+    Ok(res.tokens)
+}
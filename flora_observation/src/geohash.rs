@@ -0,0 +1,463 @@
+//! 標準 geohash（base32, 1〜12 文字）のエンコード / デコードと範囲検索用の補助
+
+pub const MIN_PRECISION: u8 = 1;
+pub const MAX_PRECISION: u8 = 12;
+
+/// 範囲検索で 1 回に展開するセル数の上限
+pub const MAX_COVER_CELLS: u64 = 64;
+
+/// 地球の平均半径（m）
+const EARTH_RADIUS_M: f64 = 6_371_008.8;
+
+const ALPHABET: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// セルの範囲（度）
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min_lat: f64,
+    pub min_lon: f64,
+    pub max_lat: f64,
+    pub max_lon: f64,
+}
+
+impl BoundingBox {
+    pub fn center(&self) -> (f64, f64) {
+        (
+            (self.min_lat + self.max_lat) / 2.0,
+            (self.min_lon + self.max_lon) / 2.0,
+        )
+    }
+}
+
+pub fn is_valid_precision(precision: u8) -> bool {
+    (MIN_PRECISION..=MAX_PRECISION).contains(&precision)
+}
+
+fn clamp(v: f64, lo: f64, hi: f64) -> f64 {
+    if v < lo {
+        lo
+    } else if v > hi {
+        hi
+    } else {
+        v
+    }
+}
+
+/// (lat, lon) を precision 文字の geohash に変換する。
+/// 範囲外の座標は clamp し、precision は 1..=12 に丸める。
+pub fn encode(lat: f64, lon: f64, precision: u8) -> String {
+    let precision = precision.clamp(MIN_PRECISION, MAX_PRECISION) as usize;
+    let lat = clamp(lat, -90.0, 90.0);
+    let lon = clamp(lon, -180.0, 180.0);
+
+    let (mut lat_lo, mut lat_hi) = (-90.0f64, 90.0f64);
+    let (mut lon_lo, mut lon_hi) = (-180.0f64, 180.0f64);
+
+    let mut out = String::with_capacity(precision);
+    // 偶数ビット = 経度, 奇数ビット = 緯度（経度から開始）
+    let mut even = true;
+    for _ in 0..precision {
+        let mut idx = 0usize;
+        for _ in 0..5 {
+            idx <<= 1;
+            if even {
+                let mid = (lon_lo + lon_hi) / 2.0;
+                if lon >= mid {
+                    idx |= 1;
+                    lon_lo = mid;
+                } else {
+                    lon_hi = mid;
+                }
+            } else {
+                let mid = (lat_lo + lat_hi) / 2.0;
+                if lat >= mid {
+                    idx |= 1;
+                    lat_lo = mid;
+                } else {
+                    lat_hi = mid;
+                }
+            }
+            even = !even;
+        }
+        out.push(ALPHABET[idx] as char);
+    }
+    out
+}
+
+fn char_index(c: u8) -> Option<usize> {
+    ALPHABET.iter().position(|&a| a == c)
+}
+
+/// geohash をセルの範囲に変換する。大文字は許容、不正文字・長さは None。
+pub fn decode_bbox(hash: &str) -> Option<BoundingBox> {
+    if hash.is_empty() || hash.len() > MAX_PRECISION as usize {
+        return None;
+    }
+    let (mut lat_lo, mut lat_hi) = (-90.0f64, 90.0f64);
+    let (mut lon_lo, mut lon_hi) = (-180.0f64, 180.0f64);
+
+    let mut even = true;
+    for c in hash.bytes() {
+        let idx = char_index(c.to_ascii_lowercase())?;
+        for bit in (0..5).rev() {
+            let on = (idx >> bit) & 1 == 1;
+            if even {
+                let mid = (lon_lo + lon_hi) / 2.0;
+                if on {
+                    lon_lo = mid;
+                } else {
+                    lon_hi = mid;
+                }
+            } else {
+                let mid = (lat_lo + lat_hi) / 2.0;
+                if on {
+                    lat_lo = mid;
+                } else {
+                    lat_hi = mid;
+                }
+            }
+            even = !even;
+        }
+    }
+    Some(BoundingBox {
+        min_lat: lat_lo,
+        min_lon: lon_lo,
+        max_lat: lat_hi,
+        max_lon: lon_hi,
+    })
+}
+
+/// 同じ桁数で保存された geohash のうち、prefix に前方一致するものの最小値と最大値。
+/// BY_GEOHASH のキーは全て同じ長さなので、この 2 つで連続範囲になる。
+pub fn prefix_range(prefix: &str, precision: u8) -> (String, String) {
+    let pad = (precision as usize).saturating_sub(prefix.len());
+    let lo = format!("{}{}", prefix, "0".repeat(pad));
+    let hi = format!("{}{}", prefix, "z".repeat(pad));
+    (lo, hi)
+}
+
+/// 緯度経度の範囲を覆う geohash セルの集合（昇順・重複なし）。
+/// 経度は min_lon > max_lon の場合に日付変更線をまたぐ範囲として扱う。
+/// セル数が MAX_COVER_CELLS 以下になる最も細かい桁数（max_precision 以下）を選び、
+/// 32 個の子が揃ったセルは親にまとめる。
+pub fn cover_bbox(bbox: &BoundingBox, max_precision: u8) -> Vec<String> {
+    let lon_ranges: Vec<(f64, f64)> = if bbox.min_lon > bbox.max_lon {
+        vec![(bbox.min_lon, 180.0), (-180.0, bbox.max_lon)]
+    } else {
+        vec![(bbox.min_lon, bbox.max_lon)]
+    };
+
+    let max_precision = max_precision.clamp(MIN_PRECISION, MAX_PRECISION);
+    let mut precision = MIN_PRECISION;
+    for p in (MIN_PRECISION..=max_precision).rev() {
+        let n: u64 = lon_ranges
+            .iter()
+            .map(|(lo, hi)| count_cells(bbox.min_lat, *lo, bbox.max_lat, *hi, p))
+            .sum();
+        if n <= MAX_COVER_CELLS {
+            precision = p;
+            break;
+        }
+    }
+
+    let mut cells: Vec<String> = vec![];
+    for (lo, hi) in lon_ranges {
+        let (rows, cols) = cell_span(bbox.min_lat, lo, bbox.max_lat, hi, precision);
+        let (h, w) = cell_size(precision);
+        for r in rows.0..=rows.1 {
+            for c in cols.0..=cols.1 {
+                let lat = -90.0 + (r as f64 + 0.5) * h;
+                let lon = -180.0 + (c as f64 + 0.5) * w;
+                cells.push(encode(lat, lon, precision));
+            }
+        }
+    }
+    cells.sort();
+    cells.dedup();
+    merge_complete_parents(cells)
+}
+
+fn bits(precision: u8) -> (u32, u32) {
+    let total = 5 * precision as u32;
+    let lat_bits = total / 2;
+    (lat_bits, total - lat_bits)
+}
+
+fn cell_size(precision: u8) -> (f64, f64) {
+    let (lat_bits, lon_bits) = bits(precision);
+    (
+        180.0 / (1u64 << lat_bits) as f64,
+        360.0 / (1u64 << lon_bits) as f64,
+    )
+}
+
+fn cell_index(v: f64, origin: f64, size: f64, bits: u32) -> u64 {
+    let max = (1u64 << bits) - 1;
+    let i = ((v - origin) / size).floor();
+    if i < 0.0 {
+        0
+    } else {
+        (i as u64).min(max)
+    }
+}
+
+fn cell_span(
+    min_lat: f64,
+    min_lon: f64,
+    max_lat: f64,
+    max_lon: f64,
+    precision: u8,
+) -> ((u64, u64), (u64, u64)) {
+    let (lat_bits, lon_bits) = bits(precision);
+    let (h, w) = cell_size(precision);
+    let rows = (
+        cell_index(min_lat, -90.0, h, lat_bits),
+        cell_index(max_lat, -90.0, h, lat_bits),
+    );
+    let cols = (
+        cell_index(min_lon, -180.0, w, lon_bits),
+        cell_index(max_lon, -180.0, w, lon_bits),
+    );
+    (rows, cols)
+}
+
+fn count_cells(min_lat: f64, min_lon: f64, max_lat: f64, max_lon: f64, precision: u8) -> u64 {
+    let (rows, cols) = cell_span(min_lat, min_lon, max_lat, max_lon, precision);
+    (rows.1 - rows.0 + 1) * (cols.1 - cols.0 + 1)
+}
+
+fn merge_complete_parents(mut cells: Vec<String>) -> Vec<String> {
+    loop {
+        let mut merged: Vec<String> = Vec::with_capacity(cells.len());
+        let mut changed = false;
+        let mut i = 0;
+        while i < cells.len() {
+            let cell = &cells[i];
+            if cell.len() > 1 {
+                let parent = &cell[..cell.len() - 1];
+                let mut j = i;
+                while j < cells.len()
+                    && cells[j].len() == cell.len()
+                    && cells[j].starts_with(parent)
+                {
+                    j += 1;
+                }
+                if j - i == ALPHABET.len() {
+                    merged.push(parent.to_string());
+                    changed = true;
+                    i = j;
+                    continue;
+                }
+            }
+            merged.push(cell.clone());
+            i += 1;
+        }
+        merged.sort();
+        cells = merged;
+        if !changed {
+            return cells;
+        }
+    }
+}
+
+/// 2 点間の大円距離（m, haversine）
+pub fn distance_m(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (p1, p2) = (lat1.to_radians(), lat2.to_radians());
+    let dp = (lat2 - lat1).to_radians();
+    let dl = (lon2 - lon1).to_radians();
+    let a = (dp / 2.0).sin().powi(2) + p1.cos() * p2.cos() * (dl / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_M * a.sqrt().min(1.0).asin()
+}
+
+/// 中心と半径（m）を含む bbox。極をまたぐ場合は経度方向を全周にする。
+pub fn bbox_around(lat: f64, lon: f64, radius_m: f64) -> BoundingBox {
+    let dlat = (radius_m / EARTH_RADIUS_M).to_degrees();
+    let min_lat = lat - dlat;
+    let max_lat = lat + dlat;
+    if min_lat <= -90.0 || max_lat >= 90.0 {
+        return BoundingBox {
+            min_lat: min_lat.max(-90.0),
+            min_lon: -180.0,
+            max_lat: max_lat.min(90.0),
+            max_lon: 180.0,
+        };
+    }
+    let dlon = (radius_m / (EARTH_RADIUS_M * lat.to_radians().cos())).to_degrees();
+    if dlon >= 180.0 {
+        return BoundingBox {
+            min_lat,
+            min_lon: -180.0,
+            max_lat,
+            max_lon: 180.0,
+        };
+    }
+    let wrap = |v: f64| {
+        if v < -180.0 {
+            v + 360.0
+        } else if v > 180.0 {
+            v - 360.0
+        } else {
+            v
+        }
+    };
+    BoundingBox {
+        min_lat,
+        min_lon: wrap(lon - dlon),
+        max_lat,
+        max_lon: wrap(lon + dlon),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn encode_reference_vectors() {
+        assert_eq!(encode(57.64911, 10.40744, 11), "u4pruydqqvj");
+        assert_eq!(encode(42.6, -5.6, 5), "ezs42");
+        assert_eq!(encode(-25.382708, -49.265506, 8), "6gkzwgjz");
+        assert_eq!(encode(0.0, 0.0, 1), "s");
+        // 範囲外は clamp、桁数は 1..=12 に丸める
+        assert_eq!(encode(91.0, 181.0, 3), encode(90.0, 180.0, 3));
+        assert_eq!(encode(42.6, -5.6, 0).len(), 1);
+        assert_eq!(encode(42.6, -5.6, 20).len(), 12);
+    }
+
+    #[test]
+    fn decode_bbox_reference_vectors() {
+        let b = decode_bbox("ezs42").unwrap();
+        assert!(close(b.min_lat, 42.5830078125));
+        assert!(close(b.max_lat, 42.626953125));
+        assert!(close(b.min_lon, -5.625));
+        assert!(close(b.max_lon, -5.5810546875));
+
+        let (lat, lon) = decode_bbox("u4pruydqqvj").unwrap().center();
+        assert!((lat - 57.64911).abs() < 1e-5);
+        assert!((lon - 10.40744).abs() < 1e-5);
+
+        let s = decode_bbox("s").unwrap();
+        assert_eq!(
+            s,
+            BoundingBox {
+                min_lat: 0.0,
+                min_lon: 0.0,
+                max_lat: 45.0,
+                max_lon: 45.0,
+            }
+        );
+        assert_eq!(decode_bbox("EZS42"), decode_bbox("ezs42"));
+        assert_eq!(decode_bbox(""), None);
+        assert_eq!(decode_bbox("ezs4a"), None);
+        assert_eq!(decode_bbox("0123456789bcd"), None);
+    }
+
+    #[test]
+    fn encode_lands_in_decoded_cell() {
+        for (lat, lon) in [(35.681, 139.767), (-33.8688, 151.2093), (64.1466, -21.9426)] {
+            for p in MIN_PRECISION..=MAX_PRECISION {
+                let b = decode_bbox(&encode(lat, lon, p)).unwrap();
+                assert!(b.min_lat <= lat && lat <= b.max_lat);
+                assert!(b.min_lon <= lon && lon <= b.max_lon);
+            }
+        }
+    }
+
+    #[test]
+    fn cover_bbox_across_antimeridian() {
+        let bbox = BoundingBox {
+            min_lat: -0.5,
+            min_lon: 179.5,
+            max_lat: 0.5,
+            max_lon: -179.5,
+        };
+        let cells = cover_bbox(&bbox, 6);
+        assert!(!cells.is_empty());
+        assert!(cells.len() as u64 <= MAX_COVER_CELLS);
+        let mut sorted = cells.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(cells, sorted);
+        // 両側の端の点を覆い、0 度付近のセルは含まない
+        for (lat, lon) in [(0.0, 179.9), (0.0, -179.9), (0.4, 179.6), (-0.4, -179.6)] {
+            let hash = encode(lat, lon, 6);
+            assert!(cells.iter().any(|c| hash.starts_with(c.as_str())));
+        }
+        for c in &cells {
+            let b = decode_bbox(c).unwrap();
+            assert!(b.min_lon >= 179.0 || b.max_lon <= -179.0, "{}", c);
+        }
+    }
+
+    #[test]
+    fn cover_bbox_merges_complete_parents() {
+        // "s" の内側いっぱい: 2 桁の子 32 個が揃うので "s" にまとまる
+        let bbox = BoundingBox {
+            min_lat: 0.1,
+            min_lon: 0.1,
+            max_lat: 44.9,
+            max_lon: 44.9,
+        };
+        assert_eq!(cover_bbox(&bbox, 3), vec!["s".to_string()]);
+
+        let mut cells: Vec<String> = ALPHABET
+            .iter()
+            .map(|c| format!("xn{}", *c as char))
+            .collect();
+        cells.push("xp0".into());
+        assert_eq!(
+            merge_complete_parents(cells),
+            vec!["xn".to_string(), "xp0".to_string()]
+        );
+
+        // 子が全て揃えば 2 段まとめて親になる
+        let all: Vec<String> = ALPHABET
+            .iter()
+            .flat_map(|a| {
+                ALPHABET
+                    .iter()
+                    .map(move |b| format!("x{}{}", *a as char, *b as char))
+            })
+            .collect();
+        assert_eq!(merge_complete_parents(all), vec!["x".to_string()]);
+
+        // 1 つ欠けていればまとめない
+        let partial: Vec<String> = ALPHABET[1..]
+            .iter()
+            .map(|c| format!("xn{}", *c as char))
+            .collect();
+        assert_eq!(merge_complete_parents(partial.clone()), partial);
+    }
+
+    #[test]
+    fn prefix_range_pads_to_precision() {
+        assert_eq!(
+            prefix_range("xn7", 6),
+            ("xn7000".to_string(), "xn7zzz".to_string())
+        );
+        assert_eq!(
+            prefix_range("xn76ur", 6),
+            ("xn76ur".to_string(), "xn76ur".to_string())
+        );
+    }
+
+    #[test]
+    fn distance_and_bbox_around() {
+        // 東京駅〜大阪駅 は約 403 km
+        let d = distance_m(35.681, 139.767, 34.702, 135.495);
+        assert!((d - 403_000.0).abs() < 3_000.0, "{}", d);
+        assert!(distance_m(0.0, 179.99, 0.0, -179.99) < 2_300.0);
+
+        // 日付変更線の近くは経度が折り返す
+        let b = bbox_around(0.0, 179.99, 5_000.0);
+        assert!(b.min_lon > b.max_lon);
+        assert!(b.min_lon > 179.9 && b.max_lon < -179.9);
+        // 極をまたぐと経度は全周
+        let b = bbox_around(89.99, 10.0, 5_000.0);
+        assert_eq!((b.min_lon, b.max_lon, b.max_lat), (-180.0, 180.0, 90.0));
+    }
+}
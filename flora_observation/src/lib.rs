@@ -2,42 +2,122 @@
 use cosmwasm_std::entry_point;
 
 use cosmwasm_std::{
-    to_json_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Order, Response, StdResult,
+    coin, to_json_binary, Addr, BankMsg, Binary, Coin, Deps, DepsMut, Env, MessageInfo, Order,
+    Reply, Response, StdError, StdResult, Storage, SubMsg, SubMsgResult, Uint128, WasmMsg,
 };
-use cw_storage_plus::Bound;
+use cw2::{get_contract_version, set_contract_version, ContractVersion};
+use cw_storage_plus::{Bound, Map, Path, PrimaryKey};
+use semver::Version;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 
+mod calendar;
+mod consensus;
 mod error;
+mod gate;
+mod geohash;
 mod msg;
+mod schema;
 mod state;
+#[cfg(test)]
+mod tests;
 
 use crate::error::ContractError;
 use crate::msg::{
-    CountResp, ExecuteMsg, GetResp, InstantiateMsg, ListResp, QueryMsg, StatsMonthlyResp,
+    AnnotationItem, AnnotationsResp, BatchItemResult, BatchMode, ChangesResp, ConfigUpdate,
+    CountResp, DecodeGeohashResp, DepositConfigMsg, DepositsResp, ExecuteMsg, GateConfigMsg,
+    GetResp, HookExecuteMsg, HooksResp, InstantiateMsg, ListResp, MigrateMsg, MigrationStatusResp,
+    MinterExecuteMsg, ModerationHistoryResp, NftMinterMsg, ObservationHookMsg, PayloadSchemaResp,
+    PendingAdminResp, PhenologySummaryResp, PhenologyYear, QueryMsg, QuotaUsageResp, RecordCursor,
+    RecordHistoryResp, ReportsResp, RoleEntry, RoleMember, RoleMembersResp, RolesResp, SortOrder,
+    StatsDailyResp, StatsMonthlyResp, StatsWeeklyResp, StoreBatchResp, StoreItem, TagCount,
+    TagCountsResp, TombstonesResp, VerificationHistoryResp, VerificationItem, VerificationsByResp,
+    VerificationsResp, VerifierOpinion,
 };
 use crate::state::{
-    normalize_species, Annotation, StoredRecord, VerificationEntry, ADMIN, BY_GEOHASH, BY_SPECIES,
-    BY_TIME, NEXT_ID, RECORDS, VERIFIERS,
+    adjust_counters, index_record, normalize_phenophase, normalize_species, normalize_tag,
+    tag_record, unindex_record, untag_record, Annotation, AuditEntry, Change, ChangeKind, Config,
+    ConsensusParams, Deposit, DepositConfig, EditInfo, GateConfig, GateMode, MigrationState,
+    MigrationStep, ModerationAction, NftMinterConfig, PastVerification, PayloadSchema, PendingAdmin,
+    QualityGrade, RecordRevision, Report, ReportCategory, ReportStatus, Role, RoleGrant, SpamPolicy,
+    StoreQuota, StoredRecord, Tombstone, TombstoneReason, VerificationEnd, VerificationEntry,
+    ADMIN_CAN_EDIT, ANNOTATIONS, AUDIT_BY_RECORD, AUDIT_LOG, BY_GEOHASH, BY_GEOHASH_TIME,
+    BY_GRADE_TIME, BY_PHENOPHASE_TIME, BY_SENDER_TIME, BY_SPECIES_TIME, BY_TAG, BY_TIME,
+    BY_VERIFIER, CHANGES, CNT_GEOHASH, CNT_MONTH, CNT_SPECIES, CNT_SPECIES_MONTH, CNT_TAG,
+    CNT_TOTAL, CONFIG, CONSENSUS_PARAMS, DEPOSITS, DEPOSITS_BY_OWNER, GATE, HOOKS, LEGACY_ADMIN,
+    LEGACY_BY_SPECIES, LEGACY_GEOHASH_PRECISION, LEGACY_MAX_BATCH_SIZE, LEGACY_MODERATORS,
+    LEGACY_RECORDS_RAW, LEGACY_VERIFIERS, MIGRATION, NEXT_ANNOTATION_SEQ, NEXT_AUDIT_SEQ,
+    NEXT_CHANGE_SEQ, NEXT_ID, NEXT_REPORT_ID, NEXT_VERIFICATION_SEQ, NFT_MINTER, OPEN_REPORTS,
+    OPEN_REPORT_BY_REPORTER, PAYLOAD_SCHEMA, PENDING_ADMIN, PENDING_MINTS, QUOTA_LOG, RECORDS,
+    RECORD_BY_TOKEN, RECORD_HISTORY, RECORD_TAGS, REPORTS, REPORTS_BY_RECORD, ROLES, SPAM_POLICY,
+    TOMBSTONES, USED_GATE_TOKENS, VERIFICATIONS, VERIFICATION_HISTORY,
 };
 
-const MAX_LIMIT: u32 = 5_000;
+// cw2
+const CONTRACT_NAME: &str = "crates.io:flora-observation";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Config の既定値
+const DEFAULT_MAX_LIMIT: u32 = 5_000;
 const DEFAULT_LIMIT: u32 = 100;
 const DEFAULT_GEOHASH_PRECISION: u8 = 6;
+const DEFAULT_CID_MIN_LEN: u32 = 20;
+const DEFAULT_CID_MAX_LEN: u32 = 200;
+const DEFAULT_MAX_PAYLOAD_BYTES: u32 = 16_384;
+/// Purge 1 回あたりの上限
+const MAX_PURGE_IDS: usize = 100;
+/// 観察記録 NFT の token_id の既定の接頭辞
+const DEFAULT_TOKEN_ID_PREFIX: &str = "obs-";
+/// 観察記録 NFT の発行（SubMsg）の reply id
+const REPLY_MINT_RECORD_NFT: u64 = 1;
+/// 通知（SubMsg）の失敗時の reply id
+const REPLY_HOOK_FAILED: u64 = 2;
+/// 登録できる通知先の数
+const MAX_HOOKS: usize = 10;
+/// 通知 1 件あたりの gas 上限（通知先が gas を使い切って本体の tx を失敗させないように）
+const HOOK_GAS_LIMIT: u64 = 300_000;
+/// StoreBatch の件数上限の既定値と、設定できる最大値
+const DEFAULT_MAX_BATCH_SIZE: u32 = 50;
+const MAX_BATCH_SIZE_LIMIT: u32 = 500;
+/// Verify の confidence の上限
+const MAX_CONFIDENCE: u8 = 100;
+/// タグ 1 つの最大長（バイト）
+const MAX_TAG_LEN: usize = 64;
+/// 注記 1 件あたりのタグ数の上限
+const MAX_TAGS_PER_ANNOTATION: usize = 16;
+/// PhenologySummary で一度に集計できる年数
+const MAX_SUMMARY_YEARS: usize = 50;
+/// デポジットのチャレンジ期間の上限（約 10 年）
+const MAX_CHALLENGE_PERIOD_SECS: u64 = 10 * 365 * 24 * 60 * 60;
 
 /* ===========================
  * role helpers
  * =========================== */
 
-fn ensure_admin(deps: &DepsMut, sender: &Addr) -> Result<(), ContractError> {
-    let admin = ADMIN.load(deps.storage)?;
-    if &admin != sender {
+fn has_role(store: &dyn Storage, env: &Env, addr: &Addr, role: Role) -> StdResult<bool> {
+    Ok(ROLES
+        .may_load(store, (role.key(), addr))?
+        .is_some_and(|g| g.is_active(env.block.time.seconds())))
+}
+
+fn ensure_admin(deps: &DepsMut, env: &Env, sender: &Addr) -> Result<(), ContractError> {
+    if !has_role(deps.storage, env, sender, Role::Admin)? {
+        return Err(ContractError::Unauthorized);
+    }
+    Ok(())
+}
+
+fn ensure_moderator(deps: &DepsMut, env: &Env, sender: &Addr) -> Result<(), ContractError> {
+    if !has_role(deps.storage, env, sender, Role::Moderator)?
+        && !has_role(deps.storage, env, sender, Role::Admin)?
+    {
         return Err(ContractError::Unauthorized);
     }
     Ok(())
 }
 
-fn ensure_verifier(deps: &DepsMut, sender: &Addr) -> Result<(), ContractError> {
-    let ok = VERIFIERS.may_load(deps.storage, sender)?.unwrap_or(false);
-    if !ok {
+fn ensure_verifier(deps: &DepsMut, env: &Env, sender: &Addr) -> Result<(), ContractError> {
+    if !has_role(deps.storage, env, sender, Role::Verifier)? {
         return Err(ContractError::Unauthorized);
     }
     Ok(())
@@ -79,60 +159,87 @@ fn extract_species(payload: &serde_json::Value) -> Option<String> {
     None
 }
 
-fn extract_geohash_prefix(payload: &serde_json::Value, precision: u8) -> String {
-    let obj = match payload.as_object() {
-        Some(o) => o,
-        None => return String::new(),
-    };
-    let place = match obj.get("place").and_then(|v| v.as_object()) {
-        Some(p) => p,
-        None => return String::new(),
-    };
-    let lat = match place.get("lat").and_then(|x| x.as_f64()) {
-        Some(v) => v,
-        None => return String::new(),
+/// "phenophase": "flowering" または { "name": "flowering" }
+fn extract_phenophase(payload: &serde_json::Value) -> Option<String> {
+    let v = payload.as_object()?.get("phenophase")?;
+    let txt = match v.as_str() {
+        Some(txt) => txt,
+        None => v.as_object()?.get("name")?.as_str()?,
     };
-    let lon = match place.get("lon").and_then(|x| x.as_f64()) {
-        Some(v) => v,
-        None => return String::new(),
-    };
-    geohash_prefix(lat, lon, precision)
+    let ph = normalize_phenophase(txt);
+    if ph.is_empty() {
+        None
+    } else {
+        Some(ph)
+    }
 }
 
-fn geohash_prefix(lat: f64, lon: f64, precision: u8) -> String {
-    fn clamp(v: f64, lo: f64, hi: f64) -> f64 {
-        if v < lo {
-            lo
-        } else if v > hi {
-            hi
-        } else {
-            v
-        }
+/// 座標値は数値と 10 進文字列（"35.681"）の両方を受け付ける。
+/// コントラクトの JSON パーサは小数を受け付けないため、実際には文字列で届く。
+fn coord_from_json(v: &serde_json::Value) -> Option<f64> {
+    match v {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(s) => parse_decimal(s),
+        _ => None,
     }
-    let lat = clamp(lat, -90.0, 90.0);
-    let lon = clamp(lon, -180.0, 180.0);
+}
+
+fn parse_decimal(s: &str) -> Option<f64> {
+    s.trim().parse::<f64>().ok().filter(|f| f.is_finite())
+}
 
-    let lat_q = (((lat + 90.0) / 180.0) * ((1u32 << 15) as f64 - 1.0)).round() as u32;
-    let lon_q = (((lon + 180.0) / 360.0) * ((1u32 << 15) as f64 - 1.0)).round() as u32;
+fn extract_lat_lon(payload: &serde_json::Value) -> Option<(f64, f64)> {
+    let place = payload.as_object()?.get("place")?.as_object()?;
+    let lat = place.get("lat").and_then(coord_from_json)?;
+    let lon = place.get("lon").and_then(coord_from_json)?;
+    Some((lat, lon))
+}
+
+fn extract_geohash_prefix(payload: &serde_json::Value, precision: u8) -> String {
+    match extract_lat_lon(payload) {
+        Some((lat, lon)) => geohash::encode(lat, lon, precision),
+        None => String::new(),
+    }
+}
 
-    fn part1by1(mut n: u32) -> u32 {
-        n = (n | (n << 8)) & 0x00FF00FF;
-        n = (n | (n << 4)) & 0x0F0F0F0F;
-        n = (n | (n << 2)) & 0x33333333;
-        n = (n | (n << 1)) & 0x55555555;
-        n
+fn validate_geohash_precision(precision: u8) -> Result<u8, ContractError> {
+    if !geohash::is_valid_precision(precision) {
+        return Err(ContractError::BadRequest {
+            msg: format!(
+                "geohash_precision must be {}..={}",
+                geohash::MIN_PRECISION,
+                geohash::MAX_PRECISION
+            ),
+        });
     }
-    let morton = (part1by1(lon_q) << 1) | part1by1(lat_q);
+    Ok(precision)
+}
 
-    const ALPHABET: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
-    let bits = morton as u64;
-    let total_bits = 5 * (precision as usize);
-    let mut out = String::with_capacity(precision as usize);
-    for i in (0..total_bits).step_by(5).rev() {
-        let idx = ((bits >> i) & 0x1F) as usize;
-        out.push(ALPHABET[idx] as char);
+fn validate_config(cfg: &Config) -> Result<(), ContractError> {
+    validate_geohash_precision(cfg.geohash_precision)?;
+    validate_max_batch_size(cfg.max_batch_size)?;
+    if cfg.default_limit == 0 || cfg.default_limit > cfg.max_limit {
+        return Err(ContractError::BadRequest {
+            msg: "default_limit must be 1..=max_limit".into(),
+        });
+    }
+    if cfg.cid_min_len == 0 || cfg.cid_min_len > cfg.cid_max_len {
+        return Err(ContractError::BadRequest {
+            msg: "cid_min_len must be 1..=cid_max_len".into(),
+        });
+    }
+    if cfg.max_payload_bytes == 0 {
+        return Err(ContractError::BadRequest {
+            msg: "max_payload_bytes must be positive".into(),
+        });
     }
-    out
+    Ok(())
+}
+
+/// 一覧系の limit（省略時は default_limit、max_limit で頭打ち）
+fn page_limit(store: &dyn Storage, limit: Option<u32>) -> StdResult<usize> {
+    let cfg = CONFIG.load(store)?;
+    Ok(limit.unwrap_or(cfg.default_limit).min(cfg.max_limit) as usize)
 }
 
 /* ===========================
@@ -142,10 +249,11 @@ fn geohash_prefix(lat: f64, lon: f64, precision: u8) -> String {
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     let start_id = msg.start_id.unwrap_or(1);
     NEXT_ID.save(deps.storage, &start_id)?;
 
@@ -153,19 +261,50 @@ pub fn instantiate(
         Some(a) => deps.api.addr_validate(&a)?,
         None => info.sender.clone(),
     };
-    ADMIN.save(deps.storage, &admin)?;
+    let grant = RoleGrant {
+        granted_by: info.sender.clone(),
+        granted_at: env.block.time.seconds(),
+        expires_at: None,
+    };
+    ROLES.save(deps.storage, (Role::Admin.key(), &admin), &grant)?;
+    ADMIN_CAN_EDIT.save(deps.storage, &msg.admin_can_edit.unwrap_or(false))?;
+    if let Some(g) = msg.gate {
+        let g = validate_gate(deps.as_ref(), g)?;
+        GATE.save(deps.storage, &g)?;
+    }
+    if let Some(m) = msg.nft_minter {
+        let m = validate_nft_minter(deps.as_ref(), m)?;
+        NFT_MINTER.save(deps.storage, &m)?;
+    }
+    let consensus = msg.consensus.unwrap_or_default();
+    validate_consensus_params(&consensus)?;
+    CONSENSUS_PARAMS.save(deps.storage, &consensus)?;
+
+    let config = Config {
+        max_limit: msg.max_limit.unwrap_or(DEFAULT_MAX_LIMIT),
+        default_limit: msg.default_limit.unwrap_or(DEFAULT_LIMIT),
+        geohash_precision: msg.geohash_precision.unwrap_or(DEFAULT_GEOHASH_PRECISION),
+        cid_min_len: msg.cid_min_len.unwrap_or(DEFAULT_CID_MIN_LEN),
+        cid_max_len: msg.cid_max_len.unwrap_or(DEFAULT_CID_MAX_LEN),
+        paused: false,
+        max_payload_bytes: msg.max_payload_bytes.unwrap_or(DEFAULT_MAX_PAYLOAD_BYTES),
+        max_batch_size: msg.max_batch_size.unwrap_or(DEFAULT_MAX_BATCH_SIZE),
+    };
+    validate_config(&config)?;
+    CONFIG.save(deps.storage, &config)?;
 
     if let Some(vs) = msg.verifiers {
         for v in vs {
             let addr = deps.api.addr_validate(&v)?;
-            VERIFIERS.save(deps.storage, &addr, &true)?;
+            ROLES.save(deps.storage, (Role::Verifier.key(), &addr), &grant)?;
         }
     }
 
     Ok(Response::new()
         .add_attribute("action", "instantiate")
         .add_attribute("next_id", start_id.to_string())
-        .add_attribute("admin", admin))
+        .add_attribute("admin", admin)
+        .add_attribute("geohash_precision", config.geohash_precision.to_string()))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -175,25 +314,90 @@ pub fn execute(
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
+    // 移行の途中はレコードと索引が揃っていないので、移行を進める以外は受け付けない
+    if MIGRATION.exists(deps.storage) && !matches!(msg, ExecuteMsg::ContinueMigration { .. }) {
+        return Err(ContractError::BadRequest {
+            msg: "migration in progress".into(),
+        });
+    }
     match msg {
-        ExecuteMsg::Store { payload, cid } => exec_store(deps, env, info, payload, cid),
+        ExecuteMsg::Store {
+            payload,
+            cid,
+            gate_token_id,
+        } => exec_store(
+            deps,
+            env,
+            info,
+            StoreItem {
+                payload,
+                cid,
+                gate_token_id,
+            },
+        ),
+        ExecuteMsg::StoreBatch { items, mode } => exec_store_batch(deps, env, info, items, mode),
+        ExecuteMsg::UpdateRecord { id, payload, cid } => {
+            exec_update_record(deps, env, info, id, payload, cid)
+        }
+        ExecuteMsg::Withdraw { id } => exec_withdraw(deps, env, info, id),
+        ExecuteMsg::Purge { ids } => exec_purge(deps, env, info, ids),
         ExecuteMsg::AppendAnnotation {
             id,
             note,
             photo_cid,
             tags,
         } => exec_append_annotation(deps, env, info, id, note, photo_cid, tags),
+        ExecuteMsg::RemoveTag { id, seq, tag } => exec_remove_tag(deps, env, info, id, seq, tag),
         ExecuteMsg::Verify {
             id,
             taxon_id,
             confidence,
         } => exec_verify(deps, env, info, id, taxon_id, confidence),
-        ExecuteMsg::Hide { id, reason } => exec_hide(deps, env, info, id, reason),
-        ExecuteMsg::SetVerifier { addr, enabled } => exec_set_verifier(deps, info, addr, enabled),
+        ExecuteMsg::RetractVerification { id } => exec_retract_verification(deps, env, info, id),
+        ExecuteMsg::Report { id, category, note } => {
+            exec_report(deps, env, info, id, category, note)
+        }
+        ExecuteMsg::Hide { id, reason, spam } => {
+            exec_hide(deps, env, info, id, reason, spam.unwrap_or(false))
+        }
+        ExecuteMsg::Unhide { id, reason } => exec_unhide(deps, env, info, id, reason),
+        ExecuteMsg::DismissReport { report_id, reason } => {
+            exec_dismiss_report(deps, env, info, report_id, reason)
+        }
+        ExecuteMsg::GrantRole {
+            role,
+            addr,
+            expires_at,
+        } => exec_grant_role(deps, env, info, role, addr, expires_at),
+        ExecuteMsg::RevokeRole { role, addr } => exec_revoke_role(deps, env, info, role, addr),
+        ExecuteMsg::ProposeAdmin { new_admin } => exec_propose_admin(deps, env, info, new_admin),
+        ExecuteMsg::AcceptAdmin {} => exec_accept_admin(deps, env, info),
+        ExecuteMsg::SetSpamPolicy { quota, deposit } => {
+            exec_set_spam_policy(deps, env, info, quota, deposit)
+        }
+        ExecuteMsg::ClaimDeposits { owner, limit } => {
+            exec_claim_deposits(deps, env, info, owner, limit)
+        }
+        ExecuteMsg::AddHook { addr } => exec_add_hook(deps, env, info, addr),
+        ExecuteMsg::RemoveHook { addr } => exec_remove_hook(deps, env, info, addr),
+        ExecuteMsg::SetNftMinter { minter } => exec_set_nft_minter(deps, env, info, minter),
+        ExecuteMsg::SetGate { gate } => exec_set_gate(deps, env, info, gate),
+        ExecuteMsg::UpdateConfig { config } => exec_update_config(deps, env, info, config),
+        ExecuteMsg::SetAdminCanEdit { enabled } => {
+            exec_set_admin_can_edit(deps, env, info, enabled)
+        }
+        ExecuteMsg::SetConsensusParams { params } => {
+            exec_set_consensus_params(deps, env, info, params)
+        }
+        ExecuteMsg::RecomputeGrades { start_after, limit } => {
+            exec_recompute_grades(deps, env, info, start_after, limit)
+        }
+        ExecuteMsg::SetPayloadSchema { schema } => exec_set_payload_schema(deps, env, info, schema),
+        ExecuteMsg::ContinueMigration { limit } => exec_continue_migration(deps, env, info, limit),
     }
 }
 
-fn normalize_cid(input: &str) -> Result<String, ContractError> {
+fn normalize_cid(cfg: &Config, input: &str) -> Result<String, ContractError> {
     let trimmed = input.trim();
     if trimmed.is_empty() {
         return Err(ContractError::BadRequest {
@@ -204,7 +408,7 @@ fn normalize_cid(input: &str) -> Result<String, ContractError> {
     let cid = trimmed.strip_prefix("ipfs://").unwrap_or(trimmed).to_string();
 
     // 軽量チェック（長さ・文字クラス）
-    if cid.len() < 20 || cid.len() > 200 {
+    if cid.len() < cfg.cid_min_len as usize || cid.len() > cfg.cid_max_len as usize {
         return Err(ContractError::BadRequest {
             msg: "cid length seems invalid".into(),
         });
@@ -220,461 +424,3737 @@ fn normalize_cid(input: &str) -> Result<String, ContractError> {
     Ok(cid)
 }
 
+fn ensure_not_paused(cfg: &Config) -> Result<(), ContractError> {
+    if cfg.paused {
+        return Err(ContractError::BadRequest {
+            msg: "submissions are paused".into(),
+        });
+    }
+    Ok(())
+}
+
 fn exec_store(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    payload: serde_json::Value,
-    cid_input: String,
+    item: StoreItem,
 ) -> Result<Response, ContractError> {
-    let observed_at = extract_observed_at(&payload)?;
-    let species_opt = extract_species(&payload).map(|s| normalize_species(&s));
-    let geohash = extract_geohash_prefix(&payload, DEFAULT_GEOHASH_PRECISION);
-    let cid = normalize_cid(&cid_input)?; // 必須・正規化
-
-    let mut id = NEXT_ID.load(deps.storage)?;
-
-    let rec = StoredRecord {
-        id,
-        sender: info.sender.clone(),
-        observed_at,
-        species: species_opt.clone(),
-        geohash_prefix: geohash.clone(),
-        cid: cid.clone(),
-        payload: payload.clone(),
-        block_time: env.block.time.seconds(),
-        block_height: env.block.height,
-        hidden: false,
-        hidden_reason: None,
-        annotations: vec![],
-        verifications: vec![],
-    };
-
-    RECORDS.save(deps.storage, id, &rec)?;
-    BY_TIME.save(deps.storage, (observed_at, id), &())?;
-    if let Some(sp) = species_opt {
-        BY_SPECIES.save(deps.storage, (sp, id), &())?;
-    }
-    if !geohash.is_empty() {
-        BY_GEOHASH.save(deps.storage, (geohash, id), &())?;
+    let cfg = CONFIG.load(deps.storage)?;
+    ensure_not_paused(&cfg)?;
+    let admission = admission(deps.as_ref(), &env, &info.sender)?;
+    if admission.remaining == Some(0) {
+        return Err(quota_exceeded(deps.as_ref())?);
     }
+    check_deposit_funds(&info, admission.deposit.as_ref(), 1)?;
 
-    id += 1;
-    NEXT_ID.save(deps.storage, &id)?;
+    let id = NEXT_ID.load(deps.storage)?;
+    let rec = new_record(deps.as_ref(), &env, &cfg, &info.sender, id, item, &[])?;
+    insert_record(deps.storage, &rec)?;
+    record_submission(deps.storage, &env, &rec, admission.deposit.as_ref())?;
+    let mint = mint_record_nft(deps.storage, &rec)?;
+    let hooks = hook_msgs(deps.storage, stored_hook(&rec))?;
 
-    Ok(Response::new()
+    let mut res = Response::new()
+        .add_submessages(mint)
+        .add_submessages(hooks)
         .add_attribute("action", "store")
-        .add_attribute("id", (id - 1).to_string())
+        .add_attribute("id", id.to_string())
         .add_attribute("sender", info.sender)
-        .add_attribute("cid", cid))
+        .add_attribute("cid", rec.cid);
+    if let Some(t) = rec.gate_token_id {
+        res = res.add_attribute("gate_token_id", t);
+    }
+    Ok(res)
 }
 
-fn exec_append_annotation(
+fn exec_store_batch(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    id: u64,
-    note: Option<String>,
-    photo_cid: Option<String>,
-    tags: Option<Vec<String>>,
+    items: Vec<StoreItem>,
+    mode: BatchMode,
 ) -> Result<Response, ContractError> {
-    if note.as_ref().map(|s| s.is_empty()).unwrap_or(false) {
+    let cfg = CONFIG.load(deps.storage)?;
+    ensure_not_paused(&cfg)?;
+    if items.is_empty() || items.len() > cfg.max_batch_size as usize {
         return Err(ContractError::BadRequest {
-            msg: "note must not be empty".into(),
+            msg: format!("items must contain 1..={} entries", cfg.max_batch_size),
         });
     }
-    if note.is_none() && photo_cid.is_none() && tags.as_ref().map(|t| t.is_empty()).unwrap_or(true)
-    {
-        return Err(ContractError::BadRequest {
-            msg: "at least one of note/photo_cid/tags is required".into(),
-        });
+
+    let admission = admission(deps.as_ref(), &env, &info.sender)?;
+    check_deposit_funds(&info, admission.deposit.as_ref(), items.len() as u128)?;
+    let mut remaining = admission.remaining.unwrap_or(u32::MAX);
+
+    let first = NEXT_ID.load(deps.storage)?;
+    let mut next = first;
+    let mut results: Vec<BatchItemResult> = Vec::with_capacity(items.len());
+    let mut submsgs: Vec<SubMsg> = vec![];
+    match mode {
+        BatchMode::AllOrNothing => {
+            if (remaining as usize) < items.len() {
+                return Err(quota_exceeded(deps.as_ref())?);
+            }
+            // 先に全件を検証してから書き込む
+            let mut recs = Vec::with_capacity(items.len());
+            let mut taken: Vec<String> = vec![];
+            for (i, item) in items.into_iter().enumerate() {
+                let rec = new_record(deps.as_ref(), &env, &cfg, &info.sender, next, item, &taken)
+                    .map_err(|e| ContractError::BadRequest {
+                    msg: format!("item {}: {}", i, e),
+                })?;
+                taken.extend(rec.gate_token_id.clone());
+                recs.push(rec);
+                next += 1;
+            }
+            for (i, rec) in recs.iter().enumerate() {
+                insert_record(deps.storage, rec)?;
+                record_submission(deps.storage, &env, rec, admission.deposit.as_ref())?;
+                submsgs.extend(mint_record_nft(deps.storage, rec)?);
+                submsgs.extend(hook_msgs(deps.storage, stored_hook(rec))?);
+                results.push(BatchItemResult {
+                    index: i as u32,
+                    id: Some(rec.id),
+                    error: None,
+                });
+            }
+        }
+        BatchMode::BestEffort => {
+            for (i, item) in items.into_iter().enumerate() {
+                if remaining == 0 {
+                    results.push(BatchItemResult {
+                        index: i as u32,
+                        id: None,
+                        error: Some(quota_exceeded(deps.as_ref())?.to_string()),
+                    });
+                    continue;
+                }
+                // new_record は書き込みをしないので、失敗しても状態は変わらない
+                match new_record(deps.as_ref(), &env, &cfg, &info.sender, next, item, &[]) {
+                    Ok(rec) => {
+                        insert_record(deps.storage, &rec)?;
+                        record_submission(deps.storage, &env, &rec, admission.deposit.as_ref())?;
+                        submsgs.extend(mint_record_nft(deps.storage, &rec)?);
+                        submsgs.extend(hook_msgs(deps.storage, stored_hook(&rec))?);
+                        remaining -= 1;
+                        results.push(BatchItemResult {
+                            index: i as u32,
+                            id: Some(rec.id),
+                            error: None,
+                        });
+                        next += 1;
+                    }
+                    Err(e) => results.push(BatchItemResult {
+                        index: i as u32,
+                        id: None,
+                        error: Some(e.to_string()),
+                    }),
+                }
+            }
+        }
     }
 
-    RECORDS.update(deps.storage, id, |maybe| -> Result<_, ContractError> {
-        let mut rec = maybe.ok_or(ContractError::NotFound)?;
-        rec.annotations.push(Annotation {
-            at: env.block.time.seconds(),
-            by: info.sender.clone(),
-            note,
-            photo_cid,
-            tags,
+    let stored = next - first;
+    let (first_id, last_id) = if stored > 0 {
+        (Some(first), Some(next - 1))
+    } else {
+        (None, None)
+    };
+    let mut res = Response::new()
+        .add_submessages(submsgs)
+        .add_attribute("action", "store_batch")
+        .add_attribute("sender", info.sender.clone())
+        .add_attribute("stored", stored.to_string())
+        .add_attribute("failed", (results.len() as u64 - stored).to_string());
+    if let (Some(a), Some(b)) = (first_id, last_id) {
+        res = res
+            .add_attribute("first_id", a.to_string())
+            .add_attribute("last_id", b.to_string());
+    }
+    for r in results.iter().filter(|r| r.error.is_some()) {
+        res = res.add_attribute(
+            format!("error.{}", r.index),
+            r.error.clone().unwrap_or_default(),
+        );
+    }
+    // 保存できなかった項目の分のデポジットは返す
+    let failed = results.len() as u128 - u128::from(stored);
+    if let (Some(d), true) = (&admission.deposit, failed > 0) {
+        res = res.add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: vec![deposit_total(d, failed)?],
         });
-        Ok(rec)
-    })?;
+    }
+    Ok(res.set_data(to_json_binary(&StoreBatchResp {
+        results,
+        first_id,
+        last_id,
+    })?))
+}
 
-    Ok(Response::new()
-        .add_attribute("action", "append_annotation")
-        .add_attribute("id", id.to_string())
-        .add_attribute("by", info.sender))
+/* ============== record NFT ============== */
+
+/// nft_minter が設定されていれば、投稿者宛ての観察記録 NFT の発行を SubMsg にする。
+/// 発行に失敗してもレコードは残り、nft_token_id は None のまま
+fn mint_record_nft(store: &mut dyn Storage, rec: &StoredRecord) -> StdResult<Option<SubMsg>> {
+    let Some(m) = NFT_MINTER.may_load(store)? else {
+        return Ok(None);
+    };
+    let token_id = format!("{}{}", m.token_id_prefix, rec.id);
+    PENDING_MINTS.save(store, rec.id, &token_id)?;
+    let msg = WasmMsg::Execute {
+        contract_addr: m.contract.to_string(),
+        msg: to_json_binary(&MinterExecuteMsg::PublicMint {
+            token_id,
+            owner: rec.sender.to_string(),
+            token_uri: format!("ipfs://{}", rec.cid),
+            extension: None,
+        })?,
+        funds: vec![],
+    };
+    Ok(Some(SubMsg::reply_always(msg, REPLY_MINT_RECORD_NFT)))
 }
 
-fn exec_verify(
-    mut deps: DepsMut,
-    env: Env,
-    info: MessageInfo,
-    id: u64,
-    taxon_id: String,
-    confidence: u8,
+/// 発行できたトークンをレコードに結びつける。失敗なら何もしない（保存は取り消さない）。
+/// SubMsg は追加した順に実行され reply もその順で届くので、待ちの最小の id が対象
+fn on_record_nft_minted(
+    deps: DepsMut,
+    env: &Env,
+    result: SubMsgResult,
 ) -> Result<Response, ContractError> {
-    ensure_verifier(&mut deps, &info.sender)?;
-    if taxon_id.trim().is_empty() {
-        return Err(ContractError::BadRequest {
-            msg: "taxon_id must not be empty".into(),
-        });
+    let (id, token_id) = PENDING_MINTS
+        .range(deps.storage, None, None, Order::Ascending)
+        .next()
+        .transpose()?
+        .ok_or_else(|| StdError::generic_err("no pending record NFT mint"))?;
+    PENDING_MINTS.remove(deps.storage, id);
+    if let SubMsgResult::Err(e) = result {
+        return Ok(Response::new()
+            .add_attribute("action", "record_nft_mint_failed")
+            .add_attribute("id", id.to_string())
+            .add_attribute("error", e));
     }
-    RECORDS.update(deps.storage, id, |maybe| -> Result<_, ContractError> {
-        let mut rec = maybe.ok_or(ContractError::NotFound)?;
-        rec.verifications.push(VerificationEntry {
-            at: env.block.time.seconds(),
-            verifier: info.sender.clone(),
-            taxon_id,
-            confidence,
-        });
+
+    RECORDS.update(deps.storage, id, |maybe| -> StdResult<_> {
+        let mut rec = maybe.ok_or_else(|| StdError::not_found("StoredRecord"))?;
+        rec.nft_token_id = Some(token_id.clone());
         Ok(rec)
     })?;
+    RECORD_BY_TOKEN.save(deps.storage, &token_id, &id)?;
+    append_change(deps.storage, env.block.height, id, ChangeKind::Update)?;
 
     Ok(Response::new()
-        .add_attribute("action", "verify")
+        .add_attribute("action", "record_nft_minted")
         .add_attribute("id", id.to_string())
-        .add_attribute("verifier", info.sender))
+        .add_attribute("token_id", token_id))
 }
 
-fn exec_hide(
-    mut deps: DepsMut,
-    _env: Env,
+fn validate_nft_minter(deps: Deps, m: NftMinterMsg) -> Result<NftMinterConfig, ContractError> {
+    let prefix = m
+        .token_id_prefix
+        .unwrap_or_else(|| DEFAULT_TOKEN_ID_PREFIX.to_string());
+    if prefix.chars().any(|c| c.is_whitespace()) {
+        return Err(ContractError::BadRequest {
+            msg: "token_id_prefix must not contain whitespace".into(),
+        });
+    }
+    Ok(NftMinterConfig {
+        contract: deps.api.addr_validate(&m.contract)?,
+        token_id_prefix: prefix,
+    })
+}
+
+fn exec_set_nft_minter(
+    deps: DepsMut,
+    env: Env,
     info: MessageInfo,
-    id: u64,
-    reason: Option<String>,
+    minter: Option<NftMinterMsg>,
 ) -> Result<Response, ContractError> {
-    ensure_admin(&mut deps, &info.sender)?;
-    RECORDS.update(deps.storage, id, |maybe| -> Result<_, ContractError> {
-        let mut rec = maybe.ok_or(ContractError::NotFound)?;
-        rec.hidden = true;
-        rec.hidden_reason = reason.clone();
-        Ok(rec)
-    })?;
-
-    Ok(Response::new()
-        .add_attribute("action", "hide")
-        .add_attribute("id", id.to_string()))
+    ensure_admin(&deps, &env, &info.sender)?;
+    let res = Response::new().add_attribute("action", "set_nft_minter");
+    match minter {
+        Some(m) => {
+            let m = validate_nft_minter(deps.as_ref(), m)?;
+            NFT_MINTER.save(deps.storage, &m)?;
+            Ok(res.add_attribute("contract", m.contract))
+        }
+        None => {
+            NFT_MINTER.remove(deps.storage);
+            Ok(res.add_attribute("contract", ""))
+        }
+    }
 }
 
-/* ============== query entry ============== */
+/* ============== hooks ============== */
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::Get { id } => to_json_binary(&query_get(deps, id)?),
-        QueryMsg::List {
-            species,
-            geohash_prefix,
-            start,
-            end,
-            limit,
-            start_after,
-        } => to_json_binary(&query_list(
-            deps,
-            species,
-            geohash_prefix,
-            start,
-            end,
-            limit,
-            start_after,
-        )?),
-        QueryMsg::Count {
-            species,
-            geohash_prefix,
-            start,
-            end,
-        } => to_json_binary(&query_count(deps, species, geohash_prefix, start, end)?),
-        QueryMsg::StatsMonthly {
-            species,
-            geohash_prefix,
-            year,
-        } => to_json_binary(&query_stats_monthly(deps, species, geohash_prefix, year)?),
+fn stored_hook(rec: &StoredRecord) -> ObservationHookMsg {
+    ObservationHookMsg::Stored {
+        id: rec.id,
+        species: rec.species.clone(),
+        geohash: rec.geohash_prefix.clone(),
+        sender: rec.sender.to_string(),
     }
 }
 
-fn query_get(deps: Deps, id: u64) -> StdResult<GetResp> {
-    let rec = RECORDS.may_load(deps.storage, id)?;
-    Ok(GetResp { record: rec })
+/// 各通知先への SubMsg。失敗しても reply で握りつぶし、元の処理は止めない
+fn hook_msgs(store: &dyn Storage, msg: ObservationHookMsg) -> StdResult<Vec<SubMsg>> {
+    let hooks: Vec<Addr> = HOOKS
+        .keys(store, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
+    if hooks.is_empty() {
+        return Ok(vec![]);
+    }
+    let payload = to_json_binary(&HookExecuteMsg::ObservationHook(msg))?;
+    Ok(hooks
+        .into_iter()
+        .map(|h| {
+            SubMsg::reply_on_error(
+                WasmMsg::Execute {
+                    contract_addr: h.to_string(),
+                    msg: payload.clone(),
+                    funds: vec![],
+                },
+                REPLY_HOOK_FAILED,
+            )
+            .with_gas_limit(HOOK_GAS_LIMIT)
+        })
+        .collect())
 }
 
-/* ============== list / count 共通 ============== */
-
-fn filter_match(
-    rec: &StoredRecord,
-    geohash_prefix: Option<&str>,
-    start: Option<u64>,
-    end: Option<u64>,
-) -> bool {
-    if let Some(geo) = geohash_prefix {
-        if !rec.geohash_prefix.starts_with(geo) {
-            return false;
-        }
-    }
-    if let Some(lo) = start {
-        if rec.observed_at < lo {
-            return false;
-        }
+fn exec_add_hook(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    addr: String,
+) -> Result<Response, ContractError> {
+    ensure_admin(&deps, &env, &info.sender)?;
+    let addr = deps.api.addr_validate(&addr)?;
+    if HOOKS.has(deps.storage, &addr) {
+        return Err(ContractError::BadRequest {
+            msg: "hook already registered".into(),
+        });
     }
-    if let Some(hi) = end {
-        if rec.observed_at > hi {
-            return false;
-        }
+    let n = HOOKS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .count();
+    if n >= MAX_HOOKS {
+        return Err(ContractError::BadRequest {
+            msg: format!("at most {} hooks", MAX_HOOKS),
+        });
     }
-    true
+    HOOKS.save(deps.storage, &addr, &())?;
+    Ok(Response::new()
+        .add_attribute("action", "add_hook")
+        .add_attribute("addr", addr))
 }
 
-fn query_list(
-    deps: Deps,
-    species: Option<String>,
-    geohash_prefix: Option<String>,
-    start: Option<u64>,
-    end: Option<u64>,
-    limit: Option<u32>,
-    start_after: Option<u64>,
-) -> StdResult<ListResp> {
-    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+fn exec_remove_hook(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    addr: String,
+) -> Result<Response, ContractError> {
+    ensure_admin(&deps, &env, &info.sender)?;
+    let addr = deps.api.addr_validate(&addr)?;
+    if !HOOKS.has(deps.storage, &addr) {
+        return Err(ContractError::NotFound);
+    }
+    HOOKS.remove(deps.storage, &addr);
+    Ok(Response::new()
+        .add_attribute("action", "remove_hook")
+        .add_attribute("addr", addr))
+}
 
-    let mut out: Vec<StoredRecord> = Vec::with_capacity(limit);
-    let mut last_id: Option<u64> = None;
+/* ============== gate ============== */
 
-    if let Some(sp0) = species.clone().map(|s| normalize_species(&s)) {
-        // species index: keys are id only（prefix化でspecies成分は除去）
-        let start_bound = start_after.map(Bound::exclusive);
-        let iter = BY_SPECIES
-            .prefix(sp0)
-            .range(deps.storage, start_bound, None, Order::Ascending);
+/// ゲートを満たすトークンを決める（ゲート未設定・importer は None）。
+/// token_id が無ければ保有トークンを順に見て最初に条件を満たすもの
+fn resolve_gate(
+    deps: Deps,
+    env: &Env,
+    sender: &Addr,
+    token_id: Option<String>,
+    taken: &[String],
+) -> Result<Option<String>, ContractError> {
+    let Some(g) = GATE.may_load(deps.storage)? else {
+        return Ok(None);
+    };
+    if has_role(deps.storage, env, sender, Role::Importer)? {
+        return Ok(None);
+    }
+    let unused_only = g.mode == GateMode::Unused;
+    let is_free = |t: &str| !taken.iter().any(|x| x == t) && !USED_GATE_TOKENS.has(deps.storage, t);
 
-        for item in iter {
-            let (id, _) = item?.into();
-            if let Some(rec) = RECORDS.may_load(deps.storage, id)? {
-                if filter_match(&rec, geohash_prefix.as_deref(), start, end) && !rec.hidden {
-                    last_id = Some(id);
-                    out.push(rec);
-                    if out.len() == limit {
-                        break;
-                    }
-                }
+    match token_id {
+        Some(t) => {
+            if gate::owner_of(&deps.querier, &g.contract, &t)? != sender.as_str() {
+                return Err(ContractError::Unauthorized);
             }
+            if unused_only && !is_free(&t) {
+                return Err(ContractError::BadRequest {
+                    msg: format!("gate token {} has already been used", t),
+                });
+            }
+            Ok(Some(t))
         }
-    } else if let Some(geo) = geohash_prefix.clone() {
-        let start_bound = start_after.map(Bound::exclusive);
-        let iter = BY_GEOHASH
-            .prefix(geo)
-            .range(deps.storage, start_bound, None, Order::Ascending);
-
-        for item in iter {
-            let (id, _) = item?.into();
-            if let Some(rec) = RECORDS.may_load(deps.storage, id)? {
-                if filter_match(&rec, None, start, end) && !rec.hidden {
-                    last_id = Some(id);
-                    out.push(rec);
-                    if out.len() == limit {
-                        break;
-                    }
+        None if g.mode == GateMode::Specific => Err(ContractError::BadRequest {
+            msg: "gate_token_id is required".into(),
+        }),
+        None => {
+            let mut start_after = None;
+            loop {
+                let page = gate::tokens(&deps.querier, &g.contract, sender, start_after)?;
+                if let Some(t) = page.iter().find(|t| !unused_only || is_free(t)) {
+                    return Ok(Some(t.clone()));
+                }
+                if page.len() < gate::TOKENS_PAGE as usize {
+                    return Err(ContractError::Unauthorized);
                 }
+                start_after = page.last().cloned();
             }
         }
-    } else {
-        // time index: (observed_at, id)
-        let lo = start.unwrap_or(0);
-        let hi = end.unwrap_or(u64::MAX);
-
-        // ページング: start_after がある場合は (lo, id) を exclusive に
-        let start_key = match start_after {
-            Some(sa) => Some(Bound::exclusive((lo, sa))),
-            None => Some(Bound::inclusive((lo, 0u64))),
-        };
-        let end_key = Some(Bound::inclusive((hi, u64::MAX)));
+    }
+}
 
-        let iter = BY_TIME.range(deps.storage, start_key, end_key, Order::Ascending);
+fn validate_gate(deps: Deps, g: GateConfigMsg) -> StdResult<GateConfig> {
+    Ok(GateConfig {
+        contract: deps.api.addr_validate(&g.contract)?,
+        mode: g.mode,
+    })
+}
 
-        for item in iter {
-            let ((_, id), _) = item?;
-            if let Some(rec) = RECORDS.may_load(deps.storage, id)? {
-                if filter_match(&rec, geohash_prefix.as_deref(), None, None) && !rec.hidden {
-                    last_id = Some(id);
-                    out.push(rec);
-                    if out.len() == limit {
-                        break;
-                    }
-                }
-            }
+fn exec_set_gate(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    gate: Option<GateConfigMsg>,
+) -> Result<Response, ContractError> {
+    ensure_admin(&deps, &env, &info.sender)?;
+    let res = Response::new().add_attribute("action", "set_gate");
+    match gate {
+        Some(g) => {
+            let g = validate_gate(deps.as_ref(), g)?;
+            GATE.save(deps.storage, &g)?;
+            Ok(res.add_attribute("contract", g.contract))
+        }
+        None => {
+            GATE.remove(deps.storage);
+            Ok(res.add_attribute("contract", ""))
         }
     }
+}
 
-    let next = if out.len() == limit { last_id } else { None };
-    Ok(ListResp {
-        records: out,
-        next_start_after: next,
+/* ============== quota / deposit ============== */
+
+/// Store / StoreBatch の前に求める投稿制限
+struct Admission {
+    /// ウィンドウ内で残っている件数（クォータ無し・importer は None）
+    remaining: Option<u32>,
+    /// 1 件ごとに預かる額（importer は None）
+    deposit: Option<DepositConfig>,
+}
+
+fn admission(deps: Deps, env: &Env, sender: &Addr) -> StdResult<Admission> {
+    let policy = SPAM_POLICY.may_load(deps.storage)?.unwrap_or_default();
+    if has_role(deps.storage, env, sender, Role::Importer)? {
+        return Ok(Admission {
+            remaining: None,
+            deposit: None,
+        });
+    }
+    let remaining = match &policy.quota {
+        Some(q) => Some(
+            q.max_stores
+                .saturating_sub(quota_used(deps, env, sender, q)?),
+        ),
+        None => None,
+    };
+    Ok(Admission {
+        remaining,
+        deposit: policy.deposit,
     })
 }
 
-fn query_count(
-    deps: Deps,
-    species: Option<String>,
-    geohash_prefix: Option<String>,
-    start: Option<u64>,
-    end: Option<u64>,
-) -> StdResult<CountResp> {
-    let mut cnt: u64 = 0;
+/// ウィンドウ内の投稿数（max_stores で打ち切り）
+fn quota_used(deps: Deps, env: &Env, sender: &Addr, q: &StoreQuota) -> StdResult<u32> {
+    let cutoff = env.block.time.seconds().saturating_sub(q.window_secs);
+    let n = QUOTA_LOG
+        .sub_prefix(sender)
+        .keys(
+            deps.storage,
+            Some(Bound::exclusive((cutoff, u64::MAX))),
+            None,
+            Order::Ascending,
+        )
+        .take(q.max_stores as usize)
+        .count();
+    Ok(n as u32)
+}
 
-    if let Some(sp0) = species.clone().map(|s| normalize_species(&s)) {
-        let iter = BY_SPECIES
-            .prefix(sp0)
-            .range(deps.storage, None, None, Order::Ascending);
-        for item in iter {
-            let (id, _) = item?.into();
-            if let Some(rec) = RECORDS.may_load(deps.storage, id)? {
-                if !rec.hidden && filter_match(&rec, geohash_prefix.as_deref(), start, end) {
-                    cnt += 1;
-                }
-            }
-        }
-    } else if let Some(geo) = geohash_prefix.clone() {
-        let iter = BY_GEOHASH
-            .prefix(geo)
-            .range(deps.storage, None, None, Order::Ascending);
-        for item in iter {
-            let (id, _) = item?.into();
-            if let Some(rec) = RECORDS.may_load(deps.storage, id)? {
-                if !rec.hidden && filter_match(&rec, None, start, end) {
-                    cnt += 1;
-                }
-            }
+fn quota_exceeded(deps: Deps) -> StdResult<ContractError> {
+    let q = SPAM_POLICY
+        .may_load(deps.storage)?
+        .and_then(|p| p.quota)
+        .ok_or_else(|| StdError::generic_err("quota is not configured"))?;
+    Ok(ContractError::BadRequest {
+        msg: format!(
+            "store quota exceeded: {} per {} seconds",
+            q.max_stores, q.window_secs
+        ),
+    })
+}
+
+/// デポジット設定があれば、ちょうど amount × n の 1 種類の coin を求める
+fn check_deposit_funds(
+    info: &MessageInfo,
+    deposit: Option<&DepositConfig>,
+    n: u128,
+) -> Result<(), ContractError> {
+    let Some(d) = deposit else {
+        return Ok(());
+    };
+    let want = deposit_total(d, n)?;
+    if info.funds != [want.clone()] {
+        return Err(ContractError::BadRequest {
+            msg: format!("deposit of exactly {} is required", want),
+        });
+    }
+    Ok(())
+}
+
+/// amount × n（桁あふれは BadRequest）
+fn deposit_total(d: &DepositConfig, n: u128) -> Result<Coin, ContractError> {
+    let amount = d
+        .amount
+        .amount
+        .checked_mul(Uint128::new(n))
+        .map_err(|_| ContractError::BadRequest {
+            msg: "deposit total overflows".into(),
+        })?;
+    Ok(coin(amount.u128(), &d.amount.denom))
+}
+
+/// 保存したレコードをクォータに数え、デポジットを預かる
+fn record_submission(
+    store: &mut dyn Storage,
+    env: &Env,
+    rec: &StoredRecord,
+    deposit: Option<&DepositConfig>,
+) -> StdResult<()> {
+    let now = env.block.time.seconds();
+    if let Some(q) = SPAM_POLICY.may_load(store)?.and_then(|p| p.quota) {
+        let cutoff = now.saturating_sub(q.window_secs);
+        let stale: Vec<(u64, u64)> = QUOTA_LOG
+            .sub_prefix(&rec.sender)
+            .keys(
+                store,
+                None,
+                Some(Bound::inclusive((cutoff, u64::MAX))),
+                Order::Ascending,
+            )
+            .collect::<StdResult<_>>()?;
+        for (t, id) in stale {
+            QUOTA_LOG.remove(store, (&rec.sender, t, id));
         }
-    } else {
-        let lo = start.unwrap_or(0);
-        let hi = end.unwrap_or(u64::MAX);
-        let iter = BY_TIME.range(
+        QUOTA_LOG.save(store, (&rec.sender, now, rec.id), &())?;
+    }
+    if let Some(d) = deposit {
+        let release_at = now
+            .checked_add(d.challenge_period_secs)
+            .ok_or_else(|| StdError::generic_err("deposit release time overflow"))?;
+        DEPOSITS.save(
+            store,
+            rec.id,
+            &Deposit {
+                record_id: rec.id,
+                owner: rec.sender.clone(),
+                amount: d.amount.clone(),
+                release_at,
+                treasury: d.treasury.clone(),
+                forfeited: false,
+            },
+        )?;
+        DEPOSITS_BY_OWNER.save(store, (&rec.sender, release_at, rec.id), &())?;
+    }
+    Ok(())
+}
+
+/// チャレンジ期間中のデポジットの没収フラグを切り替える。変わったら true。
+/// 送金は release_at 後の ClaimDeposits でまとめて行う
+fn mark_forfeited(
+    store: &mut dyn Storage,
+    env: &Env,
+    id: u64,
+    forfeited: bool,
+) -> StdResult<bool> {
+    let Some(mut d) = DEPOSITS.may_load(store, id)? else {
+        return Ok(false);
+    };
+    if d.release_at <= env.block.time.seconds() || d.forfeited == forfeited {
+        return Ok(false);
+    }
+    d.forfeited = forfeited;
+    DEPOSITS.save(store, id, &d)?;
+    Ok(true)
+}
+
+/// 期間を過ぎたデポジットを台帳から外す。送り先は没収なら treasury、そうでなければ投稿者
+fn settle_deposit(store: &mut dyn Storage, id: u64) -> StdResult<Deposit> {
+    let d = DEPOSITS.load(store, id)?;
+    DEPOSITS.remove(store, id);
+    DEPOSITS_BY_OWNER.remove(store, (&d.owner, d.release_at, id));
+    Ok(d)
+}
+
+fn exec_claim_deposits(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    owner: Option<String>,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let limit = page_limit(deps.storage, limit)?;
+    let owner = match owner {
+        Some(o) => deps.api.addr_validate(&o)?,
+        None => info.sender,
+    };
+    let now = env.block.time.seconds();
+    let due: Vec<u64> = DEPOSITS_BY_OWNER
+        .sub_prefix(&owner)
+        .keys(
             deps.storage,
-            Some(Bound::inclusive((lo, 0u64))),
-            Some(Bound::inclusive((hi, u64::MAX))),
+            None,
+            Some(Bound::inclusive((now, u64::MAX))),
             Order::Ascending,
-        );
-        for item in iter {
-            let ((_, id), _) = item?;
-            if let Some(rec) = RECORDS.may_load(deps.storage, id)? {
-                if !rec.hidden && filter_match(&rec, geohash_prefix.as_deref(), None, None) {
-                    cnt += 1;
-                }
+        )
+        .take(limit)
+        .map(|item| item.map(|(_, id)| id))
+        .collect::<StdResult<_>>()?;
+    if due.is_empty() {
+        return Err(ContractError::BadRequest {
+            msg: "no deposits to claim".into(),
+        });
+    }
+
+    // 送り先・denom ごとに合算
+    let mut sends: Vec<(Addr, Vec<Coin>)> = vec![];
+    let mut forfeited = 0u64;
+    for id in &due {
+        let d = settle_deposit(deps.storage, *id)?;
+        let to = if d.forfeited {
+            forfeited += 1;
+            d.treasury
+        } else {
+            d.owner
+        };
+        let total = match sends.iter_mut().find(|(a, _)| *a == to) {
+            Some((_, total)) => total,
+            None => {
+                sends.push((to, vec![]));
+                &mut sends.last_mut().unwrap().1
             }
+        };
+        match total.iter_mut().find(|c| c.denom == d.amount.denom) {
+            Some(c) => c.amount += d.amount.amount,
+            None => total.push(d.amount),
         }
     }
 
-    Ok(CountResp { count: cnt })
+    Ok(Response::new()
+        .add_messages(sends.into_iter().map(|(to, amount)| BankMsg::Send {
+            to_address: to.to_string(),
+            amount,
+        }))
+        .add_attribute("action", "claim_deposits")
+        .add_attribute("owner", owner)
+        .add_attribute("count", due.len().to_string())
+        .add_attribute("forfeited", forfeited.to_string()))
+}
+
+fn exec_set_spam_policy(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    quota: Option<StoreQuota>,
+    deposit: Option<DepositConfigMsg>,
+) -> Result<Response, ContractError> {
+    ensure_admin(&deps, &env, &info.sender)?;
+    if let Some(q) = &quota {
+        if q.max_stores == 0 || q.window_secs == 0 {
+            return Err(ContractError::BadRequest {
+                msg: "quota max_stores and window_secs must be positive".into(),
+            });
+        }
+    }
+    let deposit = match deposit {
+        Some(d) => {
+            if d.amount.amount.is_zero() || d.amount.denom.is_empty() {
+                return Err(ContractError::BadRequest {
+                    msg: "deposit amount must be a positive coin".into(),
+                });
+            }
+            if d.challenge_period_secs > MAX_CHALLENGE_PERIOD_SECS {
+                return Err(ContractError::BadRequest {
+                    msg: format!(
+                        "challenge_period_secs must be at most {}",
+                        MAX_CHALLENGE_PERIOD_SECS
+                    ),
+                });
+            }
+            Some(DepositConfig {
+                amount: d.amount,
+                challenge_period_secs: d.challenge_period_secs,
+                treasury: deps.api.addr_validate(&d.treasury)?,
+            })
+        }
+        None => None,
+    };
+    SPAM_POLICY.save(deps.storage, &SpamPolicy { quota, deposit })?;
+    Ok(Response::new().add_attribute("action", "set_spam_policy"))
 }
 
-/* ============== stats (簡易: 年=31536000秒近似) ============== */
+/// 検証・ゲート確認・項目抽出だけを行い、保存前のレコードを作る（ストレージには書き込まない）。
+/// taken は同じバッチで先に割り当てたゲートのトークン
+fn new_record(
+    deps: Deps,
+    env: &Env,
+    cfg: &Config,
+    sender: &Addr,
+    id: u64,
+    item: StoreItem,
+    taken: &[String],
+) -> Result<StoredRecord, ContractError> {
+    let StoreItem {
+        payload,
+        cid: cid_input,
+        gate_token_id,
+    } = item;
+    let fields = extract_fields(deps, cfg, &payload)?;
+    let cid = normalize_cid(cfg, &cid_input)?; // 必須・正規化
+    let gate_token_id = resolve_gate(deps, env, sender, gate_token_id, taken)?;
 
-fn year_bounds_utc(year: u32) -> (u64, u64) {
-    let start = u64::from(year) * 31_536_000;
-    let end = u64::from(year + 1) * 31_536_000 - 1;
-    (start, end)
+    let rec = StoredRecord {
+        id,
+        sender: sender.clone(),
+        observed_at: fields.observed_at,
+        species: fields.species,
+        phenophase: fields.phenophase,
+        geohash_prefix: fields.geohash,
+        cid,
+        payload,
+        block_time: env.block.time.seconds(),
+        block_height: env.block.height,
+        hidden: false,
+        hidden_reason: None,
+        consensus_taxon: None,
+        quality_grade: None,
+        edited: None,
+        gate_token_id,
+        nft_token_id: None,
+        annotation_count: 0,
+        verification_count: 0,
+    };
+    Ok(graded(deps.storage, rec)?)
 }
 
-fn month_from_unix_utc(ts: u64) -> u32 {
-    let seconds_in_year = 31_536_000u64;
-    let sec = ts % seconds_in_year;
-    let month = (sec * 12 / seconds_in_year) + 1;
-    month as u32
+/// new_record で作ったレコードを保存し、インデックス・カウンタ・NEXT_ID を進める
+fn insert_record(store: &mut dyn Storage, rec: &StoredRecord) -> StdResult<()> {
+    RECORDS.save(store, rec.id, rec)?;
+    if let Some(t) = &rec.gate_token_id {
+        USED_GATE_TOKENS.save(store, t, &rec.id)?;
+    }
+    index_record(store, rec)?;
+    adjust_counters(store, rec, true)?;
+    append_change(store, rec.block_height, rec.id, ChangeKind::Store)?;
+    NEXT_ID.save(store, &(rec.id + 1))
 }
 
-fn query_stats_monthly(
-    deps: Deps,
+/// payload から取り出したインデックス項目
+struct Extracted {
+    observed_at: u64,
     species: Option<String>,
-    geohash_prefix: Option<String>,
-    year: u32,
-) -> StdResult<StatsMonthlyResp> {
-    let (start, end) = year_bounds_utc(year);
-    let mut months = [0u64; 12];
+    phenophase: Option<String>,
+    geohash: String,
+}
 
-    // 走査集合の選択
-    if let Some(sp0) = species.clone().map(|s| normalize_species(&s)) {
-        let iter = BY_SPECIES
-            .prefix(sp0)
-            .range(deps.storage, None, None, Order::Ascending);
-        for item in iter {
-            let (id, _) = item?.into();
-            if let Some(rec) = RECORDS.may_load(deps.storage, id)? {
-                if rec.hidden {
-                    continue;
-                }
-                if !filter_match(&rec, geohash_prefix.as_deref(), Some(start), Some(end)) {
-                    continue;
-                }
-                let m = month_from_unix_utc(rec.observed_at);
-                if (1..=12).contains(&m) {
-                    months[(m - 1) as usize] += 1;
-                }
-            }
-        }
-    } else if let Some(geo) = geohash_prefix.clone() {
-        let iter = BY_GEOHASH
-            .prefix(geo)
-            .range(deps.storage, None, None, Order::Ascending);
-        for item in iter {
-            let (id, _) = item?.into();
-            if let Some(rec) = RECORDS.may_load(deps.storage, id)? {
-                if rec.hidden {
-                    continue;
-                }
-                if !filter_match(&rec, None, Some(start), Some(end)) {
-                    continue;
-                }
-                let m = month_from_unix_utc(rec.observed_at);
-                if (1..=12).contains(&m) {
-                    months[(m - 1) as usize] += 1;
-                }
-            }
-        }
-    } else {
-        // 時間インデックスで期間絞り
-        let iter = BY_TIME.range(
-            deps.storage,
-            Some(Bound::inclusive((start, 0u64))),
-            Some(Bound::inclusive((end, u64::MAX))),
-            Order::Ascending,
-        );
-        for item in iter {
-            let ((_, id), _) = item?;
-            if let Some(rec) = RECORDS.may_load(deps.storage, id)? {
-                if rec.hidden {
-                    continue;
-                }
-                if let Some(geo) = geohash_prefix.as_deref() {
-                    if !rec.geohash_prefix.starts_with(geo) {
-                        continue;
-                    }
-                }
-                let m = month_from_unix_utc(rec.observed_at);
-                if (1..=12).contains(&m) {
-                    months[(m - 1) as usize] += 1;
-                }
-            }
+/// スキーマ検証のうえ、Store / UpdateRecord 共通の項目抽出
+fn extract_fields(
+    deps: Deps,
+    cfg: &Config,
+    payload: &serde_json::Value,
+) -> Result<Extracted, ContractError> {
+    let size = serde_json::to_vec(payload)
+        .map_err(|e| StdError::serialize_err("payload", e))?
+        .len();
+    if size > cfg.max_payload_bytes as usize {
+        return Err(ContractError::BadRequest {
+            msg: format!("payload exceeds {} bytes", cfg.max_payload_bytes),
+        });
+    }
+    if let Some(schema) = PAYLOAD_SCHEMA.may_load(deps.storage)? {
+        schema::validate_payload(&schema, payload)?;
+    }
+    Ok(Extracted {
+        observed_at: extract_observed_at(payload)?,
+        species: extract_species(payload).map(|s| normalize_species(&s)),
+        phenophase: extract_phenophase(payload),
+        geohash: extract_geohash_prefix(payload, cfg.geohash_precision),
+    })
+}
+
+fn exec_update_record(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+    payload: serde_json::Value,
+    cid_input: String,
+) -> Result<Response, ContractError> {
+    let mut rec = RECORDS
+        .may_load(deps.storage, id)?
+        .ok_or(ContractError::NotFound)?;
+    if rec.sender != info.sender {
+        let admin_ok = ADMIN_CAN_EDIT.may_load(deps.storage)?.unwrap_or(false)
+            && has_role(deps.storage, &env, &info.sender, Role::Admin)?;
+        if !admin_ok {
+            return Err(ContractError::Unauthorized);
         }
     }
+    if rec.hidden {
+        return Err(ContractError::BadRequest {
+            msg: "hidden records cannot be updated".into(),
+        });
+    }
+    let cfg = CONFIG.load(deps.storage)?;
+    let fields = extract_fields(deps.as_ref(), &cfg, &payload)?;
+    let cid = normalize_cid(&cfg, &cid_input)?;
 
-    Ok(StatsMonthlyResp { months })
+    // 現行版を履歴へ
+    let prev = match &rec.edited {
+        Some(e) => RecordRevision {
+            revision: e.revision,
+            payload: rec.payload.clone(),
+            cid: rec.cid.clone(),
+            editor: e.by.clone(),
+            block_time: e.block_time,
+            block_height: e.block_height,
+        },
+        None => RecordRevision {
+            revision: 0,
+            payload: rec.payload.clone(),
+            cid: rec.cid.clone(),
+            editor: rec.sender.clone(),
+            block_time: rec.block_time,
+            block_height: rec.block_height,
+        },
+    };
+    RECORD_HISTORY.save(deps.storage, (id, prev.revision), &prev)?;
+
+    unindex_record(deps.storage, &rec);
+    adjust_counters(deps.storage, &rec, false)?;
+
+    rec.observed_at = fields.observed_at;
+    rec.species = fields.species;
+    rec.phenophase = fields.phenophase;
+    rec.geohash_prefix = fields.geohash;
+    rec.cid = cid.clone();
+    rec.payload = payload;
+    rec.edited = Some(EditInfo {
+        revision: prev.revision + 1,
+        by: info.sender.clone(),
+        block_time: env.block.time.seconds(),
+        block_height: env.block.height,
+    });
+    let rec = graded(deps.storage, rec)?;
+
+    RECORDS.save(deps.storage, id, &rec)?;
+    index_record(deps.storage, &rec)?;
+    adjust_counters(deps.storage, &rec, true)?;
+    append_change(deps.storage, env.block.height, id, ChangeKind::Update)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_record")
+        .add_attribute("id", id.to_string())
+        .add_attribute("revision", (prev.revision + 1).to_string())
+        .add_attribute("editor", info.sender)
+        .add_attribute("cid", cid))
 }
 
-/* ===========================
- * admin helper
- * =========================== */
+/// レコード本体・履歴・インデックスを削除し、Tombstone を残す
+fn delete_record(
+    deps: &mut DepsMut,
+    env: &Env,
+    rec: StoredRecord,
+    reason: TombstoneReason,
+    by: &Addr,
+) -> Result<(), ContractError> {
+    // デポジットは release_at まで預かったまま。Purge か未対応の通報があれば没収にする
+    let reported = REPORTS_BY_RECORD
+        .prefix(rec.id)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<u64>>>()?
+        .into_iter()
+        .any(|rid| OPEN_REPORTS.has(deps.storage, rid));
+    if reason == TombstoneReason::Purged || reported {
+        mark_forfeited(deps.storage, env, rec.id, true)?;
+    }
+    unindex_record(deps.storage, &rec);
+    if !rec.hidden {
+        adjust_counters(deps.storage, &rec, false)?;
+    }
+    let revisions: Vec<u32> = RECORD_HISTORY
+        .prefix(rec.id)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
+    for r in revisions {
+        RECORD_HISTORY.remove(deps.storage, (rec.id, r));
+    }
+    let annotations: Vec<u64> = ANNOTATIONS
+        .prefix(rec.id)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
+    for seq in annotations {
+        ANNOTATIONS.remove(deps.storage, (rec.id, seq));
+    }
+    // 集計は上の adjust_counters で済んでいる
+    let tags: Vec<String> = RECORD_TAGS
+        .prefix(rec.id)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
+    for tag in &tags {
+        RECORD_TAGS.remove(deps.storage, (rec.id, tag.as_str()));
+        BY_TAG.remove(deps.storage, (tag.as_str(), rec.id));
+    }
+    for (seq, v) in current_verifications(deps.storage, rec.id)? {
+        BY_VERIFIER.remove(deps.storage, (&v.verifier, rec.id));
+        VERIFICATIONS.remove(deps.storage, (rec.id, seq));
+    }
+    let seqs: Vec<u64> = VERIFICATION_HISTORY
+        .prefix(rec.id)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
+    for seq in seqs {
+        VERIFICATION_HISTORY.remove(deps.storage, (rec.id, seq));
+    }
+    if let Some(t) = &rec.nft_token_id {
+        RECORD_BY_TOKEN.remove(deps.storage, t);
+    }
+    RECORDS.remove(deps.storage, rec.id);
+    append_change(deps.storage, env.block.height, rec.id, ChangeKind::Delete)?;
+    resolve_open_reports(deps.storage, rec.id, ReportStatus::Closed, by)?;
+    TOMBSTONES.save(
+        deps.storage,
+        rec.id,
+        &Tombstone {
+            id: rec.id,
+            sender: rec.sender,
+            cid: rec.cid,
+            reason,
+            by: by.clone(),
+            block_time: env.block.time.seconds(),
+            block_height: env.block.height,
+        },
+    )?;
+    Ok(())
+}
 
-fn exec_set_verifier(
+fn exec_withdraw(
     mut deps: DepsMut,
+    env: Env,
     info: MessageInfo,
-    addr: String,
-    enabled: bool,
+    id: u64,
 ) -> Result<Response, ContractError> {
-    ensure_admin(&mut deps, &info.sender)?;
-    let a = deps.api.addr_validate(&addr)?;
-    VERIFIERS.save(deps.storage, &a, &enabled)?;
+    let rec = RECORDS
+        .may_load(deps.storage, id)?
+        .ok_or(ContractError::NotFound)?;
+    if rec.sender != info.sender {
+        return Err(ContractError::Unauthorized);
+    }
+    delete_record(
+        &mut deps,
+        &env,
+        rec,
+        TombstoneReason::Withdrawn,
+        &info.sender,
+    )?;
+
     Ok(Response::new()
-        .add_attribute("action", "set_verifier")
-        .add_attribute("addr", a)
-        .add_attribute("enabled", enabled.to_string()))
+        .add_attribute("action", "withdraw")
+        .add_attribute("id", id.to_string()))
+}
+
+fn exec_purge(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    ids: Vec<u64>,
+) -> Result<Response, ContractError> {
+    ensure_admin(&deps, &env, &info.sender)?;
+    if ids.is_empty() || ids.len() > MAX_PURGE_IDS {
+        return Err(ContractError::BadRequest {
+            msg: format!("ids must contain 1..={} entries", MAX_PURGE_IDS),
+        });
+    }
+    let mut purged = 0u64;
+    for id in ids {
+        let Some(rec) = RECORDS.may_load(deps.storage, id)? else {
+            // 取り下げ済み・重複指定は無視
+            if TOMBSTONES.has(deps.storage, id) {
+                continue;
+            }
+            return Err(ContractError::NotFound);
+        };
+        delete_record(&mut deps, &env, rec, TombstoneReason::Purged, &info.sender)?;
+        append_audit(
+            deps.storage,
+            &env,
+            id,
+            ModerationAction::Purge,
+            &info.sender,
+            None,
+            None,
+        )?;
+        purged += 1;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "purge")
+        .add_attribute("count", purged.to_string()))
+}
+
+fn exec_append_annotation(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+    note: Option<String>,
+    photo_cid: Option<String>,
+    tags: Option<Vec<String>>,
+) -> Result<Response, ContractError> {
+    if note.as_ref().map(|s| s.is_empty()).unwrap_or(false) {
+        return Err(ContractError::BadRequest {
+            msg: "note must not be empty".into(),
+        });
+    }
+    let tags = tags.map(validate_tags).transpose()?;
+    if note.is_none() && photo_cid.is_none() && tags.as_ref().map(|t| t.is_empty()).unwrap_or(true)
+    {
+        return Err(ContractError::BadRequest {
+            msg: "at least one of note/photo_cid/tags is required".into(),
+        });
+    }
+
+    let rec = RECORDS.update(deps.storage, id, |maybe| -> Result<_, ContractError> {
+        let mut rec = maybe.ok_or(ContractError::NotFound)?;
+        rec.annotation_count += 1;
+        Ok(rec)
+    })?;
+    for tag in tags.iter().flatten() {
+        tag_record(deps.storage, &rec, tag)?;
+    }
+    let seq = NEXT_ANNOTATION_SEQ.may_load(deps.storage)?.unwrap_or(1);
+    ANNOTATIONS.save(
+        deps.storage,
+        (id, seq),
+        &Annotation {
+            at: env.block.time.seconds(),
+            by: info.sender.clone(),
+            note,
+            photo_cid,
+            tags,
+        },
+    )?;
+    NEXT_ANNOTATION_SEQ.save(deps.storage, &(seq + 1))?;
+    append_change(deps.storage, env.block.height, id, ChangeKind::Annotate)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "append_annotation")
+        .add_attribute("id", id.to_string())
+        .add_attribute("seq", seq.to_string())
+        .add_attribute("by", info.sender))
+}
+
+/// 正規化して重複を除く（空・空白入り・長すぎるタグはエラー）
+fn validate_tags(tags: Vec<String>) -> Result<Vec<String>, ContractError> {
+    if tags.len() > MAX_TAGS_PER_ANNOTATION {
+        return Err(ContractError::BadRequest {
+            msg: format!("at most {} tags per annotation", MAX_TAGS_PER_ANNOTATION),
+        });
+    }
+    let mut out: Vec<String> = Vec::with_capacity(tags.len());
+    for raw in tags {
+        let tag = normalize_tag(&raw);
+        if !valid_tag(&tag) {
+            return Err(ContractError::BadRequest {
+                msg: format!("invalid tag: {}", raw),
+            });
+        }
+        if !out.contains(&tag) {
+            out.push(tag);
+        }
+    }
+    Ok(out)
+}
+
+fn valid_tag(tag: &str) -> bool {
+    !tag.is_empty()
+        && tag.len() <= MAX_TAG_LEN
+        && !tag.chars().any(|c| c.is_whitespace() || c.is_control())
+}
+
+fn exec_remove_tag(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+    seq: u64,
+    tag: String,
+) -> Result<Response, ContractError> {
+    let mut annotation = ANNOTATIONS
+        .may_load(deps.storage, (id, seq))?
+        .ok_or(ContractError::NotFound)?;
+    if annotation.by != info.sender {
+        return Err(ContractError::Unauthorized);
+    }
+    let tag = normalize_tag(&tag);
+    let mut tags = annotation.tags.unwrap_or_default();
+    let i = tags
+        .iter()
+        .position(|t| *t == tag)
+        .ok_or(ContractError::NotFound)?;
+    tags.remove(i);
+    annotation.tags = if tags.is_empty() { None } else { Some(tags) };
+    ANNOTATIONS.save(deps.storage, (id, seq), &annotation)?;
+    append_change(deps.storage, env.block.height, id, ChangeKind::Annotate)?;
+
+    let rec = RECORDS.load(deps.storage, id)?;
+    untag_record(deps.storage, &rec, &tag)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_tag")
+        .add_attribute("id", id.to_string())
+        .add_attribute("seq", seq.to_string())
+        .add_attribute("tag", tag))
+}
+
+fn exec_verify(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+    taxon_id: String,
+    confidence: u8,
+) -> Result<Response, ContractError> {
+    ensure_verifier(&deps, &env, &info.sender)?;
+    if taxon_id.trim().is_empty() {
+        return Err(ContractError::BadRequest {
+            msg: "taxon_id must not be empty".into(),
+        });
+    }
+    if confidence > MAX_CONFIDENCE {
+        return Err(ContractError::BadRequest {
+            msg: format!("confidence must be within 0..={}", MAX_CONFIDENCE),
+        });
+    }
+    let mut rec = RECORDS
+        .may_load(deps.storage, id)?
+        .ok_or(ContractError::NotFound)?;
+    let now = env.block.time.seconds();
+    if let Some(prev) = BY_VERIFIER.may_load(deps.storage, (&info.sender, id))? {
+        archive_verification(deps.storage, id, prev, now, VerificationEnd::Superseded)?;
+        rec.verification_count -= 1;
+    }
+    let seq = NEXT_VERIFICATION_SEQ.may_load(deps.storage)?.unwrap_or(1);
+    VERIFICATIONS.save(
+        deps.storage,
+        (id, seq),
+        &VerificationEntry {
+            at: now,
+            verifier: info.sender.clone(),
+            taxon_id: taxon_id.clone(),
+            confidence,
+        },
+    )?;
+    NEXT_VERIFICATION_SEQ.save(deps.storage, &(seq + 1))?;
+    BY_VERIFIER.save(deps.storage, (&info.sender, id), &seq)?;
+    rec.verification_count += 1;
+    let rec = regrade(deps.storage, rec)?;
+    RECORDS.save(deps.storage, id, &rec)?;
+    append_change(deps.storage, env.block.height, id, ChangeKind::Verify)?;
+
+    let hooks = hook_msgs(
+        deps.storage,
+        ObservationHookMsg::Verified {
+            id,
+            verifier: info.sender.to_string(),
+            taxon_id,
+            confidence,
+            consensus_taxon: rec.consensus_taxon.clone(),
+            quality_grade: rec.quality_grade.clone(),
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_submessages(hooks)
+        .add_attribute("action", "verify")
+        .add_attribute("id", id.to_string())
+        .add_attribute("verifier", info.sender)
+        .add_attribute(
+            "quality_grade",
+            rec.quality_grade.as_ref().map_or("", |g| g.key()),
+        ))
+}
+
+/// 現在の見解（seq 順）
+fn current_verifications(store: &dyn Storage, id: u64) -> StdResult<Vec<(u64, VerificationEntry)>> {
+    VERIFICATIONS
+        .prefix(id)
+        .range(store, None, None, Order::Ascending)
+        .collect()
+}
+
+/// 現在の見解を履歴へ移す（BY_VERIFIER はそのまま）
+fn archive_verification(
+    store: &mut dyn Storage,
+    id: u64,
+    seq: u64,
+    ended_at: u64,
+    ended_by: VerificationEnd,
+) -> StdResult<()> {
+    let entry = VERIFICATIONS.load(store, (id, seq))?;
+    VERIFICATIONS.remove(store, (id, seq));
+    VERIFICATION_HISTORY.save(
+        store,
+        (id, seq),
+        &PastVerification {
+            seq,
+            entry,
+            ended_at,
+            ended_by,
+        },
+    )
+}
+
+fn exec_retract_verification(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    let mut rec = RECORDS
+        .may_load(deps.storage, id)?
+        .ok_or(ContractError::NotFound)?;
+    let prev = BY_VERIFIER
+        .may_load(deps.storage, (&info.sender, id))?
+        .ok_or(ContractError::NotFound)?;
+    rec.verification_count -= 1;
+    archive_verification(
+        deps.storage,
+        id,
+        prev,
+        env.block.time.seconds(),
+        VerificationEnd::Retracted,
+    )?;
+    BY_VERIFIER.remove(deps.storage, (&info.sender, id));
+    let rec = regrade(deps.storage, rec)?;
+    RECORDS.save(deps.storage, id, &rec)?;
+    append_change(deps.storage, env.block.height, id, ChangeKind::Verify)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "retract_verification")
+        .add_attribute("id", id.to_string())
+        .add_attribute("verifier", info.sender)
+        .add_attribute(
+            "quality_grade",
+            rec.quality_grade.as_ref().map_or("", |g| g.key()),
+        ))
+}
+
+/* ============== consensus ============== */
+
+/// 合意 taxon とグレードを計算して設定（インデックスは触らない）
+fn graded(store: &dyn Storage, mut rec: StoredRecord) -> StdResult<StoredRecord> {
+    let params = CONSENSUS_PARAMS.may_load(store)?.unwrap_or_default();
+    let current: Vec<VerificationEntry> = current_verifications(store, rec.id)?
+        .into_iter()
+        .map(|(_, v)| v)
+        .collect();
+    rec.consensus_taxon = consensus::consensus_taxon(&current, &params);
+    rec.quality_grade = Some(consensus::quality_grade(
+        &rec,
+        rec.consensus_taxon.as_deref(),
+    ));
+    Ok(rec)
+}
+
+/// インデックス済みのレコードを再計算し、グレードが変われば BY_GRADE_TIME を付け替える
+fn regrade(store: &mut dyn Storage, rec: StoredRecord) -> StdResult<StoredRecord> {
+    let before = rec.quality_grade.clone();
+    let rec = graded(store, rec)?;
+    if before != rec.quality_grade {
+        if let Some(g) = before {
+            BY_GRADE_TIME.remove(store, (g.key().to_string(), rec.observed_at, rec.id));
+        }
+        if let Some(g) = &rec.quality_grade {
+            BY_GRADE_TIME.save(store, (g.key().to_string(), rec.observed_at, rec.id), &())?;
+        }
+    }
+    Ok(rec)
+}
+
+fn validate_consensus_params(params: &ConsensusParams) -> Result<(), ContractError> {
+    if !(51..=100).contains(&params.threshold_pct) {
+        return Err(ContractError::BadRequest {
+            msg: "threshold_pct must be within 51..=100".into(),
+        });
+    }
+    if params.min_verifiers == 0 {
+        return Err(ContractError::BadRequest {
+            msg: "min_verifiers must be at least 1".into(),
+        });
+    }
+    Ok(())
+}
+
+fn exec_set_consensus_params(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    params: ConsensusParams,
+) -> Result<Response, ContractError> {
+    ensure_admin(&deps, &env, &info.sender)?;
+    validate_consensus_params(&params)?;
+    CONSENSUS_PARAMS.save(deps.storage, &params)?;
+    Ok(Response::new()
+        .add_attribute("action", "set_consensus_params")
+        .add_attribute("threshold_pct", params.threshold_pct.to_string())
+        .add_attribute("min_verifiers", params.min_verifiers.to_string()))
+}
+
+fn exec_recompute_grades(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    ensure_admin(&deps, &env, &info.sender)?;
+    let limit = page_limit(deps.storage, limit)?;
+    let ids: Vec<u64> = RECORDS
+        .keys(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .collect::<StdResult<_>>()?;
+    let mut changed = 0u64;
+    for id in &ids {
+        let rec = RECORDS.load(deps.storage, *id)?;
+        let before = (rec.consensus_taxon.clone(), rec.quality_grade.clone());
+        let rec = regrade(deps.storage, rec)?;
+        if before != (rec.consensus_taxon.clone(), rec.quality_grade.clone()) {
+            RECORDS.save(deps.storage, *id, &rec)?;
+            append_change(deps.storage, env.block.height, *id, ChangeKind::Regrade)?;
+            changed += 1;
+        }
+    }
+    let next = if ids.len() == limit { ids.last() } else { None };
+    Ok(Response::new()
+        .add_attribute("action", "recompute_grades")
+        .add_attribute("scanned", ids.len().to_string())
+        .add_attribute("changed", changed.to_string())
+        .add_attribute(
+            "next_start_after",
+            next.map_or(String::new(), |id| id.to_string()),
+        ))
+}
+
+fn exec_hide(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+    reason: Option<String>,
+    spam: bool,
+) -> Result<Response, ContractError> {
+    ensure_moderator(&deps, &env, &info.sender)?;
+    let mut rec = RECORDS
+        .may_load(deps.storage, id)?
+        .ok_or(ContractError::NotFound)?;
+    if rec.hidden {
+        return Err(ContractError::BadRequest {
+            msg: "record is already hidden".into(),
+        });
+    }
+    adjust_counters(deps.storage, &rec, false)?;
+    let forfeited = spam && mark_forfeited(deps.storage, &env, id, true)?;
+    rec.hidden = true;
+    rec.hidden_reason = reason.clone();
+    RECORDS.save(deps.storage, id, &rec)?;
+    append_change(deps.storage, env.block.height, id, ChangeKind::Hide)?;
+
+    let resolved = resolve_open_reports(deps.storage, id, ReportStatus::Actioned, &info.sender)?;
+    append_audit(
+        deps.storage,
+        &env,
+        id,
+        ModerationAction::Hide,
+        &info.sender,
+        None,
+        reason,
+    )?;
+
+    let hooks = hook_msgs(
+        deps.storage,
+        ObservationHookMsg::Hidden {
+            id,
+            by: info.sender.to_string(),
+            spam,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_submessages(hooks)
+        .add_attribute("action", "hide")
+        .add_attribute("id", id.to_string())
+        .add_attribute("spam", spam.to_string())
+        .add_attribute("deposit_forfeited", forfeited.to_string())
+        .add_attribute("resolved_reports", resolved.to_string()))
+}
+
+/* ============== moderation ============== */
+
+/// 監査ログに 1 件追記し、その seq を返す
+fn append_audit(
+    store: &mut dyn Storage,
+    env: &Env,
+    record_id: u64,
+    action: ModerationAction,
+    actor: &Addr,
+    report_id: Option<u64>,
+    reason: Option<String>,
+) -> StdResult<u64> {
+    let seq = NEXT_AUDIT_SEQ.may_load(store)?.unwrap_or(1);
+    AUDIT_LOG.save(
+        store,
+        seq,
+        &AuditEntry {
+            seq,
+            record_id,
+            action,
+            actor: actor.clone(),
+            report_id,
+            reason,
+            block_time: env.block.time.seconds(),
+            block_height: env.block.height,
+        },
+    )?;
+    AUDIT_BY_RECORD.save(store, (record_id, seq), &())?;
+    NEXT_AUDIT_SEQ.save(store, &(seq + 1))?;
+    Ok(seq)
+}
+
+/// 変更フィードに 1 件追記する
+fn append_change(
+    store: &mut dyn Storage,
+    block_height: u64,
+    id: u64,
+    kind: ChangeKind,
+) -> StdResult<u64> {
+    let seq = NEXT_CHANGE_SEQ.may_load(store)?.unwrap_or(1);
+    CHANGES.save(
+        store,
+        seq,
+        &Change {
+            seq,
+            id,
+            kind,
+            block_height,
+        },
+    )?;
+    NEXT_CHANGE_SEQ.save(store, &(seq + 1))?;
+    Ok(seq)
+}
+
+/// 通報を未対応キューから外して status にする
+fn close_report(
+    store: &mut dyn Storage,
+    report: &mut Report,
+    status: ReportStatus,
+    by: &Addr,
+) -> StdResult<()> {
+    report.status = status;
+    report.resolved_by = Some(by.clone());
+    REPORTS.save(store, report.id, report)?;
+    OPEN_REPORTS.remove(store, report.id);
+    OPEN_REPORT_BY_REPORTER.remove(store, (report.record_id, &report.reporter));
+    Ok(())
+}
+
+/// レコードに対する未対応の通報をすべて status にし、件数を返す
+fn resolve_open_reports(
+    store: &mut dyn Storage,
+    record_id: u64,
+    status: ReportStatus,
+    by: &Addr,
+) -> StdResult<u64> {
+    let report_ids: Vec<u64> = REPORTS_BY_RECORD
+        .prefix(record_id)
+        .keys(store, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
+    let mut n = 0u64;
+    for rid in report_ids {
+        if !OPEN_REPORTS.has(store, rid) {
+            continue;
+        }
+        let mut report = REPORTS.load(store, rid)?;
+        close_report(store, &mut report, status.clone(), by)?;
+        n += 1;
+    }
+    Ok(n)
+}
+
+fn exec_report(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+    category: ReportCategory,
+    note: Option<String>,
+) -> Result<Response, ContractError> {
+    let rec = RECORDS
+        .may_load(deps.storage, id)?
+        .ok_or(ContractError::NotFound)?;
+    if rec.hidden {
+        return Err(ContractError::BadRequest {
+            msg: "record is already hidden".into(),
+        });
+    }
+    if OPEN_REPORT_BY_REPORTER.has(deps.storage, (id, &info.sender)) {
+        return Err(ContractError::BadRequest {
+            msg: "an open report from this sender already exists".into(),
+        });
+    }
+    let note = note.filter(|n| !n.trim().is_empty());
+
+    let report_id = NEXT_REPORT_ID.may_load(deps.storage)?.unwrap_or(1);
+    let report = Report {
+        id: report_id,
+        record_id: id,
+        reporter: info.sender.clone(),
+        category,
+        note: note.clone(),
+        block_time: env.block.time.seconds(),
+        block_height: env.block.height,
+        status: ReportStatus::Open,
+        resolved_by: None,
+    };
+    REPORTS.save(deps.storage, report_id, &report)?;
+    OPEN_REPORTS.save(deps.storage, report_id, &())?;
+    REPORTS_BY_RECORD.save(deps.storage, (id, report_id), &())?;
+    OPEN_REPORT_BY_REPORTER.save(deps.storage, (id, &info.sender), &report_id)?;
+    NEXT_REPORT_ID.save(deps.storage, &(report_id + 1))?;
+    append_audit(
+        deps.storage,
+        &env,
+        id,
+        ModerationAction::Report,
+        &info.sender,
+        Some(report_id),
+        note,
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "report")
+        .add_attribute("id", id.to_string())
+        .add_attribute("report_id", report_id.to_string()))
+}
+
+fn exec_unhide(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+    reason: Option<String>,
+) -> Result<Response, ContractError> {
+    ensure_moderator(&deps, &env, &info.sender)?;
+    let mut rec = RECORDS
+        .may_load(deps.storage, id)?
+        .ok_or(ContractError::NotFound)?;
+    if !rec.hidden {
+        return Err(ContractError::BadRequest {
+            msg: "record is not hidden".into(),
+        });
+    }
+    rec.hidden = false;
+    rec.hidden_reason = None;
+    RECORDS.save(deps.storage, id, &rec)?;
+    append_change(deps.storage, env.block.height, id, ChangeKind::Unhide)?;
+    adjust_counters(deps.storage, &rec, true)?;
+    // spam Hide の没収は期間内なら取り消す
+    let restored = mark_forfeited(deps.storage, &env, id, false)?;
+    append_audit(
+        deps.storage,
+        &env,
+        id,
+        ModerationAction::Unhide,
+        &info.sender,
+        None,
+        reason,
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "unhide")
+        .add_attribute("id", id.to_string())
+        .add_attribute("deposit_restored", restored.to_string()))
+}
+
+fn exec_dismiss_report(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    report_id: u64,
+    reason: Option<String>,
+) -> Result<Response, ContractError> {
+    ensure_moderator(&deps, &env, &info.sender)?;
+    let mut report = REPORTS
+        .may_load(deps.storage, report_id)?
+        .ok_or(ContractError::NotFound)?;
+    if report.status != ReportStatus::Open {
+        return Err(ContractError::BadRequest {
+            msg: "report is not open".into(),
+        });
+    }
+    close_report(
+        deps.storage,
+        &mut report,
+        ReportStatus::Dismissed,
+        &info.sender,
+    )?;
+    append_audit(
+        deps.storage,
+        &env,
+        report.record_id,
+        ModerationAction::DismissReport,
+        &info.sender,
+        Some(report_id),
+        reason,
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "dismiss_report")
+        .add_attribute("report_id", report_id.to_string())
+        .add_attribute("id", report.record_id.to_string()))
+}
+
+/* ============== query entry ============== */
+
+/// 検索インデックスや集計カウンタを読むクエリ
+fn reads_indexes(msg: &QueryMsg) -> bool {
+    matches!(
+        msg,
+        QueryMsg::List { .. }
+            | QueryMsg::Count { .. }
+            | QueryMsg::ListBySender { .. }
+            | QueryMsg::ListByTag { .. }
+            | QueryMsg::TagCounts { .. }
+            | QueryMsg::ListInBox { .. }
+            | QueryMsg::ListNear { .. }
+            | QueryMsg::StatsMonthly { .. }
+            | QueryMsg::StatsDaily { .. }
+            | QueryMsg::StatsWeekly { .. }
+            | QueryMsg::PhenologySummary { .. }
+    )
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    // 移行の途中は索引とカウンタが作りかけなので、それを読むクエリには答えない
+    if reads_indexes(&msg) && MIGRATION.exists(deps.storage) {
+        return Err(StdError::generic_err("migration in progress"));
+    }
+    match msg {
+        QueryMsg::Get { id } => to_json_binary(&query_get(deps, id)?),
+        QueryMsg::RecordHistory {
+            id,
+            start_after,
+            limit,
+        } => to_json_binary(&query_record_history(deps, id, start_after, limit)?),
+        QueryMsg::List {
+            species,
+            phenophase,
+            quality_grade,
+            geohash_prefix,
+            start,
+            end,
+            limit,
+            start_after,
+        } => to_json_binary(&query_list(
+            deps,
+            RecordFilter::new(
+                species,
+                phenophase,
+                quality_grade,
+                geohash_prefix,
+                start,
+                end,
+            ),
+            limit,
+            start_after,
+        )?),
+        QueryMsg::Count {
+            species,
+            phenophase,
+            quality_grade,
+            geohash_prefix,
+            start,
+            end,
+        } => to_json_binary(&query_count(
+            deps,
+            RecordFilter::new(
+                species,
+                phenophase,
+                quality_grade,
+                geohash_prefix,
+                start,
+                end,
+            ),
+        )?),
+        QueryMsg::StatsMonthly {
+            species,
+            phenophase,
+            quality_grade,
+            geohash_prefix,
+            year,
+            tz_offset_minutes,
+        } => to_json_binary(&query_stats_monthly(
+            deps,
+            RecordFilter::new(
+                species,
+                phenophase,
+                quality_grade,
+                geohash_prefix,
+                None,
+                None,
+            ),
+            year,
+            tz_offset_minutes,
+        )?),
+        QueryMsg::StatsDaily {
+            species,
+            phenophase,
+            quality_grade,
+            geohash_prefix,
+            year,
+            month,
+            tz_offset_minutes,
+        } => to_json_binary(&query_stats_daily(
+            deps,
+            RecordFilter::new(
+                species,
+                phenophase,
+                quality_grade,
+                geohash_prefix,
+                None,
+                None,
+            ),
+            year,
+            month,
+            tz_offset_minutes,
+        )?),
+        QueryMsg::StatsWeekly {
+            species,
+            phenophase,
+            quality_grade,
+            geohash_prefix,
+            year,
+            tz_offset_minutes,
+        } => to_json_binary(&query_stats_weekly(
+            deps,
+            RecordFilter::new(
+                species,
+                phenophase,
+                quality_grade,
+                geohash_prefix,
+                None,
+                None,
+            ),
+            year,
+            tz_offset_minutes,
+        )?),
+        QueryMsg::PhenologySummary {
+            species,
+            phenophase,
+            quality_grade,
+            geohash_prefix,
+            years,
+            tz_offset_minutes,
+            require_consensus,
+        } => to_json_binary(&query_phenology_summary(
+            deps,
+            RecordFilter::new(
+                species,
+                phenophase,
+                quality_grade,
+                geohash_prefix,
+                None,
+                None,
+            ),
+            years,
+            tz_offset_minutes,
+            require_consensus.unwrap_or(false),
+        )?),
+        QueryMsg::Tombstones { start_after, limit } => {
+            to_json_binary(&query_tombstones(deps, start_after, limit)?)
+        }
+        QueryMsg::OpenReports { start_after, limit } => {
+            to_json_binary(&query_open_reports(deps, start_after, limit)?)
+        }
+        QueryMsg::ChangesSince { seq, limit } => {
+            to_json_binary(&query_changes_since(deps, seq, limit)?)
+        }
+        QueryMsg::ModerationHistory {
+            id,
+            start_after,
+            limit,
+        } => to_json_binary(&query_moderation_history(deps, id, start_after, limit)?),
+        QueryMsg::Roles { addr } => to_json_binary(&query_roles(deps, env, addr)?),
+        QueryMsg::ListRoleMembers {
+            role,
+            start_after,
+            limit,
+        } => to_json_binary(&query_list_role_members(
+            deps,
+            env,
+            role,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::PendingAdmin {} => to_json_binary(&PendingAdminResp {
+            pending: PENDING_ADMIN.may_load(deps.storage)?,
+        }),
+        QueryMsg::ListByTag {
+            tag,
+            start_after,
+            limit,
+        } => to_json_binary(&query_list_by_tag(deps, tag, start_after, limit)?),
+        QueryMsg::TagCounts {
+            prefix,
+            start_after,
+            limit,
+        } => to_json_binary(&query_tag_counts(deps, prefix, start_after, limit)?),
+        QueryMsg::Annotations {
+            id,
+            start_after,
+            limit,
+        } => to_json_binary(&query_annotations(deps, id, start_after, limit)?),
+        QueryMsg::Verifications {
+            id,
+            start_after,
+            limit,
+        } => to_json_binary(&query_verifications(deps, id, start_after, limit)?),
+        QueryMsg::VerificationHistory {
+            id,
+            start_after,
+            limit,
+        } => to_json_binary(&query_verification_history(deps, id, start_after, limit)?),
+        QueryMsg::VerificationsBy {
+            verifier,
+            start_after,
+            limit,
+        } => to_json_binary(&query_verifications_by(deps, verifier, start_after, limit)?),
+        QueryMsg::Hooks { start_after, limit } => {
+            to_json_binary(&query_hooks(deps, start_after, limit)?)
+        }
+        QueryMsg::NftMinter {} => to_json_binary(&NFT_MINTER.may_load(deps.storage)?),
+        QueryMsg::RecordByToken { token_id } => {
+            to_json_binary(&query_record_by_token(deps, token_id)?)
+        }
+        QueryMsg::Gate {} => to_json_binary(&GATE.may_load(deps.storage)?),
+        QueryMsg::SpamPolicy {} => {
+            to_json_binary(&SPAM_POLICY.may_load(deps.storage)?.unwrap_or_default())
+        }
+        QueryMsg::QuotaUsage { addr } => to_json_binary(&query_quota_usage(deps, env, addr)?),
+        QueryMsg::Deposits { owner, limit } => to_json_binary(&query_deposits(deps, owner, limit)?),
+        QueryMsg::ConsensusParams {} => {
+            to_json_binary(&CONSENSUS_PARAMS.may_load(deps.storage)?.unwrap_or_default())
+        }
+        QueryMsg::ListBySender {
+            sender,
+            start,
+            end,
+            limit,
+            start_after,
+            order,
+        } => to_json_binary(&query_list_by_sender(
+            deps,
+            sender,
+            start,
+            end,
+            limit,
+            start_after,
+            order,
+        )?),
+        QueryMsg::ListInBox {
+            min_lat,
+            min_lon,
+            max_lat,
+            max_lon,
+            species,
+            start,
+            end,
+            limit,
+            start_after,
+        } => to_json_binary(&query_list_in_box(
+            deps,
+            geohash::BoundingBox {
+                min_lat: parse_coord("min_lat", &min_lat)?,
+                min_lon: parse_coord("min_lon", &min_lon)?,
+                max_lat: parse_coord("max_lat", &max_lat)?,
+                max_lon: parse_coord("max_lon", &max_lon)?,
+            },
+            species,
+            start,
+            end,
+            limit,
+            start_after,
+        )?),
+        QueryMsg::ListNear {
+            lat,
+            lon,
+            radius_m,
+            species,
+            start,
+            end,
+            limit,
+            start_after,
+        } => to_json_binary(&query_list_near(
+            deps,
+            parse_coord("lat", &lat)?,
+            parse_coord("lon", &lon)?,
+            radius_m,
+            species,
+            start,
+            end,
+            limit,
+            start_after,
+        )?),
+        QueryMsg::Config {} => to_json_binary(&CONFIG.load(deps.storage)?),
+        QueryMsg::PayloadSchema {} => to_json_binary(&PayloadSchemaResp {
+            schema: PAYLOAD_SCHEMA.may_load(deps.storage)?,
+        }),
+        QueryMsg::MigrationStatus {} => {
+            let v = stored_contract_version(deps.storage)?;
+            to_json_binary(&MigrationStatusResp {
+                contract: v.contract,
+                version: v.version,
+                migration: MIGRATION.may_load(deps.storage)?,
+            })
+        }
+        QueryMsg::DecodeGeohash { geohash } => to_json_binary(&query_decode_geohash(geohash)?),
+    }
+}
+
+fn query_get(deps: Deps, id: u64) -> StdResult<GetResp> {
+    let rec = RECORDS.may_load(deps.storage, id)?;
+    Ok(GetResp { record: rec })
+}
+
+fn query_hooks(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<HooksResp> {
+    let limit = page_limit(deps.storage, limit)?;
+    let start = start_after
+        .map(|s| deps.api.addr_validate(&s))
+        .transpose()?;
+    let hooks = HOOKS
+        .keys(
+            deps.storage,
+            start.as_ref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(String::from))
+        .collect::<StdResult<_>>()?;
+    Ok(HooksResp { hooks })
+}
+
+fn query_record_by_token(deps: Deps, token_id: String) -> StdResult<GetResp> {
+    let rec = match RECORD_BY_TOKEN.may_load(deps.storage, &token_id)? {
+        Some(id) => RECORDS.may_load(deps.storage, id)?,
+        None => None,
+    };
+    Ok(GetResp { record: rec })
+}
+
+fn query_record_history(
+    deps: Deps,
+    id: u64,
+    start_after: Option<u32>,
+    limit: Option<u32>,
+) -> StdResult<RecordHistoryResp> {
+    let limit = page_limit(deps.storage, limit)?;
+    let revisions = RECORD_HISTORY
+        .prefix(id)
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(_, rev)| rev))
+        .collect::<StdResult<_>>()?;
+    Ok(RecordHistoryResp { revisions })
+}
+
+fn query_tombstones(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<TombstonesResp> {
+    let limit = page_limit(deps.storage, limit)?;
+    let tombstones: Vec<Tombstone> = TOMBSTONES
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(_, t)| t))
+        .collect::<StdResult<_>>()?;
+    let next = if limit > 0 && tombstones.len() == limit {
+        tombstones.last().map(|t| t.id)
+    } else {
+        None
+    };
+    Ok(TombstonesResp {
+        tombstones,
+        next_start_after: next,
+    })
+}
+
+fn query_open_reports(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ReportsResp> {
+    let limit = page_limit(deps.storage, limit)?;
+    let reports: Vec<Report> = OPEN_REPORTS
+        .keys(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|rid| REPORTS.load(deps.storage, rid?))
+        .collect::<StdResult<_>>()?;
+    let next = if limit > 0 && reports.len() == limit {
+        reports.last().map(|r| r.id)
+    } else {
+        None
+    };
+    Ok(ReportsResp {
+        reports,
+        next_start_after: next,
+    })
+}
+
+fn query_changes_since(deps: Deps, seq: u64, limit: Option<u32>) -> StdResult<ChangesResp> {
+    let limit = page_limit(deps.storage, limit)?;
+    let changes: Vec<Change> = CHANGES
+        .range(
+            deps.storage,
+            Some(Bound::exclusive(seq)),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(_, c)| c))
+        .collect::<StdResult<_>>()?;
+    let next = if limit > 0 && changes.len() == limit {
+        changes.last().map(|c| c.seq)
+    } else {
+        None
+    };
+    Ok(ChangesResp {
+        changes,
+        next_seq: next,
+        latest_seq: NEXT_CHANGE_SEQ.may_load(deps.storage)?.unwrap_or(1) - 1,
+    })
+}
+
+fn query_moderation_history(
+    deps: Deps,
+    id: u64,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ModerationHistoryResp> {
+    let limit = page_limit(deps.storage, limit)?;
+    let entries: Vec<AuditEntry> = AUDIT_BY_RECORD
+        .prefix(id)
+        .keys(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|seq| AUDIT_LOG.load(deps.storage, seq?))
+        .collect::<StdResult<_>>()?;
+    let next = if limit > 0 && entries.len() == limit {
+        entries.last().map(|e| e.seq)
+    } else {
+        None
+    };
+    Ok(ModerationHistoryResp {
+        entries,
+        next_start_after: next,
+    })
+}
+
+fn query_roles(deps: Deps, env: Env, addr: String) -> StdResult<RolesResp> {
+    let a = deps.api.addr_validate(&addr)?;
+    let now = env.block.time.seconds();
+    let mut roles = vec![];
+    for role in Role::ALL {
+        if let Some(grant) = ROLES.may_load(deps.storage, (role.key(), &a))? {
+            if grant.is_active(now) {
+                roles.push(RoleEntry { role, grant });
+            }
+        }
+    }
+    Ok(RolesResp {
+        addr: a.to_string(),
+        roles,
+    })
+}
+
+fn query_list_role_members(
+    deps: Deps,
+    env: Env,
+    role: Role,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<RoleMembersResp> {
+    let limit = page_limit(deps.storage, limit)?;
+    let now = env.block.time.seconds();
+    let start = match &start_after {
+        Some(s) => Some(deps.api.addr_validate(s)?),
+        None => None,
+    };
+    let mut members = Vec::with_capacity(limit);
+    let mut last: Option<Addr> = None;
+    if limit > 0 {
+        for item in ROLES.prefix(role.key()).range(
+            deps.storage,
+            start.as_ref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        ) {
+            let (addr, grant) = item?;
+            if !grant.is_active(now) {
+                continue;
+            }
+            members.push(RoleMember {
+                addr: addr.to_string(),
+                grant,
+            });
+            last = Some(addr);
+            if members.len() == limit {
+                break;
+            }
+        }
+    }
+    let next = if limit > 0 && members.len() == limit {
+        last.map(|a| a.to_string())
+    } else {
+        None
+    };
+    Ok(RoleMembersResp {
+        members,
+        next_start_after: next,
+    })
+}
+
+fn query_quota_usage(deps: Deps, env: Env, addr: String) -> StdResult<QuotaUsageResp> {
+    let addr = deps.api.addr_validate(&addr)?;
+    let quota = SPAM_POLICY.may_load(deps.storage)?.and_then(|p| p.quota);
+    Ok(match quota {
+        Some(q) => QuotaUsageResp {
+            used: quota_used(deps, &env, &addr, &q)?,
+            max_stores: Some(q.max_stores),
+            window_secs: Some(q.window_secs),
+        },
+        None => QuotaUsageResp {
+            used: 0,
+            max_stores: None,
+            window_secs: None,
+        },
+    })
+}
+
+fn query_deposits(deps: Deps, owner: String, limit: Option<u32>) -> StdResult<DepositsResp> {
+    let limit = page_limit(deps.storage, limit)?;
+    let owner = deps.api.addr_validate(&owner)?;
+    let deposits = DEPOSITS_BY_OWNER
+        .sub_prefix(&owner)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.and_then(|(_, id)| DEPOSITS.load(deps.storage, id)))
+        .collect::<StdResult<_>>()?;
+    Ok(DepositsResp { deposits })
+}
+
+fn query_annotations(
+    deps: Deps,
+    id: u64,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<AnnotationsResp> {
+    let limit = page_limit(deps.storage, limit)?;
+    let annotations: Vec<AnnotationItem> = ANNOTATIONS
+        .prefix(id)
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(seq, annotation)| AnnotationItem { seq, annotation }))
+        .collect::<StdResult<_>>()?;
+    let next = if limit > 0 && annotations.len() == limit {
+        annotations.last().map(|a| a.seq)
+    } else {
+        None
+    };
+    Ok(AnnotationsResp {
+        annotations,
+        next_start_after: next,
+    })
+}
+
+fn query_verifications(
+    deps: Deps,
+    id: u64,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<VerificationsResp> {
+    let limit = page_limit(deps.storage, limit)?;
+    let verifications: Vec<VerificationItem> = VERIFICATIONS
+        .prefix(id)
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(seq, entry)| VerificationItem { seq, entry }))
+        .collect::<StdResult<_>>()?;
+    let next = if limit > 0 && verifications.len() == limit {
+        verifications.last().map(|v| v.seq)
+    } else {
+        None
+    };
+    Ok(VerificationsResp {
+        verifications,
+        next_start_after: next,
+    })
+}
+
+fn query_verification_history(
+    deps: Deps,
+    id: u64,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<VerificationHistoryResp> {
+    let limit = page_limit(deps.storage, limit)?;
+    let entries: Vec<PastVerification> = VERIFICATION_HISTORY
+        .prefix(id)
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(_, v)| v))
+        .collect::<StdResult<_>>()?;
+    let next = if limit > 0 && entries.len() == limit {
+        entries.last().map(|v| v.seq)
+    } else {
+        None
+    };
+    Ok(VerificationHistoryResp {
+        entries,
+        next_start_after: next,
+    })
+}
+
+fn query_verifications_by(
+    deps: Deps,
+    verifier: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<VerificationsByResp> {
+    let limit = page_limit(deps.storage, limit)?;
+    let verifier = deps.api.addr_validate(&verifier)?;
+    let hits: Vec<(u64, u64)> = BY_VERIFIER
+        .prefix(&verifier)
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .collect::<StdResult<_>>()?;
+
+    let mut out = Vec::with_capacity(hits.len());
+    for (id, seq) in &hits {
+        let rec = RECORDS.load(deps.storage, *id)?;
+        let entry = VERIFICATIONS.load(deps.storage, (*id, *seq))?;
+        let agrees = rec.consensus_taxon.as_ref().map(|c| c == &entry.taxon_id);
+        out.push(VerifierOpinion {
+            record_id: *id,
+            seq: *seq,
+            entry,
+            consensus_taxon: rec.consensus_taxon,
+            agrees,
+        });
+    }
+    let next = if limit > 0 && hits.len() == limit {
+        hits.last().map(|(id, _)| *id)
+    } else {
+        None
+    };
+    Ok(VerificationsByResp {
+        verifications: out,
+        next_start_after: next,
+    })
+}
+
+fn query_decode_geohash(input: String) -> StdResult<DecodeGeohashResp> {
+    let hash = input.trim().to_ascii_lowercase();
+    let bbox = geohash::decode_bbox(&hash)
+        .ok_or_else(|| StdError::generic_err(format!("invalid geohash: {}", input)))?;
+    let (center_lat, center_lon) = bbox.center();
+    Ok(DecodeGeohashResp {
+        geohash: hash,
+        min_lat: bbox.min_lat.to_string(),
+        min_lon: bbox.min_lon.to_string(),
+        max_lat: bbox.max_lat.to_string(),
+        max_lon: bbox.max_lon.to_string(),
+        center_lat: center_lat.to_string(),
+        center_lon: center_lon.to_string(),
+    })
+}
+
+/* ============== list / count 共通 ============== */
+
+/// ページの最後のレコードから次ページの再開位置を作る
+fn cursor_of(rec: &StoredRecord) -> RecordCursor {
+    RecordCursor {
+        id: rec.id,
+        observed_at: rec.observed_at,
+        geohash: rec.geohash_prefix.clone(),
+    }
+}
+
+/// List / Count / Stats 共通の絞り込み条件（species / phenophase は正規化済み）
+#[derive(Clone)]
+struct RecordFilter {
+    species: Option<String>,
+    phenophase: Option<String>,
+    quality_grade: Option<QualityGrade>,
+    geohash_prefix: Option<String>,
+    start: u64,
+    end: u64,
+}
+
+impl RecordFilter {
+    fn new(
+        species: Option<String>,
+        phenophase: Option<String>,
+        quality_grade: Option<QualityGrade>,
+        geohash_prefix: Option<String>,
+        start: Option<u64>,
+        end: Option<u64>,
+    ) -> Self {
+        RecordFilter {
+            species: species.map(|s| normalize_species(&s)),
+            phenophase: phenophase.map(|p| normalize_phenophase(&p)),
+            quality_grade,
+            geohash_prefix,
+            start: start.unwrap_or(0),
+            end: end.unwrap_or(u64::MAX),
+        }
+    }
+
+    /// 非表示でなく、全条件に一致するか
+    fn matches(&self, rec: &StoredRecord) -> bool {
+        if rec.hidden {
+            return false;
+        }
+        if self.species.is_some() && rec.species != self.species {
+            return false;
+        }
+        if self.phenophase.is_some() && rec.phenophase != self.phenophase {
+            return false;
+        }
+        if self.quality_grade.is_some() && rec.quality_grade != self.quality_grade {
+            return false;
+        }
+        if let Some(geo) = &self.geohash_prefix {
+            if !rec.geohash_prefix.starts_with(geo.as_str()) {
+                return false;
+            }
+        }
+        rec.observed_at >= self.start && rec.observed_at <= self.end
+    }
+}
+
+/// (key, observed_at, id) 形式のインデックスを期間で走査。打ち切ったら false
+fn scan_keyed_index(
+    deps: Deps,
+    index: Map<(String, u64, u64), ()>,
+    key: &str,
+    filter: &RecordFilter,
+    resume: Option<RecordCursor>,
+    f: &mut impl FnMut(StoredRecord) -> bool,
+) -> StdResult<bool> {
+    let lower = match resume {
+        Some(r) => Bound::exclusive((key.to_string(), r.observed_at, r.id)),
+        None => Bound::inclusive((key.to_string(), filter.start, 0u64)),
+    };
+    let upper = Bound::inclusive((key.to_string(), filter.end, u64::MAX));
+    let iter = index.keys(deps.storage, Some(lower), Some(upper), Order::Ascending);
+    for item in iter {
+        let (_, _, id) = item?;
+        if let Some(rec) = RECORDS.may_load(deps.storage, id)? {
+            if filter.matches(&rec) && !f(rec) {
+                return Ok(false);
+            }
+        }
+    }
+    Ok(true)
+}
+
+/// filter に一致するレコードをインデックス順に走査する。f が false を返すと打ち切り。
+///
+/// - species 指定: BY_SPECIES_TIME を (observed_at, id) 順
+/// - phenophase 指定（species なし）: BY_PHENOPHASE_TIME を (observed_at, id) 順
+/// - quality_grade 指定（上記なし）: BY_GRADE_TIME を (observed_at, id) 順
+/// - geohash_prefix のみ: BY_GEOHASH_TIME を (geohash, observed_at, id) 順
+///   （セルごとに期間外のキーを読み飛ばす）
+/// - いずれもなし: BY_TIME を (observed_at, id) 順
+fn scan_records(
+    deps: Deps,
+    filter: &RecordFilter,
+    resume: Option<RecordCursor>,
+    mut f: impl FnMut(StoredRecord) -> bool,
+) -> StdResult<()> {
+    let (start, end) = (filter.start, filter.end);
+    if start > end {
+        return Ok(());
+    }
+
+    if let Some(sp) = &filter.species {
+        scan_keyed_index(deps, BY_SPECIES_TIME, sp, filter, resume, &mut f)?;
+    } else if let Some(ph) = &filter.phenophase {
+        scan_keyed_index(deps, BY_PHENOPHASE_TIME, ph, filter, resume, &mut f)?;
+    } else if let Some(g) = &filter.quality_grade {
+        scan_keyed_index(deps, BY_GRADE_TIME, g.key(), filter, resume, &mut f)?;
+    } else if let Some(geo) = &filter.geohash_prefix {
+        let precision = CONFIG.load(deps.storage)?.geohash_precision;
+        let (lo, hi) = geohash::prefix_range(geo, precision);
+        let upper = (hi, u64::MAX, u64::MAX);
+        let mut cursor = match resume {
+            Some(r) => Bound::exclusive((r.geohash, r.observed_at, r.id)),
+            None => Bound::inclusive((lo, start, 0u64)),
+        };
+        // セルごとに (geohash, start) へ飛び、end を超えたら次のセルへ
+        'seek: loop {
+            let iter = BY_GEOHASH_TIME.keys(
+                deps.storage,
+                Some(cursor),
+                Some(Bound::inclusive(upper.clone())),
+                Order::Ascending,
+            );
+            for item in iter {
+                let (g, t, id) = item?;
+                if t < start {
+                    cursor = Bound::inclusive((g, start, 0u64));
+                    continue 'seek;
+                }
+                if t > end {
+                    cursor = Bound::exclusive((g, u64::MAX, u64::MAX));
+                    continue 'seek;
+                }
+                if let Some(rec) = RECORDS.may_load(deps.storage, id)? {
+                    if filter.matches(&rec) && !f(rec) {
+                        break 'seek;
+                    }
+                }
+            }
+            break;
+        }
+    } else {
+        // time index: (observed_at, id)
+        let lower = match resume {
+            Some(r) => Bound::exclusive((r.observed_at, r.id)),
+            None => Bound::inclusive((start, 0u64)),
+        };
+        let upper = Bound::inclusive((end, u64::MAX));
+        let iter = BY_TIME.keys(deps.storage, Some(lower), Some(upper), Order::Ascending);
+        for item in iter {
+            let (_, id) = item?;
+            if let Some(rec) = RECORDS.may_load(deps.storage, id)? {
+                if filter.matches(&rec) && !f(rec) {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// ページングは (observed_at, id) 順（geohash_prefix のみ指定時は geohash が先頭）。
+/// start_after には前ページの next_start_after を渡す。
+fn query_list(
+    deps: Deps,
+    filter: RecordFilter,
+    limit: Option<u32>,
+    start_after: Option<RecordCursor>,
+) -> StdResult<ListResp> {
+    let limit = page_limit(deps.storage, limit)?;
+
+    let mut out: Vec<StoredRecord> = Vec::with_capacity(limit);
+    if limit > 0 {
+        scan_records(deps, &filter, start_after, |rec| {
+            out.push(rec);
+            out.len() < limit
+        })?;
+    }
+
+    let next = if limit > 0 && out.len() == limit {
+        out.last().map(cursor_of)
+    } else {
+        None
+    };
+    Ok(ListResp {
+        records: out,
+        next_start_after: next,
+    })
+}
+
+/// 期間・phenophase・quality_grade 指定なしで species / geohash_prefix のどちらか
+/// 一方までならカウンタから返す
+fn count_from_counters(deps: Deps, filter: &RecordFilter) -> StdResult<Option<u64>> {
+    if filter.start != 0
+        || filter.end != u64::MAX
+        || filter.phenophase.is_some()
+        || filter.quality_grade.is_some()
+    {
+        return Ok(None);
+    }
+    match (&filter.species, filter.geohash_prefix.as_deref()) {
+        (None, None) | (None, Some("")) => Ok(Some(CNT_TOTAL.may_load(deps.storage)?.unwrap_or(0))),
+        (Some(sp), None) => Ok(Some(CNT_SPECIES.may_load(deps.storage, sp)?.unwrap_or(0))),
+        (None, Some(geo)) => {
+            let precision = CONFIG.load(deps.storage)?.geohash_precision;
+            if geo.len() > precision as usize {
+                return Ok(Some(0));
+            }
+            Ok(Some(CNT_GEOHASH.may_load(deps.storage, geo)?.unwrap_or(0)))
+        }
+        (Some(_), Some(_)) => Ok(None),
+    }
+}
+
+fn query_count(deps: Deps, filter: RecordFilter) -> StdResult<CountResp> {
+    if let Some(count) = count_from_counters(deps, &filter)? {
+        return Ok(CountResp { count });
+    }
+
+    // カウンタで賄えない組み合わせは走査
+    let mut cnt: u64 = 0;
+    scan_records(deps, &filter, None, |_| {
+        cnt += 1;
+        true
+    })?;
+    Ok(CountResp { count: cnt })
+}
+
+fn query_list_by_sender(
+    deps: Deps,
+    sender: String,
+    start: Option<u64>,
+    end: Option<u64>,
+    limit: Option<u32>,
+    start_after: Option<RecordCursor>,
+    order: Option<SortOrder>,
+) -> StdResult<ListResp> {
+    let sender = deps.api.addr_validate(&sender)?;
+    let limit = page_limit(deps.storage, limit)?;
+    let lo = start.unwrap_or(0);
+    let hi = end.unwrap_or(u64::MAX);
+
+    let order = match order.unwrap_or(SortOrder::Asc) {
+        SortOrder::Asc => Order::Ascending,
+        SortOrder::Desc => Order::Descending,
+    };
+    let mut lower = Bound::inclusive((&sender, lo, 0u64));
+    let mut upper = Bound::inclusive((&sender, hi, u64::MAX));
+    if let Some(r) = start_after {
+        let key = Bound::exclusive((&sender, r.observed_at, r.id));
+        match order {
+            Order::Ascending => lower = key,
+            Order::Descending => upper = key,
+        }
+    }
+
+    let mut out: Vec<StoredRecord> = Vec::with_capacity(limit);
+    if limit > 0 && lo <= hi {
+        let iter = BY_SENDER_TIME.keys(deps.storage, Some(lower), Some(upper), order);
+        for item in iter {
+            let (_, _, id) = item?;
+            if let Some(rec) = RECORDS.may_load(deps.storage, id)? {
+                if rec.hidden {
+                    continue;
+                }
+                out.push(rec);
+                if out.len() == limit {
+                    break;
+                }
+            }
+        }
+    }
+
+    let next = if limit > 0 && out.len() == limit {
+        out.last().map(cursor_of)
+    } else {
+        None
+    };
+    Ok(ListResp {
+        records: out,
+        next_start_after: next,
+    })
+}
+
+fn query_list_by_tag(
+    deps: Deps,
+    tag: String,
+    start_after: Option<RecordCursor>,
+    limit: Option<u32>,
+) -> StdResult<ListResp> {
+    let limit = page_limit(deps.storage, limit)?;
+    let tag = normalize_tag(&tag);
+
+    let mut out: Vec<StoredRecord> = Vec::with_capacity(limit);
+    if limit > 0 {
+        let iter = BY_TAG.prefix(tag.as_str()).keys(
+            deps.storage,
+            start_after.map(|c| Bound::exclusive(c.id)),
+            None,
+            Order::Ascending,
+        );
+        for item in iter {
+            let id = item?;
+            if let Some(rec) = RECORDS.may_load(deps.storage, id)? {
+                if rec.hidden {
+                    continue;
+                }
+                out.push(rec);
+                if out.len() == limit {
+                    break;
+                }
+            }
+        }
+    }
+
+    let next = if limit > 0 && out.len() == limit {
+        out.last().map(cursor_of)
+    } else {
+        None
+    };
+    Ok(ListResp {
+        records: out,
+        next_start_after: next,
+    })
+}
+
+fn query_tag_counts(
+    deps: Deps,
+    prefix: Option<String>,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<TagCountsResp> {
+    let limit = page_limit(deps.storage, limit)?;
+    let prefix = prefix.map(|p| normalize_tag(&p)).unwrap_or_default();
+    let start_after = start_after.map(|s| normalize_tag(&s));
+    let lower = match &start_after {
+        Some(s) if s.as_str() >= prefix.as_str() => Bound::exclusive(s.as_str()),
+        _ => Bound::inclusive(prefix.as_str()),
+    };
+
+    let mut tags: Vec<TagCount> = Vec::with_capacity(limit);
+    if limit > 0 {
+        for item in CNT_TAG.range(deps.storage, Some(lower), None, Order::Ascending) {
+            let (tag, count) = item?;
+            if !tag.starts_with(&prefix) {
+                break;
+            }
+            tags.push(TagCount { tag, count });
+            if tags.len() == limit {
+                break;
+            }
+        }
+    }
+
+    let next = if limit > 0 && tags.len() == limit {
+        tags.last().map(|t| t.tag.clone())
+    } else {
+        None
+    };
+    Ok(TagCountsResp {
+        tags,
+        next_start_after: next,
+    })
+}
+
+/* ============== spatial (bbox / radius) ============== */
+
+fn parse_coord(name: &str, input: &str) -> StdResult<f64> {
+    parse_decimal(input)
+        .ok_or_else(|| StdError::generic_err(format!("{} must be a decimal string", name)))
+}
+
+fn validate_lat_lon(lat: f64, lon: f64) -> StdResult<()> {
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return Err(StdError::generic_err(
+            "lat must be -90..=90 and lon must be -180..=180",
+        ));
+    }
+    Ok(())
+}
+
+fn in_box(bbox: &geohash::BoundingBox, lat: f64, lon: f64) -> bool {
+    if lat < bbox.min_lat || lat > bbox.max_lat {
+        return false;
+    }
+    if bbox.min_lon <= bbox.max_lon {
+        lon >= bbox.min_lon && lon <= bbox.max_lon
+    } else {
+        lon >= bbox.min_lon || lon <= bbox.max_lon
+    }
+}
+
+fn query_list_in_box(
+    deps: Deps,
+    bbox: geohash::BoundingBox,
+    species: Option<String>,
+    start: Option<u64>,
+    end: Option<u64>,
+    limit: Option<u32>,
+    start_after: Option<RecordCursor>,
+) -> StdResult<ListResp> {
+    validate_lat_lon(bbox.min_lat, bbox.min_lon)?;
+    validate_lat_lon(bbox.max_lat, bbox.max_lon)?;
+    if bbox.min_lat > bbox.max_lat {
+        return Err(StdError::generic_err("min_lat must be <= max_lat"));
+    }
+    query_list_in_area(
+        deps,
+        &bbox,
+        |lat, lon| in_box(&bbox, lat, lon),
+        species,
+        start,
+        end,
+        limit,
+        start_after,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn query_list_near(
+    deps: Deps,
+    lat: f64,
+    lon: f64,
+    radius_m: u32,
+    species: Option<String>,
+    start: Option<u64>,
+    end: Option<u64>,
+    limit: Option<u32>,
+    start_after: Option<RecordCursor>,
+) -> StdResult<ListResp> {
+    validate_lat_lon(lat, lon)?;
+    if radius_m == 0 {
+        return Err(StdError::generic_err("radius_m must be > 0"));
+    }
+    let radius = f64::from(radius_m);
+    let bbox = geohash::bbox_around(lat, lon, radius);
+    query_list_in_area(
+        deps,
+        &bbox,
+        |la, lo| geohash::distance_m(lat, lon, la, lo) <= radius,
+        species,
+        start,
+        end,
+        limit,
+        start_after,
+    )
+}
+
+/// bbox を覆う geohash セルを BY_GEOHASH 上で順に走査し、
+/// payload.place の座標で厳密に絞り込む。
+/// ページングは (セル順, geohash, id) 順で、start_after の (geohash, id) から再開する。
+#[allow(clippy::too_many_arguments)]
+fn query_list_in_area(
+    deps: Deps,
+    bbox: &geohash::BoundingBox,
+    accept: impl Fn(f64, f64) -> bool,
+    species: Option<String>,
+    start: Option<u64>,
+    end: Option<u64>,
+    limit: Option<u32>,
+    start_after: Option<RecordCursor>,
+) -> StdResult<ListResp> {
+    let limit = page_limit(deps.storage, limit)?;
+    let filter = RecordFilter::new(species, None, None, None, start, end);
+    let precision = CONFIG.load(deps.storage)?.geohash_precision;
+    let cells = geohash::cover_bbox(bbox, precision);
+
+    // 再開位置: start_after の geohash が属するセルと、その (geohash, id)
+    let (first_cell, mut resume) = match start_after {
+        Some(c) => {
+            let idx = cells
+                .iter()
+                .position(|cell| c.geohash.starts_with(cell.as_str()))
+                .ok_or_else(|| StdError::generic_err("start_after is outside the area"))?;
+            (idx, Some((c.geohash, c.id)))
+        }
+        None => (0, None),
+    };
+
+    let mut out: Vec<StoredRecord> = Vec::with_capacity(limit);
+    'cells: for cell in cells.iter().skip(first_cell) {
+        let (lo, hi) = geohash::prefix_range(cell, precision);
+        let start_key = match resume.take() {
+            Some(key) => Bound::exclusive(key),
+            None => Bound::inclusive((lo, 0u64)),
+        };
+        let end_key = Bound::inclusive((hi, u64::MAX));
+        let iter = BY_GEOHASH.keys(
+            deps.storage,
+            Some(start_key),
+            Some(end_key),
+            Order::Ascending,
+        );
+        for item in iter {
+            let (_, id) = item?;
+            let rec = match RECORDS.may_load(deps.storage, id)? {
+                Some(r) => r,
+                None => continue,
+            };
+            if !filter.matches(&rec) {
+                continue;
+            }
+            match extract_lat_lon(&rec.payload) {
+                Some((lat, lon)) if accept(lat, lon) => {}
+                _ => continue,
+            }
+            out.push(rec);
+            if out.len() == limit {
+                break 'cells;
+            }
+        }
+    }
+
+    let next = if limit > 0 && out.len() == limit {
+        out.last().map(cursor_of)
+    } else {
+        None
+    };
+    Ok(ListResp {
+        records: out,
+        next_start_after: next,
+    })
+}
+
+/* ============== stats（暦日ベース, tz_offset_minutes でローカル日付） ============== */
+
+fn validate_tz_offset(tz_offset_minutes: Option<i32>) -> StdResult<i32> {
+    let tz = tz_offset_minutes.unwrap_or(0);
+    if tz.abs() > calendar::MAX_TZ_OFFSET_MINUTES {
+        return Err(StdError::generic_err(format!(
+            "tz_offset_minutes must be within ±{}",
+            calendar::MAX_TZ_OFFSET_MINUTES
+        )));
+    }
+    Ok(tz)
+}
+
+fn query_stats_monthly(
+    deps: Deps,
+    filter: RecordFilter,
+    year: u32,
+    tz_offset_minutes: Option<i32>,
+) -> StdResult<StatsMonthlyResp> {
+    let tz = validate_tz_offset(tz_offset_minutes)?;
+    let y = i64::from(year);
+    let mut months = [0u64; 12];
+
+    // UTC かつ geohash / phenophase / quality_grade 指定なしは月次カウンタから
+    if tz == 0
+        && filter.geohash_prefix.is_none()
+        && filter.phenophase.is_none()
+        && filter.quality_grade.is_none()
+    {
+        for (i, slot) in months.iter_mut().enumerate() {
+            let m = i as u8 + 1;
+            *slot = match &filter.species {
+                Some(sp) => CNT_SPECIES_MONTH
+                    .may_load(deps.storage, (sp.as_str(), year, m))?
+                    .unwrap_or(0),
+                None => CNT_MONTH.may_load(deps.storage, (year, m))?.unwrap_or(0),
+            };
+        }
+        return Ok(StatsMonthlyResp { months });
+    }
+
+    let first = calendar::days_from_civil(y, 1, 1);
+    let last = calendar::days_from_civil(y + 1, 1, 1);
+    if let Some((start, end)) = calendar::utc_range(first, last, tz) {
+        let filter = RecordFilter {
+            start,
+            end,
+            ..filter
+        };
+        scan_records(deps, &filter, None, |rec| {
+            let (ry, m, _) = calendar::civil_from_days(calendar::local_days(rec.observed_at, tz));
+            if ry == y {
+                months[(m - 1) as usize] += 1;
+            }
+            true
+        })?;
+    }
+
+    Ok(StatsMonthlyResp { months })
+}
+
+fn query_stats_daily(
+    deps: Deps,
+    filter: RecordFilter,
+    year: u32,
+    month: u32,
+    tz_offset_minutes: Option<i32>,
+) -> StdResult<StatsDailyResp> {
+    let tz = validate_tz_offset(tz_offset_minutes)?;
+    if !(1..=12).contains(&month) {
+        return Err(StdError::generic_err("month must be 1..=12"));
+    }
+    let y = i64::from(year);
+    let mut days = vec![0u64; calendar::days_in_month(y, month) as usize];
+
+    let first = calendar::days_from_civil(y, month, 1);
+    let last = first + days.len() as i64;
+    if let Some((start, end)) = calendar::utc_range(first, last, tz) {
+        let filter = RecordFilter {
+            start,
+            end,
+            ..filter
+        };
+        scan_records(deps, &filter, None, |rec| {
+            let d = calendar::local_days(rec.observed_at, tz);
+            if (first..last).contains(&d) {
+                days[(d - first) as usize] += 1;
+            }
+            true
+        })?;
+    }
+
+    Ok(StatsDailyResp { days })
+}
+
+fn query_stats_weekly(
+    deps: Deps,
+    filter: RecordFilter,
+    year: u32,
+    tz_offset_minutes: Option<i32>,
+) -> StdResult<StatsWeeklyResp> {
+    let tz = validate_tz_offset(tz_offset_minutes)?;
+    let y = i64::from(year);
+    let mut weeks = vec![0u64; calendar::iso_weeks_in_year(y) as usize];
+
+    let first = calendar::iso_year_start(y);
+    let last = calendar::iso_year_start(y + 1);
+    if let Some((start, end)) = calendar::utc_range(first, last, tz) {
+        let filter = RecordFilter {
+            start,
+            end,
+            ..filter
+        };
+        scan_records(deps, &filter, None, |rec| {
+            let (wy, w) = calendar::iso_week(calendar::local_days(rec.observed_at, tz));
+            if wy == y {
+                weeks[(w - 1) as usize] += 1;
+            }
+            true
+        })?;
+    }
+
+    Ok(StatsWeeklyResp { weeks })
+}
+
+/* ============== phenology ============== */
+
+fn query_phenology_summary(
+    deps: Deps,
+    filter: RecordFilter,
+    mut years: Vec<u32>,
+    tz_offset_minutes: Option<i32>,
+    require_consensus: bool,
+) -> StdResult<PhenologySummaryResp> {
+    let tz = validate_tz_offset(tz_offset_minutes)?;
+    years.sort_unstable();
+    years.dedup();
+    if years.is_empty() || years.len() > MAX_SUMMARY_YEARS {
+        return Err(StdError::generic_err(format!(
+            "years must contain 1..={} entries",
+            MAX_SUMMARY_YEARS
+        )));
+    }
+
+    let mut out = Vec::with_capacity(years.len());
+    for year in years {
+        let y = i64::from(year);
+        let first = calendar::days_from_civil(y, 1, 1);
+        let last = calendar::days_from_civil(y + 1, 1, 1);
+
+        // 通算日ごとの件数（index 0 が 1 月 1 日）
+        let mut hist = vec![0u64; (last - first) as usize];
+        let mut count = 0u64;
+        if let Some((start, end)) = calendar::utc_range(first, last, tz) {
+            let filter = RecordFilter {
+                start,
+                end,
+                ..filter.clone()
+            };
+            scan_records(deps, &filter, None, |rec| {
+                if require_consensus && rec.consensus_taxon.is_none() {
+                    return true;
+                }
+                let d = calendar::local_days(rec.observed_at, tz);
+                if (first..last).contains(&d) {
+                    hist[(d - first) as usize] += 1;
+                    count += 1;
+                }
+                true
+            })?;
+        }
+
+        let day_of = |i: usize| i as u16 + 1;
+        let first_day = hist.iter().position(|&n| n > 0).map(day_of);
+        let last_day = hist.iter().rposition(|&n| n > 0).map(day_of);
+        let median_day = if count == 0 {
+            None
+        } else {
+            let rank = count.div_ceil(2);
+            let mut seen = 0u64;
+            hist.iter()
+                .position(|&n| {
+                    seen += n;
+                    seen >= rank
+                })
+                .map(day_of)
+        };
+        out.push(PhenologyYear {
+            year,
+            count,
+            first_day,
+            median_day,
+            last_day,
+        });
+    }
+
+    Ok(PhenologySummaryResp { years: out })
+}
+
+/* ===========================
+ * admin helper
+ * =========================== */
+
+fn exec_grant_role(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    role: Role,
+    addr: String,
+    expires_at: Option<u64>,
+) -> Result<Response, ContractError> {
+    ensure_admin(&deps, &env, &info.sender)?;
+    if let Some(t) = expires_at {
+        if t <= env.block.time.seconds() {
+            return Err(ContractError::BadRequest {
+                msg: "expires_at must be in the future".into(),
+            });
+        }
+    }
+    let a = deps.api.addr_validate(&addr)?;
+    // 最後の無期限 admin を期限付きに上書きすると、期限後に admin がいなくなる
+    if role == Role::Admin && expires_at.is_some() && !has_other_permanent_admin(deps.storage, &a)?
+    {
+        return Err(ContractError::BadRequest {
+            msg: "cannot make the last permanent admin expire".into(),
+        });
+    }
+    let grant = RoleGrant {
+        granted_by: info.sender.clone(),
+        granted_at: env.block.time.seconds(),
+        expires_at,
+    };
+    ROLES.save(deps.storage, (role.key(), &a), &grant)?;
+    Ok(Response::new()
+        .add_attribute("action", "grant_role")
+        .add_attribute("role", role.key())
+        .add_attribute("addr", a)
+        .add_attribute(
+            "expires_at",
+            expires_at.map_or("never".to_string(), |t| t.to_string()),
+        ))
+}
+
+/// 無期限の admin が except 以外に残っているか
+fn has_other_permanent_admin(store: &dyn Storage, except: &Addr) -> StdResult<bool> {
+    for item in ROLES
+        .prefix(Role::Admin.key())
+        .range(store, None, None, Order::Ascending)
+    {
+        let (addr, grant) = item?;
+        if addr != *except && grant.expires_at.is_none() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn exec_revoke_role(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    role: Role,
+    addr: String,
+) -> Result<Response, ContractError> {
+    ensure_admin(&deps, &env, &info.sender)?;
+    let a = deps.api.addr_validate(&addr)?;
+    if !ROLES.has(deps.storage, (role.key(), &a)) {
+        return Err(ContractError::NotFound);
+    }
+    if role == Role::Admin && !has_other_permanent_admin(deps.storage, &a)? {
+        return Err(ContractError::BadRequest {
+            msg: "cannot revoke the last permanent admin".into(),
+        });
+    }
+    ROLES.remove(deps.storage, (role.key(), &a));
+    // 外された admin が出していた引き継ぎは取り消す
+    if role == Role::Admin
+        && PENDING_ADMIN
+            .may_load(deps.storage)?
+            .is_some_and(|p| p.proposer == a)
+    {
+        PENDING_ADMIN.remove(deps.storage);
+    }
+    Ok(Response::new()
+        .add_attribute("action", "revoke_role")
+        .add_attribute("role", role.key())
+        .add_attribute("addr", a))
+}
+
+fn exec_propose_admin(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    new_admin: String,
+) -> Result<Response, ContractError> {
+    ensure_admin(&deps, &env, &info.sender)?;
+    let candidate = deps.api.addr_validate(&new_admin)?;
+    PENDING_ADMIN.save(
+        deps.storage,
+        &PendingAdmin {
+            proposer: info.sender,
+            candidate: candidate.clone(),
+        },
+    )?;
+    Ok(Response::new()
+        .add_attribute("action", "propose_admin")
+        .add_attribute("candidate", candidate))
+}
+
+fn exec_accept_admin(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let Some(p) = PENDING_ADMIN.may_load(deps.storage)? else {
+        return Err(ContractError::NoPendingAdmin);
+    };
+    if p.candidate != info.sender {
+        return Err(ContractError::Unauthorized);
+    }
+    // 指名した admin が外された・期限切れなら引き継ぎは無効
+    if !has_role(deps.storage, &env, &p.proposer, Role::Admin)? {
+        return Err(ContractError::BadRequest {
+            msg: "proposer is no longer an admin".into(),
+        });
+    }
+    let grant = RoleGrant {
+        granted_by: p.proposer.clone(),
+        granted_at: env.block.time.seconds(),
+        expires_at: None,
+    };
+    ROLES.save(deps.storage, (Role::Admin.key(), &info.sender), &grant)?;
+    if p.proposer != info.sender {
+        ROLES.remove(deps.storage, (Role::Admin.key(), &p.proposer));
+    }
+    PENDING_ADMIN.remove(deps.storage);
+    Ok(Response::new()
+        .add_attribute("action", "accept_admin")
+        .add_attribute("admin", info.sender)
+        .add_attribute("previous", p.proposer))
+}
+
+fn exec_set_admin_can_edit(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    enabled: bool,
+) -> Result<Response, ContractError> {
+    ensure_admin(&deps, &env, &info.sender)?;
+    ADMIN_CAN_EDIT.save(deps.storage, &enabled)?;
+    Ok(Response::new()
+        .add_attribute("action", "set_admin_can_edit")
+        .add_attribute("enabled", enabled.to_string()))
+}
+
+fn validate_max_batch_size(max: u32) -> Result<u32, ContractError> {
+    if max == 0 || max > MAX_BATCH_SIZE_LIMIT {
+        return Err(ContractError::BadRequest {
+            msg: format!("max_batch_size must be 1..={}", MAX_BATCH_SIZE_LIMIT),
+        });
+    }
+    Ok(max)
+}
+
+fn exec_update_config(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    update: ConfigUpdate,
+) -> Result<Response, ContractError> {
+    ensure_admin(&deps, &env, &info.sender)?;
+    let prev = CONFIG.load(deps.storage)?;
+    // 同じ桁数の指定で全件の張り直しを始めない
+    if update.geohash_precision == Some(prev.geohash_precision) {
+        return Err(ContractError::BadRequest {
+            msg: format!("geohash_precision is already {}", prev.geohash_precision),
+        });
+    }
+    let cfg = Config {
+        max_limit: update.max_limit.unwrap_or(prev.max_limit),
+        default_limit: update.default_limit.unwrap_or(prev.default_limit),
+        geohash_precision: update.geohash_precision.unwrap_or(prev.geohash_precision),
+        cid_min_len: update.cid_min_len.unwrap_or(prev.cid_min_len),
+        cid_max_len: update.cid_max_len.unwrap_or(prev.cid_max_len),
+        paused: update.paused.unwrap_or(prev.paused),
+        max_payload_bytes: update.max_payload_bytes.unwrap_or(prev.max_payload_bytes),
+        max_batch_size: update.max_batch_size.unwrap_or(prev.max_batch_size),
+    };
+    validate_config(&cfg)?;
+    CONFIG.save(deps.storage, &cfg)?;
+
+    // 桁数が変わったら索引を張り直すまで他の execute を止める
+    let reindex = cfg.geohash_precision != prev.geohash_precision;
+    if reindex {
+        let version = stored_contract_version(deps.storage)?.version;
+        MIGRATION.save(
+            deps.storage,
+            &MigrationState {
+                from_version: version.clone(),
+                to_version: version,
+                steps: vec![MigrationStep::ClearIndexes, MigrationStep::Reindex],
+                cursor: None,
+            },
+        )?;
+    }
+    Ok(Response::new()
+        .add_attribute("action", "update_config")
+        .add_attribute("paused", cfg.paused.to_string())
+        .add_attribute("geohash_precision", cfg.geohash_precision.to_string())
+        .add_attribute("reindex", reindex.to_string()))
+}
+
+fn exec_set_payload_schema(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    schema: Option<PayloadSchema>,
+) -> Result<Response, ContractError> {
+    ensure_admin(&deps, &env, &info.sender)?;
+    let fields = match schema {
+        Some(schema) => {
+            schema::check_schema(&schema)?;
+            let n = schema.fields.len();
+            PAYLOAD_SCHEMA.save(deps.storage, &schema)?;
+            n
+        }
+        None => {
+            PAYLOAD_SCHEMA.remove(deps.storage);
+            0
+        }
+    };
+    Ok(Response::new()
+        .add_attribute("action", "set_payload_schema")
+        .add_attribute("fields", fields.to_string()))
+}
+
+/* ===========================
+ * reply
+ * =========================== */
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        REPLY_MINT_RECORD_NFT => on_record_nft_minted(deps, &env, msg.result),
+        // 通知先の失敗はイベントに残すだけ
+        REPLY_HOOK_FAILED => {
+            let err = match msg.result {
+                SubMsgResult::Err(e) => e,
+                SubMsgResult::Ok(_) => String::new(),
+            };
+            Ok(Response::new()
+                .add_attribute("action", "hook_failed")
+                .add_attribute("error", err))
+        }
+        id => Err(StdError::generic_err(format!("unknown reply id: {}", id)).into()),
+    }
+}
+
+/* ===========================
+ * migrate
+ * =========================== */
+
+/// cw2 導入前（0.3.0 未満）のインスタンスは "0.0.0" として扱う
+const LEGACY_VERSION: &str = "0.0.0";
+/// migrate / ContinueMigration 1 回あたりに処理するレコード数の既定値
+const DEFAULT_MIGRATION_LIMIT: u32 = 1_000;
+/// (このバージョン未満から上げるときに必要, 段階)。索引の張り直しは毎回末尾に付く
+const MIGRATIONS: [(&str, MigrationStep); 4] = [
+    ("0.3.0", MigrationStep::LegacyRoles),
+    ("0.3.0", MigrationStep::InlineLists),
+    ("0.3.0", MigrationStep::ClearTagIndex),
+    ("0.3.0", MigrationStep::TagIndex),
+];
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    let stored = stored_contract_version(deps.storage)?;
+    if stored.contract != CONTRACT_NAME {
+        return Err(ContractError::BadRequest {
+            msg: format!("cannot migrate from {}", stored.contract),
+        });
+    }
+    if parse_version(&stored.version)? > parse_version(CONTRACT_VERSION)? {
+        return Err(ContractError::BadRequest {
+            msg: format!(
+                "cannot downgrade from {} to {}",
+                stored.version, CONTRACT_VERSION
+            ),
+        });
+    }
+
+    let state = match MIGRATION.may_load(deps.storage)? {
+        // 同じコードへの再 migrate は続きから
+        Some(s) if s.to_version == CONTRACT_VERSION => {
+            if msg.geohash_precision.is_some() {
+                return Err(ContractError::BadRequest {
+                    msg: "migration in progress; geohash_precision cannot be changed".into(),
+                });
+            }
+            s
+        }
+        // 途中で別のコードに上げた場合は最初から（各段階はやり直しても結果が同じ）
+        prev => {
+            let from_version = prev.map_or(stored.version, |s| s.from_version);
+            let mut cfg = migrate_config(deps.storage)?;
+            if let Some(p) = msg.geohash_precision {
+                cfg.geohash_precision = validate_geohash_precision(p)?;
+            }
+            CONFIG.save(deps.storage, &cfg)?;
+            MigrationState {
+                steps: migration_steps(&from_version)?,
+                from_version,
+                to_version: CONTRACT_VERSION.to_string(),
+                cursor: None,
+            }
+        }
+    };
+    run_migration(deps, &env, state, msg.limit, "migrate")
+}
+
+fn exec_continue_migration(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    ensure_admin(&deps, &env, &info.sender)?;
+    let state = MIGRATION
+        .may_load(deps.storage)?
+        .ok_or_else(|| ContractError::BadRequest {
+            msg: "no migration in progress".into(),
+        })?;
+    run_migration(deps, &env, state, limit, "continue_migration")
+}
+
+/// cw2 のバージョン。cw2 導入前のインスタンス（NEXT_ID はある）は LEGACY_VERSION
+fn stored_contract_version(store: &dyn Storage) -> StdResult<ContractVersion> {
+    match get_contract_version(store) {
+        Err(StdError::NotFound { .. }) if NEXT_ID.may_load(store)?.is_some() => {
+            Ok(ContractVersion {
+                contract: CONTRACT_NAME.to_string(),
+                version: LEGACY_VERSION.to_string(),
+            })
+        }
+        r => r,
+    }
+}
+
+fn parse_version(v: &str) -> Result<Version, ContractError> {
+    v.parse().map_err(|_| ContractError::BadRequest {
+        msg: format!("invalid contract version: {}", v),
+    })
+}
+
+/// from_version から上げるときに必要な段階
+fn migration_steps(from_version: &str) -> Result<Vec<MigrationStep>, ContractError> {
+    let from = parse_version(from_version)?;
+    let mut steps = vec![];
+    for (since, step) in MIGRATIONS {
+        if from < parse_version(since)? {
+            steps.push(step);
+        }
+    }
+    steps.extend([MigrationStep::ClearIndexes, MigrationStep::Reindex]);
+    Ok(steps)
+}
+
+/// 段階を先頭から limit 件分進める。全段階が終われば cw2 のバージョンを更新する
+fn run_migration(
+    deps: DepsMut,
+    env: &Env,
+    mut state: MigrationState,
+    limit: Option<u32>,
+    action: &str,
+) -> Result<Response, ContractError> {
+    let mut budget = limit.unwrap_or(DEFAULT_MIGRATION_LIMIT).max(1) as usize;
+    let mut processed = 0usize;
+    while budget > 0 {
+        let Some(step) = state.steps.first().cloned() else {
+            break;
+        };
+        let p = run_migration_step(deps.storage, env, &step, state.cursor, budget)?;
+        processed += p.used;
+        budget -= p.used;
+        if p.done {
+            state.steps.remove(0);
+            state.cursor = None;
+        } else {
+            state.cursor = p.cursor;
+        }
+    }
+
+    let done = state.steps.is_empty();
+    if done {
+        MIGRATION.remove(deps.storage);
+        set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    } else {
+        MIGRATION.save(deps.storage, &state)?;
+    }
+    let precision = CONFIG.load(deps.storage)?.geohash_precision;
+    Ok(Response::new()
+        .add_attribute("action", action)
+        .add_attribute("from_version", &state.from_version)
+        .add_attribute("to_version", &state.to_version)
+        .add_attribute("geohash_precision", precision.to_string())
+        .add_attribute("processed", processed.to_string())
+        .add_attribute("step", state.steps.first().map_or("", MigrationStep::key))
+        .add_attribute("done", done.to_string()))
+}
+
+/// 段階 1 回分の結果。used は消費した件数（レコード数または消したキー数）
+struct StepProgress {
+    used: usize,
+    done: bool,
+    cursor: Option<u64>,
+}
+
+/// レコード単位の段階は cursor の次の id から、消去の段階は先頭から最大 limit 件を処理する
+fn run_migration_step(
+    store: &mut dyn Storage,
+    env: &Env,
+    step: &MigrationStep,
+    cursor: Option<u64>,
+    limit: usize,
+) -> StdResult<StepProgress> {
+    let cleared = |used: usize| StepProgress {
+        used,
+        done: used < limit,
+        cursor: None,
+    };
+    match step {
+        MigrationStep::LegacyRoles => {
+            migrate_legacy_roles(store, env)?;
+            return Ok(StepProgress {
+                used: 1,
+                done: true,
+                cursor: None,
+            });
+        }
+        MigrationStep::ClearTagIndex => {
+            let mut n = clear_page(store, &RECORD_TAGS, limit);
+            n += clear_page(store, &BY_TAG, limit - n);
+            return Ok(cleared(n));
+        }
+        MigrationStep::ClearIndexes => return Ok(cleared(clear_indexes(store, limit))),
+        MigrationStep::InlineLists | MigrationStep::TagIndex | MigrationStep::Reindex => {}
+    }
+
+    // keys も値を読むので、旧形式のレコードでも失敗しないよう生の JSON として読む
+    let ids: Vec<u64> = LEGACY_RECORDS_RAW
+        .keys(store, cursor.map(Bound::exclusive), None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<_>>()?;
+    let precision = CONFIG.load(store)?.geohash_precision;
+    for &id in &ids {
+        match step {
+            MigrationStep::InlineLists => migrate_inline_lists(store, id)?,
+            MigrationStep::TagIndex => reindex_tags(store, id)?,
+            _ => reindex_record(store, env.block.height, precision, id)?,
+        }
+    }
+    Ok(StepProgress {
+        used: ids.len(),
+        done: ids.len() < limit,
+        cursor: ids.last().copied(),
+    })
+}
+
+/// map を先頭から最大 limit 件消す。消した件数を返す
+fn clear_page<'a, K, T>(store: &mut dyn Storage, map: &Map<'a, K, T>, limit: usize) -> usize
+where
+    K: PrimaryKey<'a>,
+    T: Serialize + DeserializeOwned,
+{
+    let keys: Vec<Vec<u8>> = map
+        .keys_raw(store, None, None, Order::Ascending)
+        .take(limit)
+        .collect();
+    for k in &keys {
+        store.remove(&Path::<T>::new(map.namespace(), &[k.as_slice()]));
+    }
+    keys.len()
+}
+
+/// 検索インデックスと集計カウンタを先頭から最大 limit 件消す。消した件数を返す
+fn clear_indexes(store: &mut dyn Storage, limit: usize) -> usize {
+    CNT_TOTAL.remove(store);
+    let mut n = clear_page(store, &BY_GEOHASH, limit);
+    n += clear_page(store, &BY_GEOHASH_TIME, limit - n);
+    n += clear_page(store, &BY_SPECIES_TIME, limit - n);
+    n += clear_page(store, &LEGACY_BY_SPECIES, limit - n);
+    n += clear_page(store, &BY_SENDER_TIME, limit - n);
+    n += clear_page(store, &BY_PHENOPHASE_TIME, limit - n);
+    n += clear_page(store, &BY_GRADE_TIME, limit - n);
+    n += clear_page(store, &CNT_SPECIES, limit - n);
+    n += clear_page(store, &CNT_GEOHASH, limit - n);
+    n += clear_page(store, &CNT_MONTH, limit - n);
+    n += clear_page(store, &CNT_SPECIES_MONTH, limit - n);
+    n += clear_page(store, &CNT_TAG, limit - n);
+    n
+}
+
+/// 旧形式の GEOHASH_PRECISION / MAX_BATCH_SIZE から Config を作る（CONFIG があればそれを返す）
+fn migrate_config(store: &mut dyn Storage) -> StdResult<Config> {
+    if let Some(cfg) = CONFIG.may_load(store)? {
+        return Ok(cfg);
+    }
+    let cfg = Config {
+        max_limit: DEFAULT_MAX_LIMIT,
+        default_limit: DEFAULT_LIMIT,
+        geohash_precision: LEGACY_GEOHASH_PRECISION
+            .may_load(store)?
+            .unwrap_or(DEFAULT_GEOHASH_PRECISION),
+        cid_min_len: DEFAULT_CID_MIN_LEN,
+        cid_max_len: DEFAULT_CID_MAX_LEN,
+        paused: false,
+        max_payload_bytes: DEFAULT_MAX_PAYLOAD_BYTES,
+        max_batch_size: LEGACY_MAX_BATCH_SIZE
+            .may_load(store)?
+            .unwrap_or(DEFAULT_MAX_BATCH_SIZE),
+    };
+    LEGACY_GEOHASH_PRECISION.remove(store);
+    LEGACY_MAX_BATCH_SIZE.remove(store);
+    Ok(cfg)
+}
+
+/// 旧形式の ADMIN / VERIFIERS / MODERATORS を ROLES に移す
+fn migrate_legacy_roles(store: &mut dyn Storage, env: &Env) -> StdResult<()> {
+    let Some(admin) = LEGACY_ADMIN.may_load(store)? else {
+        return Ok(());
+    };
+    let grant = RoleGrant {
+        granted_by: admin.clone(),
+        granted_at: env.block.time.seconds(),
+        expires_at: None,
+    };
+    ROLES.save(store, (Role::Admin.key(), &admin), &grant)?;
+    for (legacy, role) in [
+        (LEGACY_VERIFIERS, Role::Verifier),
+        (LEGACY_MODERATORS, Role::Moderator),
+    ] {
+        let enabled: Vec<Addr> = legacy
+            .range(store, None, None, Order::Ascending)
+            .filter_map(|item| match item {
+                Ok((addr, true)) => Some(Ok(addr)),
+                Ok(_) => None,
+                Err(e) => Some(Err(e)),
+            })
+            .collect::<StdResult<_>>()?;
+        for addr in enabled {
+            ROLES.save(store, (role.key(), &addr), &grant)?;
+        }
+        legacy.clear(store);
+    }
+    LEGACY_ADMIN.remove(store);
+    Ok(())
+}
+
+/// 旧形式のレコード内 annotations / verifications を ANNOTATIONS / VERIFICATIONS に移し、
+/// 件数だけをレコードに残す。同じ検証者の複数エントリは最新 1 件を現在の見解とし、残りは履歴へ
+fn migrate_inline_lists(store: &mut dyn Storage, id: u64) -> StdResult<()> {
+    let mut raw = LEGACY_RECORDS_RAW.load(store, id)?;
+    let Some(obj) = raw.as_object_mut() else {
+        return Ok(());
+    };
+    if obj.contains_key("annotation_count") {
+        return Ok(());
+    }
+    let annotations: Vec<Annotation> = match obj.remove("annotations") {
+        Some(v) => serde_json::from_value(v).map_err(|e| StdError::parse_err("Annotation", e))?,
+        None => vec![],
+    };
+    let verifications: Vec<VerificationEntry> = match obj.remove("verifications") {
+        Some(v) => {
+            serde_json::from_value(v).map_err(|e| StdError::parse_err("VerificationEntry", e))?
+        }
+        None => vec![],
+    };
+
+    let mut next_annotation = NEXT_ANNOTATION_SEQ.may_load(store)?.unwrap_or(1);
+    for a in &annotations {
+        ANNOTATIONS.save(store, (id, next_annotation), a)?;
+        next_annotation += 1;
+    }
+    NEXT_ANNOTATION_SEQ.save(store, &next_annotation)?;
+
+    for v in &verifications {
+        BY_VERIFIER.remove(store, (&v.verifier, id));
+    }
+    let mut next_verification = NEXT_VERIFICATION_SEQ.may_load(store)?.unwrap_or(1);
+    let mut current = 0u32;
+    for v in verifications {
+        let seq = next_verification;
+        next_verification += 1;
+        let ended_at = v.at;
+        if let Some(prev) = BY_VERIFIER.may_load(store, (&v.verifier, id))? {
+            archive_verification(store, id, prev, ended_at, VerificationEnd::Superseded)?;
+        } else {
+            current += 1;
+        }
+        BY_VERIFIER.save(store, (&v.verifier, id), &seq)?;
+        VERIFICATIONS.save(store, (id, seq), &v)?;
+    }
+    NEXT_VERIFICATION_SEQ.save(store, &next_verification)?;
+
+    obj.insert("annotation_count".into(), annotations.len().into());
+    obj.insert("verification_count".into(), current.into());
+    let rec: StoredRecord =
+        serde_json::from_value(raw).map_err(|e| StdError::parse_err("StoredRecord", e))?;
+    RECORDS.save(store, id, &rec)
+}
+
+/// レコードの注記のタグを正規化して RECORD_TAGS / BY_TAG に載せる（CNT_TAG は Reindex で）。
+/// 正規化後も不正なタグは注記に残すが索引しない
+fn reindex_tags(store: &mut dyn Storage, id: u64) -> StdResult<()> {
+    let seqs: Vec<u64> = ANNOTATIONS
+        .prefix(id)
+        .keys(store, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
+    for seq in seqs {
+        let mut annotation = ANNOTATIONS.load(store, (id, seq))?;
+        let Some(tags) = annotation.tags.take() else {
+            continue;
+        };
+        let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+        for t in &tags {
+            let t = normalize_tag(t);
+            if !normalized.contains(&t) {
+                normalized.push(t);
+            }
+        }
+        for t in normalized.iter().filter(|t| valid_tag(t)) {
+            let refs = RECORD_TAGS.may_load(store, (id, t.as_str()))?.unwrap_or(0);
+            RECORD_TAGS.save(store, (id, t.as_str()), &(refs + 1))?;
+            BY_TAG.save(store, (t.as_str(), id), &())?;
+        }
+        if normalized != tags {
+            annotation.tags = Some(normalized);
+            ANNOTATIONS.save(store, (id, seq), &annotation)?;
+        }
+    }
+    Ok(())
+}
+
+/// geohash を標準 geohash で作り直し、合意とグレードを再計算して
+/// BY_GEOHASH・複合・投稿者・グレードのインデックスと集計カウンタに載せる
+fn reindex_record(
+    store: &mut dyn Storage,
+    block_height: u64,
+    precision: u8,
+    id: u64,
+) -> StdResult<()> {
+    let mut rec = RECORDS.load(store, id)?;
+    let before = rec.clone();
+    rec.geohash_prefix = extract_geohash_prefix(&rec.payload, precision);
+    rec.phenophase = extract_phenophase(&rec.payload);
+    let rec = graded(store, rec)?;
+    if rec != before {
+        RECORDS.save(store, id, &rec)?;
+        append_change(store, block_height, id, ChangeKind::Update)?;
+    }
+    index_record(store, &rec)?;
+    if !rec.hidden {
+        adjust_counters(store, &rec, true)?;
+    }
+    Ok(())
 }
@@ -5,13 +5,71 @@ pub struct InstantiateMsg {
     pub start_id: Option<u64>,
     pub admin: Option<String>,
     pub verifiers: Option<Vec<String>>,
+    /// BY_GEOHASH の桁数（1..=12, 既定 6）
+    pub geohash_precision: Option<u8>,
+    /// admin による UpdateRecord を許可するか（既定 false）
+    pub admin_can_edit: Option<bool>,
+    /// 合意判定（既定 67% / 2 人）
+    pub consensus: Option<super::state::ConsensusParams>,
+    /// StoreBatch の件数上限（既定 50）
+    pub max_batch_size: Option<u32>,
+    /// 一覧系の limit の上限と既定値（既定 5000 / 100）
+    pub max_limit: Option<u32>,
+    pub default_limit: Option<u32>,
+    /// CID の長さの範囲（既定 20..=200）
+    pub cid_min_len: Option<u32>,
+    pub cid_max_len: Option<u32>,
+    /// payload の最大バイト数（既定 16384）
+    pub max_payload_bytes: Option<u32>,
+    /// 投稿できる cw721 保有者の制限（既定なし）
+    pub gate: Option<GateConfigMsg>,
+    /// 保存時に観察記録 NFT を発行する s_mint（既定なし）
+    pub nft_minter: Option<NftMinterMsg>,
+}
+
+#[cw_serde]
+pub struct MigrateMsg {
+    /// 指定時は桁数を変更してから全レコードを再インデックス（移行の途中では指定不可）
+    pub geohash_precision: Option<u8>,
+    /// この tx で処理するレコード数の上限。残りは ContinueMigration か再度の migrate で
+    pub limit: Option<u32>,
 }
 
 #[cw_serde]
 pub enum ExecuteMsg {
-    /// CID は必須（"bafy..." または "ipfs://bafy..."）
-    Store { payload: serde_json::Value, cid: String },
+    /// CID は必須（"bafy..." または "ipfs://bafy..."）。
+    /// ゲートが specific なら gate_token_id も必須
+    Store {
+        payload: serde_json::Value,
+        cid: String,
+        gate_token_id: Option<String>,
+    },
+
+    /// 複数件の Store。id は成功した順に連番で振られる。
+    /// 結果（StoreBatchResp）は Response の data に入る
+    StoreBatch {
+        items: Vec<StoreItem>,
+        mode: BatchMode,
+    },
+
+    /// 投稿者（admin_can_edit なら admin も）による修正。旧版は RecordHistory に残る
+    UpdateRecord {
+        id: u64,
+        payload: serde_json::Value,
+        cid: String,
+    },
+
+    /// 投稿者による取り下げ（レコードを削除し Tombstone を残す）
+    Withdraw {
+        id: u64,
+    },
 
+    /// admin によるレコードの削除（1 回あたり最大 100 件）
+    Purge {
+        ids: Vec<u64>,
+    },
+
+    /// tags は正規化して保存（"#BioBlitz2025" → "bioblitz2025"）
     AppendAnnotation {
         id: u64,
         note: Option<String>,
@@ -19,15 +77,131 @@ pub enum ExecuteMsg {
         tags: Option<Vec<String>>,
     },
 
+    /// 自分の注記からタグを外す
+    RemoveTag {
+        id: u64,
+        seq: u64,
+        tag: String,
+    },
+
+    /// 同じ検証者の以前の見解は置き換え（履歴に残る）。confidence は 0..=100
     Verify {
         id: u64,
         taxon_id: String,
         confidence: u8,
     },
 
-    Hide { id: u64, reason: Option<String> },
+    /// 自分の現在の見解を取り消す
+    RetractVerification {
+        id: u64,
+    },
+
+    /// 誰でも可。同じ利用者は同じレコードに未対応の通報を 1 件まで
+    Report {
+        id: u64,
+        category: super::state::ReportCategory,
+        note: Option<String>,
+    },
+
+    /// 以下 3 つは moderator または admin。未対応の通報は Actioned になる。
+    /// spam = true ならチャレンジ期間中のデポジットを没収扱いにする（期間内の Unhide で戻る）
+    Hide {
+        id: u64,
+        reason: Option<String>,
+        spam: Option<bool>,
+    },
+
+    Unhide {
+        id: u64,
+        reason: Option<String>,
+    },
+
+    DismissReport {
+        report_id: u64,
+        reason: Option<String>,
+    },
+
+    /// admin のみ。expires_at（秒）を過ぎると無効
+    GrantRole {
+        role: super::state::Role,
+        addr: String,
+        expires_at: Option<u64>,
+    },
+
+    /// admin のみ。無期限の admin が 1 人もいなくなる取り消しは不可
+    RevokeRole {
+        role: super::state::Role,
+        addr: String,
+    },
+
+    /// admin の引き継ぎ（new_admin が AcceptAdmin すると提案者の admin は外れる）
+    ProposeAdmin {
+        new_admin: String,
+    },
+
+    AcceptAdmin {},
+
+    SetAdminCanEdit {
+        enabled: bool,
+    },
+
+    /// 投稿の件数クォータとデポジットの設定（admin）。None の項目は無効
+    SetSpamPolicy {
+        quota: Option<super::state::StoreQuota>,
+        deposit: Option<DepositConfigMsg>,
+    },
+
+    /// チャレンジ期間を過ぎた owner（省略時は自分）のデポジットを古い順に最大 limit 件精算する。
+    /// 没収扱いのものは treasury へ、それ以外は owner へ送る
+    ClaimDeposits {
+        owner: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// 通知先の登録・解除（admin）
+    AddHook {
+        addr: String,
+    },
+    RemoveHook {
+        addr: String,
+    },
+
+    /// 観察記録 NFT の発行先の設定（admin）。None で発行を止める
+    SetNftMinter {
+        minter: Option<NftMinterMsg>,
+    },
+
+    /// 投稿ゲートの設定（admin）。None で解除
+    SetGate {
+        gate: Option<GateConfigMsg>,
+    },
+
+    /// 運用パラメータの変更（admin）。geohash_precision を変えると再インデックスが始まり、
+    /// ContinueMigration で完了させるまで他の execute と索引を読むクエリは拒否
+    UpdateConfig {
+        config: ConfigUpdate,
+    },
 
-    SetVerifier { addr: String, enabled: bool },
+    /// 合意判定のパラメータ変更（admin）。既存レコードは RecomputeGrades で反映
+    SetConsensusParams {
+        params: super::state::ConsensusParams,
+    },
+
+    /// id 順に limit 件ずつ合意とグレードを再計算（admin）
+    RecomputeGrades {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    /// Store 時の payload 検証プロファイルを登録（None で解除）
+    SetPayloadSchema {
+        schema: Option<super::state::PayloadSchema>,
+    },
+
+    /// 途中の移行を limit 件分進める（admin）
+    ContinueMigration {
+        limit: Option<u32>,
+    },
 }
 
 #[cw_serde]
@@ -36,30 +210,264 @@ pub enum QueryMsg {
     #[returns(GetResp)]
     Get { id: u64 },
 
+    /// UpdateRecord で置き換えられた旧版（revision 昇順）
+    #[returns(RecordHistoryResp)]
+    RecordHistory {
+        id: u64,
+        start_after: Option<u32>,
+        limit: Option<u32>,
+    },
+
     #[returns(ListResp)]
     List {
         species: Option<String>,
+        phenophase: Option<String>,
+        quality_grade: Option<super::state::QualityGrade>,
         geohash_prefix: Option<String>,
         start: Option<u64>,
         end: Option<u64>,
         limit: Option<u32>,
-        start_after: Option<u64>,
+        start_after: Option<RecordCursor>,
     },
 
     #[returns(CountResp)]
     Count {
         species: Option<String>,
+        phenophase: Option<String>,
+        quality_grade: Option<super::state::QualityGrade>,
         geohash_prefix: Option<String>,
         start: Option<u64>,
         end: Option<u64>,
     },
 
+    /// tz_offset_minutes: ローカル時刻の UTC からの差（分, 例: JST = 540）
     #[returns(StatsMonthlyResp)]
     StatsMonthly {
         species: Option<String>,
+        phenophase: Option<String>,
+        quality_grade: Option<super::state::QualityGrade>,
         geohash_prefix: Option<String>,
         year: u32,
+        tz_offset_minutes: Option<i32>,
+    },
+
+    #[returns(StatsDailyResp)]
+    StatsDaily {
+        species: Option<String>,
+        phenophase: Option<String>,
+        quality_grade: Option<super::state::QualityGrade>,
+        geohash_prefix: Option<String>,
+        year: u32,
+        month: u32,
+        tz_offset_minutes: Option<i32>,
+    },
+
+    /// year は ISO 週年
+    #[returns(StatsWeeklyResp)]
+    StatsWeekly {
+        species: Option<String>,
+        phenophase: Option<String>,
+        quality_grade: Option<super::state::QualityGrade>,
+        geohash_prefix: Option<String>,
+        year: u32,
+        tz_offset_minutes: Option<i32>,
+    },
+
+    /// 年ごとの初認・中央・終認日（ローカル日付の通算日 1..=366）。
+    /// require_consensus = true なら合意 taxon のあるレコードのみ
+    #[returns(PhenologySummaryResp)]
+    PhenologySummary {
+        species: Option<String>,
+        phenophase: Option<String>,
+        quality_grade: Option<super::state::QualityGrade>,
+        geohash_prefix: Option<String>,
+        years: Vec<u32>,
+        tz_offset_minutes: Option<i32>,
+        require_consensus: Option<bool>,
+    },
+
+    /// Withdraw / Purge されたレコード（id 昇順）
+    #[returns(TombstonesResp)]
+    Tombstones {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    /// 未対応の通報（通報順）
+    #[returns(ReportsResp)]
+    OpenReports {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    /// seq より後の変更（Store・編集・注記・検証・非表示など）を古い順に。
+    /// 初回は seq = 0、以降は受け取った最後の seq を渡す
+    #[returns(ChangesResp)]
+    ChangesSince { seq: u64, limit: Option<u32> },
+
+    /// レコードに対するモデレーション操作の履歴（古い順）
+    #[returns(ModerationHistoryResp)]
+    ModerationHistory {
+        id: u64,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    /// addr が持つ有効な権限
+    #[returns(RolesResp)]
+    Roles { addr: String },
+
+    /// role を持つアドレス（有効なもののみ, アドレス順）
+    #[returns(RoleMembersResp)]
+    ListRoleMembers {
+        role: super::state::Role,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    #[returns(PendingAdminResp)]
+    PendingAdmin {},
+
+    /// 投稿者ごとの一覧（observed_at 順, 非表示は除外）
+    #[returns(ListResp)]
+    ListBySender {
+        sender: String,
+        start: Option<u64>,
+        end: Option<u64>,
+        limit: Option<u32>,
+        start_after: Option<RecordCursor>,
+        /// 既定は asc
+        order: Option<SortOrder>,
+    },
+
+    /// 注記のタグで絞り込み（レコード id 順、非表示は除く）
+    #[returns(ListResp)]
+    ListByTag {
+        tag: String,
+        start_after: Option<RecordCursor>,
+        limit: Option<u32>,
+    },
+
+    /// タグごとのレコード数（prefix 一致、タグ名順）
+    #[returns(TagCountsResp)]
+    TagCounts {
+        prefix: Option<String>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// 緯度経度の矩形内（min_lon > max_lon は日付変更線をまたぐ範囲）。
+    /// 座標は 10 進文字列（例: "35.681"）
+    #[returns(ListResp)]
+    ListInBox {
+        min_lat: String,
+        min_lon: String,
+        max_lat: String,
+        max_lon: String,
+        species: Option<String>,
+        start: Option<u64>,
+        end: Option<u64>,
+        limit: Option<u32>,
+        start_after: Option<RecordCursor>,
+    },
+
+    /// 中心から radius_m（メートル）以内。座標は 10 進文字列
+    #[returns(ListResp)]
+    ListNear {
+        lat: String,
+        lon: String,
+        radius_m: u32,
+        species: Option<String>,
+        start: Option<u64>,
+        end: Option<u64>,
+        limit: Option<u32>,
+        start_after: Option<RecordCursor>,
+    },
+
+    #[returns(super::state::ConsensusParams)]
+    ConsensusParams {},
+
+    #[returns(Option<super::state::GateConfig>)]
+    Gate {},
+
+    #[returns(Option<super::state::NftMinterConfig>)]
+    NftMinter {},
+
+    /// 通知先（アドレス順）
+    #[returns(HooksResp)]
+    Hooks {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// 観察記録 NFT の token_id からレコード
+    #[returns(GetResp)]
+    RecordByToken { token_id: String },
+
+    #[returns(super::state::SpamPolicy)]
+    SpamPolicy {},
+
+    /// 現在のウィンドウでの投稿数
+    #[returns(QuotaUsageResp)]
+    QuotaUsage { addr: String },
+
+    /// 預かり中のデポジット（release_at 順）
+    #[returns(DepositsResp)]
+    Deposits { owner: String, limit: Option<u32> },
+
+    /// レコードの注記（古い順）
+    #[returns(AnnotationsResp)]
+    Annotations {
+        id: u64,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    /// レコードに対する各検証者の現在の見解（古い順）
+    #[returns(VerificationsResp)]
+    Verifications {
+        id: u64,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    /// 置き換え・取り消された検証（古い順）
+    #[returns(VerificationHistoryResp)]
+    VerificationHistory {
+        id: u64,
+        start_after: Option<u64>,
+        limit: Option<u32>,
     },
+
+    /// 検証者の現在の見解と、各レコードの合意との一致（レコード id 順）
+    #[returns(VerificationsByResp)]
+    VerificationsBy {
+        verifier: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    /// 現在の payload 検証プロファイル（フロントのフォーム生成用）
+    #[returns(PayloadSchemaResp)]
+    PayloadSchema {},
+
+    /// 現在の運用パラメータ
+    #[returns(super::state::Config)]
+    Config {},
+
+    /// cw2 のコントラクト名・バージョンと実行中の移行
+    #[returns(MigrationStatusResp)]
+    MigrationStatus {},
+
+    /// geohash セルの範囲（bbox）を返す
+    #[returns(DecodeGeohashResp)]
+    DecodeGeohash { geohash: String },
+}
+
+#[cw_serde]
+pub enum SortOrder {
+    Asc,
+    Desc,
 }
 
 #[cw_serde]
@@ -67,10 +475,254 @@ pub struct GetResp {
     pub record: Option<super::state::StoredRecord>,
 }
 
+#[cw_serde]
+pub struct RecordHistoryResp {
+    pub revisions: Vec<super::state::RecordRevision>,
+}
+
+#[cw_serde]
+pub struct TombstonesResp {
+    pub tombstones: Vec<super::state::Tombstone>,
+    pub next_start_after: Option<u64>,
+}
+
+#[cw_serde]
+pub struct ReportsResp {
+    pub reports: Vec<super::state::Report>,
+    /// 次ページの start_after（report id）
+    pub next_start_after: Option<u64>,
+}
+
+#[cw_serde]
+pub struct ModerationHistoryResp {
+    pub entries: Vec<super::state::AuditEntry>,
+    /// 次ページの start_after（監査ログの seq）
+    pub next_start_after: Option<u64>,
+}
+
+#[cw_serde]
+pub struct ChangesResp {
+    pub changes: Vec<super::state::Change>,
+    /// 続きがあれば次に渡す seq
+    pub next_seq: Option<u64>,
+    /// 現時点で最新の seq（変更がなければ 0）
+    pub latest_seq: u64,
+}
+
+#[cw_serde]
+pub struct RolesResp {
+    pub addr: String,
+    pub roles: Vec<RoleEntry>,
+}
+
+#[cw_serde]
+pub struct RoleEntry {
+    pub role: super::state::Role,
+    pub grant: super::state::RoleGrant,
+}
+
+#[cw_serde]
+pub struct RoleMembersResp {
+    pub members: Vec<RoleMember>,
+    pub next_start_after: Option<String>,
+}
+
+#[cw_serde]
+pub struct RoleMember {
+    pub addr: String,
+    pub grant: super::state::RoleGrant,
+}
+
+#[cw_serde]
+pub struct PendingAdminResp {
+    pub pending: Option<super::state::PendingAdmin>,
+}
+
+#[cw_serde]
+pub struct AnnotationsResp {
+    pub annotations: Vec<AnnotationItem>,
+    /// 次ページの start_after（seq）
+    pub next_start_after: Option<u64>,
+}
+
+#[cw_serde]
+pub struct AnnotationItem {
+    pub seq: u64,
+    pub annotation: super::state::Annotation,
+}
+
+#[cw_serde]
+pub struct VerificationsResp {
+    pub verifications: Vec<VerificationItem>,
+    /// 次ページの start_after（seq）
+    pub next_start_after: Option<u64>,
+}
+
+#[cw_serde]
+pub struct VerificationItem {
+    pub seq: u64,
+    pub entry: super::state::VerificationEntry,
+}
+
+#[cw_serde]
+pub struct VerificationHistoryResp {
+    pub entries: Vec<super::state::PastVerification>,
+    /// 次ページの start_after（seq）
+    pub next_start_after: Option<u64>,
+}
+
+#[cw_serde]
+pub struct VerificationsByResp {
+    pub verifications: Vec<VerifierOpinion>,
+    /// 次ページの start_after（レコード id）
+    pub next_start_after: Option<u64>,
+}
+
+#[cw_serde]
+pub struct VerifierOpinion {
+    pub record_id: u64,
+    pub seq: u64,
+    pub entry: super::state::VerificationEntry,
+    pub consensus_taxon: Option<String>,
+    /// 合意が無ければ None
+    pub agrees: Option<bool>,
+}
+
 #[cw_serde]
 pub struct ListResp {
     pub records: Vec<super::state::StoredRecord>,
-    pub next_start_after: Option<u64>,
+    pub next_start_after: Option<RecordCursor>,
+}
+
+/// List 系の再開位置。前ページの next_start_after をそのまま渡す。
+/// 位置をレコードから引き直さないので、途中で削除・編集されてもずれない
+#[cw_serde]
+pub struct RecordCursor {
+    pub id: u64,
+    pub observed_at: u64,
+    pub geohash: String,
+}
+
+#[cw_serde]
+pub struct DepositConfigMsg {
+    pub amount: cosmwasm_std::Coin,
+    pub challenge_period_secs: u64,
+    pub treasury: String,
+}
+
+#[cw_serde]
+pub struct QuotaUsageResp {
+    pub used: u32,
+    /// クォータ未設定なら None
+    pub max_stores: Option<u32>,
+    pub window_secs: Option<u64>,
+}
+
+#[cw_serde]
+pub struct DepositsResp {
+    pub deposits: Vec<super::state::Deposit>,
+}
+
+#[cw_serde]
+pub struct StoreItem {
+    pub payload: serde_json::Value,
+    pub cid: String,
+    pub gate_token_id: Option<String>,
+}
+
+#[cw_serde]
+pub struct HooksResp {
+    pub hooks: Vec<String>,
+}
+
+/// 通知先コントラクトが受け取る ExecuteMsg（{"observation_hook": {...}}）
+#[cw_serde]
+pub enum HookExecuteMsg {
+    ObservationHook(ObservationHookMsg),
+}
+
+#[cw_serde]
+pub enum ObservationHookMsg {
+    Stored {
+        id: u64,
+        species: Option<String>,
+        geohash: String,
+        sender: String,
+    },
+    Verified {
+        id: u64,
+        verifier: String,
+        taxon_id: String,
+        confidence: u8,
+        consensus_taxon: Option<String>,
+        quality_grade: Option<super::state::QualityGrade>,
+    },
+    Hidden {
+        id: u64,
+        by: String,
+        spam: bool,
+    },
+}
+
+#[cw_serde]
+pub struct NftMinterMsg {
+    pub contract: String,
+    /// 既定 "obs-"
+    pub token_id_prefix: Option<String>,
+}
+
+/// s_mint の PublicMint（extension は使わない）
+#[cw_serde]
+pub enum MinterExecuteMsg {
+    PublicMint {
+        token_id: String,
+        owner: String,
+        token_uri: String,
+        extension: Option<cosmwasm_std::Empty>,
+    },
+}
+
+#[cw_serde]
+pub struct GateConfigMsg {
+    pub contract: String,
+    pub mode: super::state::GateMode,
+}
+
+#[cw_serde]
+pub enum BatchMode {
+    /// 1 件でも失敗すれば全体をエラーにする
+    AllOrNothing,
+    /// 失敗した項目は飛ばし、残りを保存する
+    BestEffort,
+}
+
+#[cw_serde]
+pub struct StoreBatchResp {
+    /// items と同じ順
+    pub results: Vec<BatchItemResult>,
+    /// 保存された id の範囲（1 件も無ければ None）
+    pub first_id: Option<u64>,
+    pub last_id: Option<u64>,
+}
+
+#[cw_serde]
+pub struct BatchItemResult {
+    pub index: u32,
+    pub id: Option<u64>,
+    pub error: Option<String>,
+}
+
+#[cw_serde]
+pub struct TagCountsResp {
+    pub tags: Vec<TagCount>,
+    /// 次ページの start_after（タグ）
+    pub next_start_after: Option<String>,
+}
+
+#[cw_serde]
+pub struct TagCount {
+    pub tag: String,
+    pub count: u64,
 }
 
 #[cw_serde]
@@ -83,3 +735,70 @@ pub struct StatsMonthlyResp {
     /// index 0..11 が Jan..Dec
     pub months: [u64; 12],
 }
+
+#[cw_serde]
+pub struct StatsDailyResp {
+    /// index 0 が 1 日（要素数 = その月の日数）
+    pub days: Vec<u64>,
+}
+
+#[cw_serde]
+pub struct StatsWeeklyResp {
+    /// index 0 が ISO 第 1 週（要素数 = 52 または 53）
+    pub weeks: Vec<u64>,
+}
+
+#[cw_serde]
+pub struct PhenologySummaryResp {
+    /// 要求された年の昇順
+    pub years: Vec<PhenologyYear>,
+}
+
+#[cw_serde]
+pub struct PhenologyYear {
+    pub year: u32,
+    pub count: u64,
+    /// count = 0 の年は None
+    pub first_day: Option<u16>,
+    /// 偶数件のときは小さい側
+    pub median_day: Option<u16>,
+    pub last_day: Option<u16>,
+}
+
+/// 座標は 10 進文字列（JSON の浮動小数点はコントラクトで扱えないため）
+#[cw_serde]
+pub struct DecodeGeohashResp {
+    pub geohash: String,
+    pub min_lat: String,
+    pub min_lon: String,
+    pub max_lat: String,
+    pub max_lon: String,
+    pub center_lat: String,
+    pub center_lon: String,
+}
+
+#[cw_serde]
+pub struct PayloadSchemaResp {
+    pub schema: Option<super::state::PayloadSchema>,
+}
+
+/// None の項目は据え置き
+#[cw_serde]
+pub struct ConfigUpdate {
+    pub max_limit: Option<u32>,
+    pub default_limit: Option<u32>,
+    pub geohash_precision: Option<u8>,
+    pub cid_min_len: Option<u32>,
+    pub cid_max_len: Option<u32>,
+    pub paused: Option<bool>,
+    pub max_payload_bytes: Option<u32>,
+    pub max_batch_size: Option<u32>,
+}
+
+#[cw_serde]
+pub struct MigrationStatusResp {
+    pub contract: String,
+    pub version: String,
+    /// None なら移行は完了している
+    pub migration: Option<super::state::MigrationState>,
+}
@@ -0,0 +1,150 @@
+//! 管理者が登録した PayloadSchema による Store 時の payload 検証
+
+use crate::error::ContractError;
+use crate::parse_decimal;
+use crate::state::{FieldKind, FieldRule, PayloadSchema};
+
+fn bad(msg: String) -> ContractError {
+    ContractError::BadRequest { msg }
+}
+
+fn lookup<'a>(payload: &'a serde_json::Value, path: &str) -> Option<&'a serde_json::Value> {
+    path.split('.')
+        .try_fold(payload, |v, key| v.as_object()?.get(key))
+}
+
+fn kind_name(kind: &FieldKind) -> &'static str {
+    match kind {
+        FieldKind::String => "string",
+        FieldKind::Number => "number",
+        FieldKind::Integer => "integer",
+        FieldKind::Decimal => "decimal",
+        FieldKind::Bool => "bool",
+        FieldKind::Object => "object",
+        FieldKind::Array => "array",
+    }
+}
+
+fn matches_kind(v: &serde_json::Value, kind: &FieldKind) -> bool {
+    match kind {
+        FieldKind::String => v.is_string(),
+        FieldKind::Number => v.is_number(),
+        FieldKind::Integer => v.is_i64() || v.is_u64(),
+        FieldKind::Decimal => numeric_value(v).is_some(),
+        FieldKind::Bool => v.is_boolean(),
+        FieldKind::Object => v.is_object(),
+        FieldKind::Array => v.is_array(),
+    }
+}
+
+fn numeric_value(v: &serde_json::Value) -> Option<f64> {
+    match v {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(s) => parse_decimal(s),
+        _ => None,
+    }
+}
+
+/// SetPayloadSchema 時の整合性チェック
+pub fn check_schema(schema: &PayloadSchema) -> Result<(), ContractError> {
+    for (i, rule) in schema.fields.iter().enumerate() {
+        if rule.path.is_empty() || rule.path.split('.').any(|k| k.is_empty()) {
+            return Err(bad(format!("fields[{}].path is invalid", i)));
+        }
+        if schema.fields[..i].iter().any(|r| r.path == rule.path) {
+            return Err(bad(format!("duplicate rule for {}", rule.path)));
+        }
+        let min = parse_bound(rule, "min", rule.min.as_deref())?;
+        let max = parse_bound(rule, "max", rule.max.as_deref())?;
+        if let (Some(lo), Some(hi)) = (min, max) {
+            if lo > hi {
+                return Err(bad(format!("{}: min must be <= max", rule.path)));
+            }
+        }
+        if let Some(values) = &rule.one_of {
+            if values.is_empty() {
+                return Err(bad(format!("{}: one_of must not be empty", rule.path)));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_bound(
+    rule: &FieldRule,
+    name: &str,
+    v: Option<&str>,
+) -> Result<Option<f64>, ContractError> {
+    match v {
+        None => Ok(None),
+        Some(s) => parse_decimal(s)
+            .map(Some)
+            .ok_or_else(|| bad(format!("{}: {} must be a decimal string", rule.path, name))),
+    }
+}
+
+/// payload をスキーマに照らして検証。最初に見つかった違反を返す
+pub fn validate_payload(
+    schema: &PayloadSchema,
+    payload: &serde_json::Value,
+) -> Result<(), ContractError> {
+    for rule in &schema.fields {
+        let value = match lookup(payload, &rule.path) {
+            Some(serde_json::Value::Null) | None => {
+                if rule.required {
+                    return Err(bad(format!("payload.{} is required", rule.path)));
+                }
+                continue;
+            }
+            Some(v) => v,
+        };
+
+        if let Some(kind) = &rule.kind {
+            if !matches_kind(value, kind) {
+                return Err(bad(format!(
+                    "payload.{} must be {}",
+                    rule.path,
+                    kind_name(kind)
+                )));
+            }
+        }
+
+        if rule.min.is_some() || rule.max.is_some() {
+            let n = numeric_value(value)
+                .ok_or_else(|| bad(format!("payload.{} must be numeric", rule.path)))?;
+            if let Some(lo) = parse_bound(rule, "min", rule.min.as_deref())? {
+                if n < lo {
+                    return Err(bad(format!(
+                        "payload.{} must be >= {}",
+                        rule.path,
+                        rule.min.as_deref().unwrap_or_default()
+                    )));
+                }
+            }
+            if let Some(hi) = parse_bound(rule, "max", rule.max.as_deref())? {
+                if n > hi {
+                    return Err(bad(format!(
+                        "payload.{} must be <= {}",
+                        rule.path,
+                        rule.max.as_deref().unwrap_or_default()
+                    )));
+                }
+            }
+        }
+
+        if let Some(values) = &rule.one_of {
+            let ok = value
+                .as_str()
+                .map(|s| values.iter().any(|x| x == s))
+                .unwrap_or(false);
+            if !ok {
+                return Err(bad(format!(
+                    "payload.{} must be one of: {}",
+                    rule.path,
+                    values.join(", ")
+                )));
+            }
+        }
+    }
+    Ok(())
+}
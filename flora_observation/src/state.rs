@@ -1,10 +1,238 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::Addr;
+use cosmwasm_std::{Addr, Coin, Order, StdError, StdResult, Storage};
 use cw_storage_plus::{Item, Map};
 
 pub const NEXT_ID: Item<u64> = Item::new("next_id");
-pub const ADMIN: Item<Addr> = Item::new("admin");
-pub const VERIFIERS: Map<&Addr, bool> = Map::new("verifiers");
+
+// 運用パラメータ（instantiate で設定、UpdateConfig で変更）
+pub const CONFIG: Item<Config> = Item::new("config");
+
+#[cw_serde]
+pub struct Config {
+    /// 一覧系の limit の上限と、省略時の既定値
+    pub max_limit: u32,
+    pub default_limit: u32,
+    /// BY_GEOHASH に保存する geohash の桁数（1..=12）。変更すると全レコードを再インデックス
+    pub geohash_precision: u8,
+    /// CID（"ipfs://" を除く）の長さの範囲
+    pub cid_min_len: u32,
+    pub cid_max_len: u32,
+    /// true の間は Store / StoreBatch を受け付けない
+    pub paused: bool,
+    /// payload（JSON にシリアライズしたバイト数）の上限
+    pub max_payload_bytes: u32,
+    /// StoreBatch 1 回あたりの件数上限
+    pub max_batch_size: u32,
+}
+
+// 権限（role, addr）。expires_at を過ぎた付与は無効
+pub const ROLES: Map<(&str, &Addr), RoleGrant> = Map::new("roles");
+// 合意判定のパラメータ（未設定なら ConsensusParams::default()）
+pub const CONSENSUS_PARAMS: Item<ConsensusParams> = Item::new("consensus_params");
+// ProposeAdmin で指名された引き継ぎ先
+pub const PENDING_ADMIN: Item<PendingAdmin> = Item::new("pending_admin");
+
+// 旧形式の権限（migrate で ROLES に移して削除）
+pub const LEGACY_ADMIN: Item<Addr> = Item::new("admin");
+pub const LEGACY_VERIFIERS: Map<&Addr, bool> = Map::new("verifiers");
+pub const LEGACY_MODERATORS: Map<&Addr, bool> = Map::new("moderators");
+// 旧形式の設定（migrate で CONFIG に移して削除）
+pub const LEGACY_GEOHASH_PRECISION: Item<u8> = Item::new("geohash_precision");
+pub const LEGACY_MAX_BATCH_SIZE: Item<u32> = Item::new("max_batch_size");
+
+#[cw_serde]
+#[derive(Copy)]
+pub enum Role {
+    /// 権限管理・設定変更・Purge。moderator の操作も可
+    Admin,
+    /// Hide / Unhide / DismissReport
+    Moderator,
+    /// Verify
+    Verifier,
+    /// 一括投入
+    Importer,
+}
+
+impl Role {
+    pub const ALL: [Role; 4] = [Role::Admin, Role::Moderator, Role::Verifier, Role::Importer];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::Moderator => "moderator",
+            Role::Verifier => "verifier",
+            Role::Importer => "importer",
+        }
+    }
+}
+
+#[cw_serde]
+pub struct RoleGrant {
+    pub granted_by: Addr,
+    pub granted_at: u64,
+    /// この時刻（秒）以降は無効。None は無期限
+    pub expires_at: Option<u64>,
+}
+
+impl RoleGrant {
+    pub fn is_active(&self, now: u64) -> bool {
+        self.expires_at.is_none_or(|t| now < t)
+    }
+}
+
+#[cw_serde]
+pub struct ConsensusParams {
+    /// 首位 taxon の重み（confidence 合計）の割合の下限（%, 51..=100）
+    pub threshold_pct: u8,
+    /// 首位 taxon を支持する検証者数の下限
+    pub min_verifiers: u32,
+}
+
+impl Default for ConsensusParams {
+    fn default() -> Self {
+        ConsensusParams {
+            threshold_pct: 67,
+            min_verifiers: 2,
+        }
+    }
+}
+
+#[cw_serde]
+pub enum QualityGrade {
+    /// species または位置が無い
+    Casual,
+    NeedsId,
+    /// 検証者の合意あり
+    ResearchGrade,
+}
+
+impl QualityGrade {
+    pub fn key(&self) -> &'static str {
+        match self {
+            QualityGrade::Casual => "casual",
+            QualityGrade::NeedsId => "needs_id",
+            QualityGrade::ResearchGrade => "research_grade",
+        }
+    }
+}
+
+#[cw_serde]
+pub struct PendingAdmin {
+    pub proposer: Addr,
+    pub candidate: Addr,
+}
+// true なら admin も UpdateRecord で他人のレコードを修正できる
+pub const ADMIN_CAN_EDIT: Item<bool> = Item::new("admin_can_edit");
+// 投稿できる cw721 保有者の制限（未設定なら誰でも可）
+pub const GATE: Item<GateConfig> = Item::new("gate");
+// ゲートに使われたトークン -> レコード id
+pub const USED_GATE_TOKENS: Map<&str, u64> = Map::new("used_gate_tokens");
+// 保存時に観察記録 NFT を発行する s_mint（未設定なら発行しない）
+pub const NFT_MINTER: Item<NftMinterConfig> = Item::new("nft_minter");
+// 発行中（reply 待ち）のレコード id -> トークン。同じ tx の中でしか残らない
+pub const PENDING_MINTS: Map<u64, String> = Map::new("pending_mints");
+// 発行済みのトークン -> レコード id
+pub const RECORD_BY_TOKEN: Map<&str, u64> = Map::new("record_by_token");
+// 保存・検証・非表示を通知するコントラクト（admin が管理）
+pub const HOOKS: Map<&Addr, ()> = Map::new("hooks");
+// 投稿の件数クォータとデポジット（未設定なら制限なし）
+pub const SPAM_POLICY: Item<SpamPolicy> = Item::new("spam_policy");
+
+// Store 時の payload 検証プロファイル（未設定なら observed_at のみ必須）
+pub const PAYLOAD_SCHEMA: Item<PayloadSchema> = Item::new("payload_schema");
+
+#[cw_serde]
+pub struct NftMinterConfig {
+    /// s_mint（cw721 public mint）のコントラクト
+    pub contract: Addr,
+    /// token_id は "<prefix><レコード id>"
+    pub token_id_prefix: String,
+}
+
+#[cw_serde]
+pub struct GateConfig {
+    /// cw721 コントラクト（例: s_mint の入場券）
+    pub contract: Addr,
+    pub mode: GateMode,
+}
+
+#[cw_serde]
+#[derive(Copy)]
+pub enum GateMode {
+    /// いずれかのトークンを保有
+    Any,
+    /// メッセージの gate_token_id を保有
+    Specific,
+    /// まだ投稿に使われていないトークンを保有（1 トークン 1 投稿）
+    Unused,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct SpamPolicy {
+    pub quota: Option<StoreQuota>,
+    pub deposit: Option<DepositConfig>,
+}
+
+/// window_secs 秒（block time）あたり max_stores 件まで
+#[cw_serde]
+pub struct StoreQuota {
+    pub max_stores: u32,
+    pub window_secs: u64,
+}
+
+#[cw_serde]
+pub struct DepositConfig {
+    /// 1 レコードあたりのデポジット
+    pub amount: Coin,
+    /// この秒数が過ぎると投稿者が ClaimDeposits で受け取れる
+    pub challenge_period_secs: u64,
+    /// spam として Hide されたときの没収先
+    pub treasury: Addr,
+}
+
+/// レコード 1 件分の預かり。レコードが取り下げ・削除されても release_at までは動かさない
+#[cw_serde]
+pub struct Deposit {
+    pub record_id: u64,
+    pub owner: Addr,
+    pub amount: Coin,
+    pub release_at: u64,
+    /// 預かった時点の没収先
+    pub treasury: Addr,
+    /// 精算時に treasury へ送る。spam Hide・Purge で立ち、release_at 前の Unhide で戻る
+    pub forfeited: bool,
+}
+
+#[cw_serde]
+pub struct PayloadSchema {
+    pub fields: Vec<FieldRule>,
+}
+
+#[cw_serde]
+pub struct FieldRule {
+    /// ドット区切りのパス（例: "place.lat"）
+    pub path: String,
+    pub required: bool,
+    pub kind: Option<FieldKind>,
+    /// 数値系（number / integer / decimal）の下限・上限。10 進文字列
+    pub min: Option<String>,
+    pub max: Option<String>,
+    /// 文字列の許容値
+    pub one_of: Option<Vec<String>>,
+}
+
+#[cw_serde]
+pub enum FieldKind {
+    String,
+    Number,
+    Integer,
+    /// 数値または 10 進文字列（"35.681"）
+    Decimal,
+    Bool,
+    Object,
+    Array,
+}
 
 #[cw_serde]
 pub struct StoredRecord {
@@ -14,6 +242,7 @@ pub struct StoredRecord {
     // 主要インデックス項目
     pub observed_at: u64,
     pub species: Option<String>,
+    pub phenophase: Option<String>,
     pub geohash_prefix: String,
 
     // CID（必須）
@@ -30,8 +259,146 @@ pub struct StoredRecord {
     pub hidden: bool,
     pub hidden_reason: Option<String>,
 
-    pub annotations: Vec<Annotation>,
-    pub verifications: Vec<VerificationEntry>,
+    // 検証の集約結果（Store / Verify / UpdateRecord のたびに再計算）
+    pub consensus_taxon: Option<String>,
+    pub quality_grade: Option<QualityGrade>,
+
+    // 最後の UpdateRecord（未編集なら None）
+    pub edited: Option<EditInfo>,
+
+    // 投稿に使ったゲートのトークン（ゲート無し・importer は None）
+    pub gate_token_id: Option<String>,
+
+    // 保存時に発行した観察記録 NFT（reply で設定）
+    pub nft_token_id: Option<String>,
+
+    // 本体は ANNOTATIONS / VERIFICATIONS（件数のみ保持）
+    pub annotation_count: u32,
+    /// 現在の見解の件数（履歴は含まない）
+    pub verification_count: u32,
+}
+
+#[cw_serde]
+pub struct EditInfo {
+    pub revision: u32,
+    pub by: Addr,
+    pub block_time: u64,
+    pub block_height: u64,
+}
+
+/// UpdateRecord で置き換えられた版（revision 0 が Store 時の内容）
+#[cw_serde]
+pub struct RecordRevision {
+    pub revision: u32,
+    pub payload: serde_json::Value,
+    pub cid: String,
+    pub editor: Addr,
+    pub block_time: u64,
+    pub block_height: u64,
+}
+
+/// Withdraw / Purge で削除されたレコードの痕跡（ミラー側の削除用）
+#[cw_serde]
+pub struct Tombstone {
+    pub id: u64,
+    pub sender: Addr,
+    pub cid: String,
+    pub reason: TombstoneReason,
+    pub by: Addr,
+    pub block_time: u64,
+    pub block_height: u64,
+}
+
+#[cw_serde]
+pub enum TombstoneReason {
+    /// 投稿者による取り下げ
+    Withdrawn,
+    /// admin による削除
+    Purged,
+}
+
+/// 利用者からの通報
+#[cw_serde]
+pub struct Report {
+    pub id: u64,
+    pub record_id: u64,
+    pub reporter: Addr,
+    pub category: ReportCategory,
+    pub note: Option<String>,
+    pub block_time: u64,
+    pub block_height: u64,
+    pub status: ReportStatus,
+    /// 対応した moderator（Open の間は None）
+    pub resolved_by: Option<Addr>,
+}
+
+#[cw_serde]
+pub enum ReportCategory {
+    Spam,
+    Inappropriate,
+    Misidentified,
+    WrongLocation,
+    Duplicate,
+    Other,
+}
+
+#[cw_serde]
+pub enum ReportStatus {
+    Open,
+    /// Hide により対応済み
+    Actioned,
+    Dismissed,
+    /// レコードが取り下げ・削除された
+    Closed,
+}
+
+/// モデレーション操作の監査ログ（追記のみ）
+#[cw_serde]
+pub struct AuditEntry {
+    pub seq: u64,
+    pub record_id: u64,
+    pub action: ModerationAction,
+    pub actor: Addr,
+    pub report_id: Option<u64>,
+    pub reason: Option<String>,
+    pub block_time: u64,
+    pub block_height: u64,
+}
+
+#[cw_serde]
+pub enum ModerationAction {
+    Report,
+    Hide,
+    Unhide,
+    DismissReport,
+    Purge,
+}
+
+/// 変更フィードの 1 件。内容は id で引き直す（Delete なら Tombstone）
+#[cw_serde]
+pub struct Change {
+    pub seq: u64,
+    pub id: u64,
+    pub kind: ChangeKind,
+    pub block_height: u64,
+}
+
+#[cw_serde]
+pub enum ChangeKind {
+    /// Store / StoreBatch
+    Store,
+    /// UpdateRecord・記録 NFT の発行・再インデックス（geohash 等の作り直し）
+    Update,
+    /// AppendAnnotation / RemoveTag
+    Annotate,
+    /// Verify / RetractVerification（合意・グレードも変わりうる）
+    Verify,
+    /// RecomputeGrades で合意・グレードが変わった
+    Regrade,
+    Hide,
+    Unhide,
+    /// Withdraw / Purge
+    Delete,
 }
 
 #[cw_serde]
@@ -43,21 +410,267 @@ pub struct Annotation {
     pub tags: Option<Vec<String>>,
 }
 
+/// 検証者ごとの現在の見解（1 レコードにつき 1 人 1 件）
 #[cw_serde]
 pub struct VerificationEntry {
     pub at: u64,
     pub verifier: Addr,
     pub taxon_id: String,
+    /// 0..=100
     pub confidence: u8,
 }
 
+/// 置き換え・取り消しで現在の見解から外れた検証（seq は元の見解のもの）
+#[cw_serde]
+pub struct PastVerification {
+    pub seq: u64,
+    pub entry: VerificationEntry,
+    pub ended_at: u64,
+    pub ended_by: VerificationEnd,
+}
+
+#[cw_serde]
+pub enum VerificationEnd {
+    /// 同じ検証者の新しい Verify で置き換え
+    Superseded,
+    /// RetractVerification
+    Retracted,
+}
+
 pub const RECORDS: Map<u64, StoredRecord> = Map::new("records");
+pub const RECORD_HISTORY: Map<(u64, u32), RecordRevision> = Map::new("record_history"); // (id, revision)
+pub const TOMBSTONES: Map<u64, Tombstone> = Map::new("tombstones"); // id
+
+/// 旧形式（annotations / verifications をレコード内に持つ）の読み出し用。migrate でのみ使う
+pub const LEGACY_RECORDS_RAW: Map<u64, serde_json::Value> = Map::new("records");
+
+// 実行中の段階的な移行（完了すると消える。ある間は ContinueMigration 以外の execute を拒否）
+pub const MIGRATION: Item<MigrationState> = Item::new("migration");
+
+#[cw_serde]
+pub struct MigrationState {
+    /// 移行前の cw2 バージョン（cw2 導入前のインスタンスは "0.0.0"）
+    pub from_version: String,
+    pub to_version: String,
+    /// 残りの段階（先頭が実行中）
+    pub steps: Vec<MigrationStep>,
+    /// 実行中の段階で処理済みの最後のレコード id
+    pub cursor: Option<u64>,
+}
+
+#[cw_serde]
+pub enum MigrationStep {
+    /// 旧 ADMIN / VERIFIERS / MODERATORS を ROLES に移す
+    LegacyRoles,
+    /// レコード内の annotations / verifications を ANNOTATIONS / VERIFICATIONS に移す
+    InlineLists,
+    /// RECORD_TAGS / BY_TAG を消す
+    ClearTagIndex,
+    /// 注記のタグを正規化して索引し直す
+    TagIndex,
+    /// 検索インデックスと集計カウンタを消す
+    ClearIndexes,
+    /// geohash・合意・グレードを計算し直して索引し直す
+    Reindex,
+}
+
+impl MigrationStep {
+    pub fn key(&self) -> &'static str {
+        match self {
+            MigrationStep::LegacyRoles => "legacy_roles",
+            MigrationStep::InlineLists => "inline_lists",
+            MigrationStep::ClearTagIndex => "clear_tag_index",
+            MigrationStep::TagIndex => "tag_index",
+            MigrationStep::ClearIndexes => "clear_indexes",
+            MigrationStep::Reindex => "reindex",
+        }
+    }
+}
+
+// 注記
+pub const NEXT_ANNOTATION_SEQ: Item<u64> = Item::new("next_annotation_seq");
+pub const ANNOTATIONS: Map<(u64, u64), Annotation> = Map::new("annotations"); // (id, seq)
+
+// 投稿クォータの記録（block_time が window より古いものは次の投稿時に消す）
+pub const QUOTA_LOG: Map<(&Addr, u64, u64), ()> = Map::new("quota_log"); // (sender, block_time, id)
+
+// デポジット
+pub const DEPOSITS: Map<u64, Deposit> = Map::new("deposits"); // record id
+pub const DEPOSITS_BY_OWNER: Map<(&Addr, u64, u64), ()> = Map::new("idx_deposit_owner"); // (owner, release_at, id)
+
+// タグ（正規化済み）。1 レコードの同じタグは注記の数だけ参照を数える
+pub const RECORD_TAGS: Map<(u64, &str), u32> = Map::new("record_tags"); // (id, tag) -> 参照数
+pub const BY_TAG: Map<(&str, u64), ()> = Map::new("idx_tag"); // (tag, id)
+
+// 検証
+pub const NEXT_VERIFICATION_SEQ: Item<u64> = Item::new("next_verification_seq");
+pub const VERIFICATIONS: Map<(u64, u64), VerificationEntry> = Map::new("verifications"); // (id, seq) 現在の見解
+pub const BY_VERIFIER: Map<(&Addr, u64), u64> = Map::new("idx_verifier"); // (verifier, id) -> seq
+pub const VERIFICATION_HISTORY: Map<(u64, u64), PastVerification> =
+    Map::new("verification_history"); // (id, seq)
+
+// 通報と監査ログ
+pub const NEXT_REPORT_ID: Item<u64> = Item::new("next_report_id");
+pub const REPORTS: Map<u64, Report> = Map::new("reports"); // report_id
+pub const OPEN_REPORTS: Map<u64, ()> = Map::new("open_reports"); // report_id（未対応のみ）
+pub const REPORTS_BY_RECORD: Map<(u64, u64), ()> = Map::new("reports_by_record"); // (record_id, report_id)
+pub const OPEN_REPORT_BY_REPORTER: Map<(u64, &Addr), u64> = Map::new("open_report_by_reporter"); // (record_id, reporter) -> report_id
+pub const NEXT_AUDIT_SEQ: Item<u64> = Item::new("next_audit_seq");
+pub const AUDIT_LOG: Map<u64, AuditEntry> = Map::new("audit_log"); // seq
+pub const AUDIT_BY_RECORD: Map<(u64, u64), ()> = Map::new("audit_by_record"); // (record_id, seq)
+
+// 変更フィード（オフチェーンのミラー向け）。seq は 1 からの連番
+pub const NEXT_CHANGE_SEQ: Item<u64> = Item::new("next_change_seq");
+pub const CHANGES: Map<u64, Change> = Map::new("changes"); // seq
 
 // セカンダリ・インデックス
 pub const BY_TIME: Map<(u64, u64), ()> = Map::new("idx_time");       // (observed_at, id)
-pub const BY_SPECIES: Map<(String, u64), ()> = Map::new("idx_species"); // (species_norm, id)
 pub const BY_GEOHASH: Map<(String, u64), ()> = Map::new("idx_geohash"); // (geohash_prefix, id)
 
+// 複合インデックス（期間指定の走査でキーだけを辿る）
+pub const BY_SPECIES_TIME: Map<(String, u64, u64), ()> = Map::new("idx_species_time"); // (species_norm, observed_at, id)
+pub const BY_GEOHASH_TIME: Map<(String, u64, u64), ()> = Map::new("idx_geohash_time"); // (geohash_prefix, observed_at, id)
+pub const BY_SENDER_TIME: Map<(&Addr, u64, u64), ()> = Map::new("idx_sender_time"); // (sender, observed_at, id)
+pub const BY_PHENOPHASE_TIME: Map<(String, u64, u64), ()> = Map::new("idx_phenophase_time"); // (phenophase_norm, observed_at, id)
+pub const BY_GRADE_TIME: Map<(String, u64, u64), ()> = Map::new("idx_grade_time"); // (quality_grade, observed_at, id)
+
+// 旧 (species_norm, id) インデックス。BY_SPECIES_TIME に置き換えたので migrate で消すだけ
+pub const LEGACY_BY_SPECIES: Map<(String, u64), ()> = Map::new("idx_species");
+
+/// レコードの全セカンダリ・インデックスを登録
+pub fn index_record(store: &mut dyn Storage, rec: &StoredRecord) -> StdResult<()> {
+    BY_TIME.save(store, (rec.observed_at, rec.id), &())?;
+    BY_SENDER_TIME.save(store, (&rec.sender, rec.observed_at, rec.id), &())?;
+    if let Some(sp) = &rec.species {
+        BY_SPECIES_TIME.save(store, (sp.clone(), rec.observed_at, rec.id), &())?;
+    }
+    if let Some(ph) = &rec.phenophase {
+        BY_PHENOPHASE_TIME.save(store, (ph.clone(), rec.observed_at, rec.id), &())?;
+    }
+    if let Some(g) = &rec.quality_grade {
+        BY_GRADE_TIME.save(store, (g.key().to_string(), rec.observed_at, rec.id), &())?;
+    }
+    if !rec.geohash_prefix.is_empty() {
+        let geo = rec.geohash_prefix.clone();
+        BY_GEOHASH.save(store, (geo.clone(), rec.id), &())?;
+        BY_GEOHASH_TIME.save(store, (geo, rec.observed_at, rec.id), &())?;
+    }
+    Ok(())
+}
+
+/// index_record で登録したエントリを全て削除（UpdateRecord / 削除の前に呼ぶ）
+pub fn unindex_record(store: &mut dyn Storage, rec: &StoredRecord) {
+    BY_TIME.remove(store, (rec.observed_at, rec.id));
+    BY_SENDER_TIME.remove(store, (&rec.sender, rec.observed_at, rec.id));
+    if let Some(sp) = &rec.species {
+        BY_SPECIES_TIME.remove(store, (sp.clone(), rec.observed_at, rec.id));
+    }
+    if let Some(ph) = &rec.phenophase {
+        BY_PHENOPHASE_TIME.remove(store, (ph.clone(), rec.observed_at, rec.id));
+    }
+    if let Some(g) = &rec.quality_grade {
+        BY_GRADE_TIME.remove(store, (g.key().to_string(), rec.observed_at, rec.id));
+    }
+    if !rec.geohash_prefix.is_empty() {
+        let geo = rec.geohash_prefix.clone();
+        BY_GEOHASH.remove(store, (geo.clone(), rec.id));
+        BY_GEOHASH_TIME.remove(store, (geo, rec.observed_at, rec.id));
+    }
+}
+
+// 集計カウンタ（非表示でないレコードのみ。月は UTC）
+pub const CNT_TOTAL: Item<u64> = Item::new("cnt_total");
+pub const CNT_SPECIES: Map<&str, u64> = Map::new("cnt_species"); // species_norm
+pub const CNT_GEOHASH: Map<&str, u64> = Map::new("cnt_geohash"); // geohash の先頭 1..=precision 文字
+pub const CNT_MONTH: Map<(u32, u8), u64> = Map::new("cnt_month"); // (year, month)
+pub const CNT_SPECIES_MONTH: Map<(&str, u32, u8), u64> = Map::new("cnt_species_month"); // (species_norm, year, month)
+pub const CNT_TAG: Map<&str, u64> = Map::new("cnt_tag"); // tag -> レコード数
+
+fn bump<'a, K>(store: &mut dyn Storage, map: Map<'a, K, u64>, key: K, up: bool) -> StdResult<()>
+where
+    K: cw_storage_plus::PrimaryKey<'a> + Clone,
+{
+    let cur = map.may_load(store, key.clone())?.unwrap_or(0);
+    let next = if up { cur + 1 } else { cur.saturating_sub(1) };
+    if next == 0 {
+        map.remove(store, key);
+    } else {
+        map.save(store, key, &next)?;
+    }
+    Ok(())
+}
+
+/// レコード 1 件分だけカウンタを増減（up = true で加算）
+pub fn adjust_counters(store: &mut dyn Storage, rec: &StoredRecord, up: bool) -> StdResult<()> {
+    let total = CNT_TOTAL.may_load(store)?.unwrap_or(0);
+    let total = if up {
+        total + 1
+    } else {
+        total.saturating_sub(1)
+    };
+    CNT_TOTAL.save(store, &total)?;
+
+    let (y, m, _) =
+        crate::calendar::civil_from_days(crate::calendar::local_days(rec.observed_at, 0));
+    let y = u32::try_from(y)
+        .map_err(|_| StdError::generic_err("observed_at is out of range for monthly counters"))?;
+    let m = m as u8;
+    bump(store, CNT_MONTH, (y, m), up)?;
+
+    if let Some(sp) = &rec.species {
+        bump(store, CNT_SPECIES, sp.as_str(), up)?;
+        bump(store, CNT_SPECIES_MONTH, (sp.as_str(), y, m), up)?;
+    }
+    for len in 1..=rec.geohash_prefix.len() {
+        bump(store, CNT_GEOHASH, &rec.geohash_prefix[..len], up)?;
+    }
+    let tags: Vec<String> = RECORD_TAGS
+        .prefix(rec.id)
+        .keys(store, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
+    for tag in &tags {
+        bump(store, CNT_TAG, tag.as_str(), up)?;
+    }
+    Ok(())
+}
+
+/// 注記 1 件分のタグ参照を追加（レコードに初めて付いたタグなら索引と集計に反映）
+pub fn tag_record(store: &mut dyn Storage, rec: &StoredRecord, tag: &str) -> StdResult<()> {
+    let refs = RECORD_TAGS.may_load(store, (rec.id, tag))?.unwrap_or(0);
+    RECORD_TAGS.save(store, (rec.id, tag), &(refs + 1))?;
+    if refs == 0 {
+        BY_TAG.save(store, (tag, rec.id), &())?;
+        if !rec.hidden {
+            bump(store, CNT_TAG, tag, true)?;
+        }
+    }
+    Ok(())
+}
+
+/// 注記 1 件分のタグ参照を外す（最後の参照なら索引と集計からも外す）
+pub fn untag_record(store: &mut dyn Storage, rec: &StoredRecord, tag: &str) -> StdResult<()> {
+    let refs = RECORD_TAGS.may_load(store, (rec.id, tag))?.unwrap_or(0);
+    if refs > 1 {
+        return RECORD_TAGS.save(store, (rec.id, tag), &(refs - 1));
+    }
+    RECORD_TAGS.remove(store, (rec.id, tag));
+    BY_TAG.remove(store, (tag, rec.id));
+    if refs == 1 && !rec.hidden {
+        bump(store, CNT_TAG, tag, false)?;
+    }
+    Ok(())
+}
+
 pub fn normalize_species(s: &str) -> String {
     s.trim().to_ascii_lowercase()
 }
+
+pub fn normalize_phenophase(s: &str) -> String {
+    s.trim().to_ascii_lowercase()
+}
+
+/// 前後の空白と先頭の '#' を除いて小文字化（"#BioBlitz2025" → "bioblitz2025"）
+pub fn normalize_tag(s: &str) -> String {
+    let s = s.trim();
+    s.strip_prefix('#').unwrap_or(s).trim().to_lowercase()
+}
@@ -0,0 +1,175 @@
+use cosmwasm_std::{Addr, Order, Storage};
+
+use super::*;
+use crate::state::{
+    ANNOTATIONS, BY_VERIFIER, LEGACY_RECORDS_RAW, NEXT_ANNOTATION_SEQ, NEXT_VERIFICATION_SEQ,
+    VERIFICATIONS,
+};
+
+fn annotate(deps: &mut TestDeps, who: &str, id: u64, note: &str) {
+    exec(
+        deps,
+        who,
+        json!({"append_annotation": {"id": id, "note": note}}),
+    )
+    .unwrap();
+}
+
+fn migrate(deps: &mut TestDeps) {
+    crate::migrate(
+        deps.as_mut(),
+        mock_env(),
+        crate::msg::MigrateMsg {
+            geohash_precision: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+}
+
+/// 保存済みレコードを旧形式（注記・検証をレコード内に持つ）へ書き戻す
+fn make_legacy(deps: &mut TestDeps, id: u64, annotations: Value, verifications: Value) {
+    let mut raw = LEGACY_RECORDS_RAW.load(&deps.storage, id).unwrap();
+    let obj = raw.as_object_mut().unwrap();
+    obj.remove("annotation_count");
+    obj.remove("verification_count");
+    obj.insert("annotations".into(), annotations);
+    obj.insert("verifications".into(), verifications);
+    LEGACY_RECORDS_RAW
+        .save(&mut deps.storage, id, &raw)
+        .unwrap();
+    // cw2 導入前のインスタンスとして移行させる
+    deps.storage.remove(b"contract_info");
+}
+
+#[test]
+fn annotations_are_paged_by_seq() {
+    let mut deps = setup();
+    let a = store(&mut deps, "alice", 10, "Prunus mume", "35.681", "139.767");
+    let b = store(&mut deps, "alice", 20, "Prunus mume", "35.681", "139.767");
+    annotate(&mut deps, "alice", a, "n1");
+    annotate(&mut deps, "bob", b, "other");
+    annotate(&mut deps, "bob", a, "n2");
+    annotate(&mut deps, "alice", a, "n3");
+
+    let rec = query_json(&deps, json!({"get": {"id": a}}))["record"].clone();
+    assert_eq!(rec["annotation_count"], 3);
+
+    let page = query_json(&deps, json!({"annotations": {"id": a, "limit": 2}}));
+    let got = page["annotations"].as_array().unwrap();
+    let notes: Vec<&str> = got
+        .iter()
+        .map(|a| a["annotation"]["note"].as_str().unwrap())
+        .collect();
+    assert_eq!(notes, vec!["n1", "n2"]);
+    assert_eq!(got[1]["annotation"]["by"], "bob");
+    let next = page["next_start_after"].as_u64().unwrap();
+    assert_eq!(next, got[1]["seq"].as_u64().unwrap());
+
+    let page = query_json(
+        &deps,
+        json!({"annotations": {"id": a, "start_after": next, "limit": 2}}),
+    );
+    let got = page["annotations"].as_array().unwrap();
+    assert_eq!(got.len(), 1);
+    assert_eq!(got[0]["annotation"]["note"], "n3");
+    assert!(page["next_start_after"].is_null());
+
+    let err = exec(
+        &mut deps,
+        "alice",
+        json!({"append_annotation": {"id": 99, "note": "x"}}),
+    );
+    assert!(matches!(err, Err(ContractError::NotFound)));
+}
+
+#[test]
+fn migrate_moves_inline_lists() {
+    let mut deps = setup();
+    let id = store(&mut deps, "alice", 10, "Prunus mume", "35.681", "139.767");
+    let untouched = store(&mut deps, "alice", 20, "Prunus mume", "35.681", "139.767");
+    annotate(&mut deps, "alice", untouched, "kept");
+    make_legacy(
+        &mut deps,
+        id,
+        json!([
+            {"at": 11, "by": "alice", "note": "first", "photo_cid": null, "tags": null},
+            {"at": 12, "by": "bob", "note": null, "photo_cid": null, "tags": ["flower"]},
+        ]),
+        // ver の 2 件目が現在の見解、1 件目は履歴へ
+        json!([
+            {"at": 13, "verifier": "ver", "taxon_id": "t0", "confidence": 40},
+            {"at": 14, "verifier": "ver2", "taxon_id": "t1", "confidence": 90},
+            {"at": 15, "verifier": "ver", "taxon_id": "t1", "confidence": 80},
+        ]),
+    );
+
+    migrate(&mut deps);
+
+    let rec = query_json(&deps, json!({"get": {"id": id}}))["record"].clone();
+    assert_eq!(rec["annotation_count"], 2);
+    assert_eq!(rec["verification_count"], 2);
+    assert_eq!(rec["consensus_taxon"], "t1");
+    assert_eq!(rec["quality_grade"], "research_grade");
+
+    let anns = query_json(&deps, json!({"annotations": {"id": id}}));
+    let anns = anns["annotations"].as_array().unwrap();
+    assert_eq!(anns.len(), 2);
+    assert_eq!(anns[0]["annotation"]["note"], "first");
+    assert_eq!(anns[1]["annotation"]["tags"], json!(["flower"]));
+    // 移行前から分離済みのレコードはそのまま
+    let kept = query_json(&deps, json!({"annotations": {"id": untouched}}));
+    assert_eq!(kept["annotations"][0]["annotation"]["note"], "kept");
+    assert_eq!(
+        ANNOTATIONS
+            .keys(&deps.storage, None, None, Order::Ascending)
+            .count(),
+        3
+    );
+
+    let current = query_json(&deps, json!({"verifications": {"id": id}}));
+    let current = current["verifications"].as_array().unwrap();
+    let who: Vec<(&str, &str)> = current
+        .iter()
+        .map(|v| {
+            (
+                v["entry"]["verifier"].as_str().unwrap(),
+                v["entry"]["taxon_id"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(who, vec![("ver2", "t1"), ("ver", "t1")]);
+    let past = query_json(&deps, json!({"verification_history": {"id": id}}));
+    let past = past["entries"].as_array().unwrap();
+    assert_eq!(past.len(), 1);
+    assert_eq!(past[0]["entry"]["taxon_id"], "t0");
+    assert_eq!(past[0]["ended_at"], 15);
+    assert_eq!(past[0]["ended_by"], "superseded");
+
+    let ver = BY_VERIFIER
+        .load(&deps.storage, (&Addr::unchecked("ver"), id))
+        .unwrap();
+    assert_eq!(ver, current[1]["seq"].as_u64().unwrap());
+
+    // 採番は移行分の後ろから続く
+    let next = NEXT_ANNOTATION_SEQ.load(&deps.storage).unwrap();
+    annotate(&mut deps, "alice", id, "after");
+    let anns = query_json(
+        &deps,
+        json!({"annotations": {"id": id, "start_after": next - 1}}),
+    );
+    assert_eq!(anns["annotations"][0]["annotation"]["note"], "after");
+    assert!(NEXT_VERIFICATION_SEQ.load(&deps.storage).unwrap() > ver);
+
+    // 2 回目の migrate は何もしない
+    migrate(&mut deps);
+    assert_eq!(
+        VERIFICATIONS
+            .prefix(id)
+            .keys(&deps.storage, None, None, Order::Ascending)
+            .count(),
+        2
+    );
+    let rec = query_json(&deps, json!({"get": {"id": id}}))["record"].clone();
+    assert_eq!(rec["annotation_count"], 3);
+}
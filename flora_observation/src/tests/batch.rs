@@ -0,0 +1,161 @@
+use super::*;
+
+fn item(observed_at: u64, species: &str) -> Value {
+    json!({
+        "payload": {
+            "observed_at": observed_at,
+            "species": species,
+            "place": {"lat": "35.681", "lon": "139.767"},
+        },
+        "cid": CID,
+    })
+}
+
+/// CID が不正で new_record に弾かれる項目
+fn bad_item(observed_at: u64) -> Value {
+    let mut v = item(observed_at, "x");
+    v["cid"] = json!("not-a-cid");
+    v
+}
+
+fn batch(deps: &mut TestDeps, items: Vec<Value>, mode: &str) -> Result<Response, ContractError> {
+    exec(
+        deps,
+        "alice",
+        json!({"store_batch": {"items": items, "mode": mode}}),
+    )
+}
+
+fn total(deps: &TestDeps) -> u64 {
+    query_json(deps, json!({"count": {}}))["count"]
+        .as_u64()
+        .unwrap()
+}
+
+#[test]
+fn all_or_nothing_stores_every_item() {
+    let mut deps = setup();
+    store(&mut deps, "bob", 1, "a", "0", "0");
+    let res = batch(
+        &mut deps,
+        vec![item(10, "a"), item(20, "b"), item(30, "c")],
+        "all_or_nothing",
+    )
+    .unwrap();
+    assert_eq!(attr(&res, "stored"), "3");
+    assert_eq!(attr(&res, "first_id"), "2");
+    assert_eq!(attr(&res, "last_id"), "4");
+
+    let data: Value = serde_json::from_slice(res.data.unwrap().as_slice()).unwrap();
+    assert_eq!(data["first_id"], 2);
+    assert_eq!(data["last_id"], 4);
+    let got: Vec<u64> = data["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["id"].as_u64().unwrap())
+        .collect();
+    assert_eq!(got, vec![2, 3, 4]);
+
+    let rec = query_json(&deps, json!({"get": {"id": 3}}))["record"].clone();
+    assert_eq!(rec["sender"], "alice");
+    assert_eq!(rec["species"], "b");
+    assert_eq!(total(&deps), 4);
+    // 次の Store は続きの id
+    assert_eq!(store(&mut deps, "bob", 40, "d", "0", "0"), 5);
+}
+
+#[test]
+fn all_or_nothing_rejects_whole_batch() {
+    let mut deps = setup();
+    let err = batch(
+        &mut deps,
+        vec![item(10, "a"), bad_item(20), item(30, "c")],
+        "all_or_nothing",
+    );
+    assert!(bad_request(err).starts_with("item 1:"));
+    assert_eq!(total(&deps), 0);
+    assert!(query_json(&deps, json!({"get": {"id": 1}}))["record"].is_null());
+    assert_eq!(store(&mut deps, "bob", 40, "d", "0", "0"), 1);
+}
+
+#[test]
+fn best_effort_skips_failed_items() {
+    let mut deps = setup();
+    let res = batch(
+        &mut deps,
+        vec![bad_item(5), item(10, "a"), bad_item(20), item(30, "c")],
+        "best_effort",
+    )
+    .unwrap();
+    assert_eq!(attr(&res, "stored"), "2");
+    assert_eq!(attr(&res, "failed"), "2");
+    assert_eq!(attr(&res, "first_id"), "1");
+    assert_eq!(attr(&res, "last_id"), "2");
+    assert!(!attr(&res, "error.0").is_empty());
+    assert!(!attr(&res, "error.2").is_empty());
+
+    let data: Value = serde_json::from_slice(res.data.unwrap().as_slice()).unwrap();
+    let results = data["results"].as_array().unwrap();
+    assert_eq!(results.len(), 4);
+    assert!(results[0]["id"].is_null());
+    assert!(results[0]["error"].is_string());
+    assert_eq!(results[1]["id"], 1);
+    assert_eq!(results[2]["index"], 2);
+    assert!(results[2]["id"].is_null());
+    assert_eq!(results[3]["id"], 2);
+    // 失敗した項目は id を消費しない
+    assert_eq!(
+        query_json(&deps, json!({"get": {"id": 2}}))["record"]["species"],
+        "c"
+    );
+    assert_eq!(total(&deps), 2);
+
+    // 全件失敗でもエラーにはならない
+    let res = batch(&mut deps, vec![bad_item(1)], "best_effort").unwrap();
+    assert_eq!(attr(&res, "stored"), "0");
+    let data: Value = serde_json::from_slice(res.data.unwrap().as_slice()).unwrap();
+    assert!(data["first_id"].is_null());
+    assert_eq!(total(&deps), 2);
+}
+
+#[test]
+fn batch_size_limit() {
+    let mut deps = setup_with(json!({"verifiers": ["ver"], "max_batch_size": 2}));
+    let err = batch(&mut deps, vec![], "best_effort");
+    assert!(bad_request(err).contains("1..=2"));
+    let err = batch(
+        &mut deps,
+        vec![item(1, "a"), item(2, "a"), item(3, "a")],
+        "best_effort",
+    );
+    assert!(bad_request(err).contains("1..=2"));
+
+    assert!(matches!(
+        exec(
+            &mut deps,
+            "alice",
+            json!({"update_config": {"config": {"max_batch_size": 3}}})
+        ),
+        Err(ContractError::Unauthorized)
+    ));
+    let err = exec(
+        &mut deps,
+        "admin",
+        json!({"update_config": {"config": {"max_batch_size": 0}}}),
+    );
+    assert!(bad_request(err).contains("max_batch_size"));
+    exec(
+        &mut deps,
+        "admin",
+        json!({"update_config": {"config": {"max_batch_size": 3}}}),
+    )
+    .unwrap();
+    batch(
+        &mut deps,
+        vec![item(1, "a"), item(2, "a"), item(3, "a")],
+        "all_or_nothing",
+    )
+    .unwrap();
+    assert_eq!(total(&deps), 3);
+}
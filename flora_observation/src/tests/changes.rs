@@ -0,0 +1,195 @@
+use cosmwasm_std::{Reply, SubMsgResponse, SubMsgResult};
+
+use super::*;
+
+fn changes(deps: &TestDeps, seq: u64, limit: Option<u32>) -> Value {
+    query_json(deps, json!({"changes_since": {"seq": seq, "limit": limit}}))
+}
+
+/// seq より後の変更を (id, kind) で
+fn kinds_since(deps: &TestDeps, seq: u64) -> Vec<(u64, String)> {
+    changes(deps, seq, Some(100))["changes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| {
+            (
+                c["id"].as_u64().unwrap(),
+                c["kind"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect()
+}
+
+fn latest(deps: &TestDeps) -> u64 {
+    changes(deps, 0, Some(1))["latest_seq"].as_u64().unwrap()
+}
+
+fn kind(id: u64, k: &str) -> (u64, String) {
+    (id, k.to_string())
+}
+
+#[test]
+fn changes_are_paged_by_seq() {
+    let mut deps = setup();
+    let empty = changes(&deps, 0, None);
+    assert!(empty["changes"].as_array().unwrap().is_empty());
+    assert!(empty["next_seq"].is_null());
+    assert_eq!(empty["latest_seq"], 0);
+
+    for t in 1..=5 {
+        store(&mut deps, "alice", t, "a", "0", "0");
+    }
+
+    let first = changes(&deps, 0, Some(2));
+    let seqs: Vec<u64> = first["changes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["seq"].as_u64().unwrap())
+        .collect();
+    assert_eq!(seqs, vec![1, 2]);
+    assert_eq!(first["next_seq"], 2);
+    assert_eq!(first["latest_seq"], 5);
+    assert_eq!(first["changes"][0]["block_height"], mock_env().block.height);
+
+    // 受け取った最後の seq を渡すと続きから、重複も抜けもない
+    let second = changes(&deps, 2, Some(2));
+    assert_eq!(second["changes"][0]["seq"], 3);
+    assert_eq!(second["next_seq"], 4);
+    let last = changes(&deps, 4, Some(10));
+    assert_eq!(last["changes"].as_array().unwrap().len(), 1);
+    assert_eq!(last["changes"][0]["id"], 5);
+    assert!(last["next_seq"].is_null());
+    assert!(changes(&deps, 5, None)["changes"]
+        .as_array()
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn every_mutation_is_recorded() {
+    let mut deps = setup();
+    let id = store(&mut deps, "alice", 10, "a", "35.681", "139.767");
+    let mut seq = latest(&deps);
+    assert_eq!(kinds_since(&deps, 0), vec![kind(id, "store")]);
+
+    let mut step = |deps: &mut TestDeps, who: &str, msg: Value, expect: Vec<(u64, String)>| {
+        exec(deps, who, msg.clone()).unwrap();
+        assert_eq!(kinds_since(deps, seq), expect, "{msg}");
+        seq = latest(deps);
+    };
+
+    step(
+        &mut deps,
+        "alice",
+        json!({"update_record": {"id": id, "payload": {"observed_at": 11, "species": "a"}, "cid": CID}}),
+        vec![kind(id, "update")],
+    );
+    step(
+        &mut deps,
+        "bob",
+        json!({"append_annotation": {"id": id, "tags": ["bloom"]}}),
+        vec![kind(id, "annotate")],
+    );
+    step(
+        &mut deps,
+        "bob",
+        json!({"remove_tag": {"id": id, "seq": 1, "tag": "bloom"}}),
+        vec![kind(id, "annotate")],
+    );
+    step(
+        &mut deps,
+        "ver",
+        json!({"verify": {"id": id, "taxon_id": "t1", "confidence": 90}}),
+        vec![kind(id, "verify")],
+    );
+    step(
+        &mut deps,
+        "admin",
+        json!({"set_consensus_params": {"params": {"threshold_pct": 60, "min_verifiers": 1}}}),
+        vec![],
+    );
+    step(
+        &mut deps,
+        "admin",
+        json!({"recompute_grades": {}}),
+        vec![kind(id, "regrade")],
+    );
+    step(
+        &mut deps,
+        "ver",
+        json!({"retract_verification": {"id": id}}),
+        vec![kind(id, "verify")],
+    );
+    step(
+        &mut deps,
+        "admin",
+        json!({"hide": {"id": id}}),
+        vec![kind(id, "hide")],
+    );
+    step(
+        &mut deps,
+        "admin",
+        json!({"unhide": {"id": id}}),
+        vec![kind(id, "unhide")],
+    );
+
+    let items = vec![
+        json!({"payload": {"observed_at": 1}, "cid": CID}),
+        json!({"payload": {"observed_at": 2}, "cid": CID}),
+    ];
+    step(
+        &mut deps,
+        "alice",
+        json!({"store_batch": {"items": items, "mode": "all_or_nothing"}}),
+        vec![kind(id + 1, "store"), kind(id + 2, "store")],
+    );
+    step(
+        &mut deps,
+        "alice",
+        json!({"withdraw": {"id": id + 1}}),
+        vec![kind(id + 1, "delete")],
+    );
+    // 失敗した execute は何も残さない
+    assert!(exec(&mut deps, "bob", json!({"withdraw": {"id": id}})).is_err());
+    assert!(kinds_since(&deps, seq).is_empty());
+}
+
+#[test]
+fn nft_mint_and_reindex_are_recorded() {
+    let mut deps = setup();
+    exec(
+        &mut deps,
+        "admin",
+        json!({"set_nft_minter": {"minter": {"contract": "minter"}}}),
+    )
+    .unwrap();
+    let res = exec(
+        &mut deps,
+        "alice",
+        json!({"store": {"payload": {"observed_at": 1, "place": {"lat": "35.681", "lon": "139.767"}}, "cid": CID}}),
+    )
+    .unwrap();
+    let seq = latest(&deps);
+    let reply = Reply {
+        id: res.messages[0].id,
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: None,
+        }),
+    };
+    crate::reply(deps.as_mut(), mock_env(), reply).unwrap();
+    assert_eq!(kinds_since(&deps, seq), vec![kind(1, "update")]);
+
+    // 桁数の変更で geohash が変わったレコードは update として流れる
+    let seq = latest(&deps);
+    exec(
+        &mut deps,
+        "admin",
+        json!({"update_config": {"config": {"geohash_precision": 4}}}),
+    )
+    .unwrap();
+    exec(&mut deps, "admin", json!({"continue_migration": {}})).unwrap();
+    assert_eq!(kinds_since(&deps, seq), vec![kind(1, "update")]);
+}
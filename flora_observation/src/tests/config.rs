@@ -0,0 +1,155 @@
+use super::*;
+
+fn update(deps: &mut TestDeps, who: &str, config: Value) -> Result<Response, ContractError> {
+    exec(deps, who, json!({"update_config": {"config": config}}))
+}
+
+fn store_item(deps: &mut TestDeps, payload: Value, cid: &str) -> Result<Response, ContractError> {
+    exec(
+        deps,
+        "alice",
+        json!({"store": {"payload": payload, "cid": cid}}),
+    )
+}
+
+#[test]
+fn update_config_is_admin_only_and_validated() {
+    let mut deps = setup();
+    let cfg = query_json(&deps, json!({"config": {}}));
+    assert_eq!(cfg["geohash_precision"], 6);
+    assert_eq!(cfg["paused"], false);
+
+    assert!(matches!(
+        update(&mut deps, "alice", json!({"paused": true})),
+        Err(ContractError::Unauthorized)
+    ));
+    let err = update(&mut deps, "admin", json!({"default_limit": 0}));
+    assert!(bad_request(err).contains("default_limit"));
+    let err = update(&mut deps, "admin", json!({"cid_min_len": 300}));
+    assert!(bad_request(err).contains("cid_min_len"));
+    let err = update(&mut deps, "admin", json!({"max_payload_bytes": 0}));
+    assert!(bad_request(err).contains("max_payload_bytes"));
+    let err = update(&mut deps, "admin", json!({"geohash_precision": 13}));
+    assert!(err.is_err());
+    // 拒否された更新は何も変えない
+    assert_eq!(query_json(&deps, json!({"config": {}})), cfg);
+}
+
+#[test]
+fn paused_blocks_submissions() {
+    let mut deps = setup();
+    let res = update(&mut deps, "admin", json!({"paused": true})).unwrap();
+    assert_eq!(attr(&res, "paused"), "true");
+    assert_eq!(attr(&res, "reindex"), "false");
+
+    let err = store_item(&mut deps, json!({"observed_at": 1}), CID);
+    assert!(bad_request(err).contains("paused"));
+    let err = exec(
+        &mut deps,
+        "alice",
+        json!({"store_batch": {"items": [{"payload": {"observed_at": 1}, "cid": CID}], "mode": "best_effort"}}),
+    );
+    assert!(bad_request(err).contains("paused"));
+
+    update(&mut deps, "admin", json!({"paused": false})).unwrap();
+    store_item(&mut deps, json!({"observed_at": 1}), CID).unwrap();
+}
+
+#[test]
+fn limits_and_payload_bounds() {
+    let mut deps = setup();
+    for t in 1..=4 {
+        store(&mut deps, "alice", t, "a", "0", "0");
+    }
+    update(
+        &mut deps,
+        "admin",
+        json!({"default_limit": 2, "max_limit": 3}),
+    )
+    .unwrap();
+    assert_eq!(ids(&query_json(&deps, json!({"list": {}}))).len(), 2);
+    assert_eq!(
+        ids(&query_json(&deps, json!({"list": {"limit": 10}}))).len(),
+        3
+    );
+
+    update(&mut deps, "admin", json!({"max_payload_bytes": 32})).unwrap();
+    let err = store_item(
+        &mut deps,
+        json!({"observed_at": 1, "note": "x".repeat(64)}),
+        CID,
+    );
+    assert!(bad_request(err).contains("payload exceeds 32 bytes"));
+
+    update(
+        &mut deps,
+        "admin",
+        json!({"max_payload_bytes": 16384, "cid_min_len": 100}),
+    )
+    .unwrap();
+    assert!(store_item(&mut deps, json!({"observed_at": 1}), CID).is_err());
+}
+
+#[test]
+fn precision_change_hides_indexes_until_rebuilt() {
+    let mut deps = setup();
+    let a = store(&mut deps, "alice", 10, "a", "35.681", "139.767");
+    let b = store(&mut deps, "alice", 20, "b", "35.681", "139.767");
+
+    let res = update(&mut deps, "admin", json!({"geohash_precision": 4})).unwrap();
+    assert_eq!(attr(&res, "reindex"), "true");
+
+    // 張り直しが終わるまで索引・カウンタを読むクエリはエラー
+    for q in [
+        json!({"list": {}}),
+        json!({"count": {}}),
+        json!({"list_by_sender": {"sender": "alice"}}),
+        json!({"tag_counts": {}}),
+        json!({"stats_monthly": {"year": 2019}}),
+    ] {
+        let err = try_query(&deps, q.clone()).unwrap_err();
+        assert!(err.to_string().contains("migration in progress"), "{q}");
+    }
+    // レコード単体や設定は読める
+    assert_eq!(
+        query_json(&deps, json!({"get": {"id": a}}))["record"]["id"],
+        a
+    );
+    assert_eq!(
+        query_json(&deps, json!({"config": {}}))["geohash_precision"],
+        4
+    );
+    let status = query_json(&deps, json!({"migration_status": {}}));
+    assert_eq!(status["migration"]["steps"][0], "clear_indexes");
+    let err = store_item(&mut deps, json!({"observed_at": 1}), CID);
+    assert!(bad_request(err).contains("migration in progress"));
+
+    let mut calls = 0;
+    while !query_json(&deps, json!({"migration_status": {}}))["migration"].is_null() {
+        exec(
+            &mut deps,
+            "admin",
+            json!({"continue_migration": {"limit": 1}}),
+        )
+        .unwrap();
+        calls += 1;
+        assert!(calls < 50);
+    }
+    let resp = query_json(&deps, json!({"list": {"geohash_prefix": "xn76"}}));
+    assert_eq!(ids(&resp), vec![a, b]);
+    assert_eq!(query_json(&deps, json!({"count": {}}))["count"], 2);
+    assert_eq!(
+        query_json(&deps, json!({"get": {"id": b}}))["record"]["geohash_prefix"],
+        "xn76"
+    );
+}
+
+#[test]
+fn same_precision_is_rejected() {
+    let mut deps = setup();
+    store(&mut deps, "alice", 10, "a", "35.681", "139.767");
+    let err = update(&mut deps, "admin", json!({"geohash_precision": 6}));
+    assert!(bad_request(err).contains("already 6"));
+    assert!(query_json(&deps, json!({"migration_status": {}}))["migration"].is_null());
+    assert_eq!(ids(&query_json(&deps, json!({"list": {}}))), vec![1]);
+}
@@ -0,0 +1,156 @@
+use super::*;
+
+fn verify(deps: &mut TestDeps, who: &str, id: u64, taxon: &str, confidence: u8) -> Response {
+    exec(
+        deps,
+        who,
+        json!({"verify": {"id": id, "taxon_id": taxon, "confidence": confidence}}),
+    )
+    .unwrap()
+}
+
+fn record(deps: &TestDeps, id: u64) -> Value {
+    query_json(deps, json!({"get": {"id": id}}))["record"].clone()
+}
+
+fn by_grade(deps: &TestDeps, grade: &str) -> Vec<u64> {
+    ids(&query_json(deps, json!({"list": {"quality_grade": grade}})))
+}
+
+fn setup_verifiers() -> TestDeps {
+    let mut deps = setup();
+    exec(
+        &mut deps,
+        "admin",
+        json!({"grant_role": {"role": "verifier", "addr": "ver2"}}),
+    )
+    .unwrap();
+    exec(
+        &mut deps,
+        "admin",
+        json!({"grant_role": {"role": "verifier", "addr": "ver3"}}),
+    )
+    .unwrap();
+    deps
+}
+
+#[test]
+fn grade_transitions() {
+    let mut deps = setup_verifiers();
+    let full = store(&mut deps, "alice", 10, "Prunus mume", "35.681", "139.767");
+    let no_place = store_payload(
+        &mut deps,
+        "alice",
+        json!({"observed_at": 20, "species": "a"}),
+    );
+    let no_species = store_payload(
+        &mut deps,
+        "alice",
+        json!({"observed_at": 30, "place": {"lat": "0", "lon": "0"}}),
+    );
+    assert_eq!(record(&deps, full)["quality_grade"], "needs_id");
+    assert_eq!(record(&deps, no_place)["quality_grade"], "casual");
+    assert_eq!(record(&deps, no_species)["quality_grade"], "casual");
+    assert_eq!(by_grade(&deps, "casual"), vec![no_place, no_species]);
+
+    let res = verify(&mut deps, "ver", full, "t1", 90);
+    assert_eq!(attr(&res, "quality_grade"), "needs_id");
+    let res = verify(&mut deps, "ver2", full, "t1", 80);
+    assert_eq!(attr(&res, "quality_grade"), "research_grade");
+    assert_eq!(record(&deps, full)["consensus_taxon"], "t1");
+    assert_eq!(by_grade(&deps, "research_grade"), vec![full]);
+    assert_eq!(by_grade(&deps, "needs_id"), Vec::<u64>::new());
+
+    // 異論が入って閾値を割ると needs_id に戻る
+    let res = verify(&mut deps, "ver3", full, "t2", 100);
+    assert_eq!(attr(&res, "quality_grade"), "needs_id");
+    assert!(record(&deps, full)["consensus_taxon"].is_null());
+    assert_eq!(by_grade(&deps, "needs_id"), vec![full]);
+    assert_eq!(by_grade(&deps, "research_grade"), Vec::<u64>::new());
+
+    // 合意があっても位置が無ければ casual のまま
+    verify(&mut deps, "ver", no_place, "t1", 90);
+    verify(&mut deps, "ver2", no_place, "t1", 90);
+    assert_eq!(record(&deps, no_place)["consensus_taxon"], "t1");
+    assert_eq!(record(&deps, no_place)["quality_grade"], "casual");
+
+    let count = query_json(&deps, json!({"count": {"quality_grade": "casual"}}));
+    assert_eq!(count["count"], 2);
+}
+
+#[test]
+fn update_regrades_record() {
+    let mut deps = setup_verifiers();
+    let id = store(&mut deps, "alice", 10, "a", "35.681", "139.767");
+    verify(&mut deps, "ver", id, "t1", 90);
+    verify(&mut deps, "ver2", id, "t1", 90);
+    assert_eq!(by_grade(&deps, "research_grade"), vec![id]);
+    exec(
+        &mut deps,
+        "alice",
+        json!({"update_record": {"id": id, "payload": {"observed_at": 10, "species": "a"}, "cid": CID}}),
+    )
+    .unwrap();
+    assert_eq!(record(&deps, id)["quality_grade"], "casual");
+    assert_eq!(by_grade(&deps, "research_grade"), Vec::<u64>::new());
+    assert_eq!(by_grade(&deps, "casual"), vec![id]);
+}
+
+#[test]
+fn params_change_and_recompute() {
+    let mut deps = setup_verifiers();
+    let a = store(&mut deps, "alice", 10, "a", "35.681", "139.767");
+    let b = store(&mut deps, "alice", 20, "a", "35.681", "139.767");
+    for id in [a, b] {
+        verify(&mut deps, "ver", id, "t1", 90);
+    }
+    assert_eq!(by_grade(&deps, "research_grade"), Vec::<u64>::new());
+
+    let set = |deps: &mut TestDeps, sender: &str, t: u8, n: u32| {
+        exec(
+            deps,
+            sender,
+            json!({"set_consensus_params": {"params": {"threshold_pct": t, "min_verifiers": n}}}),
+        )
+    };
+    assert_eq!(
+        set(&mut deps, "ver", 60, 1),
+        Err(ContractError::Unauthorized)
+    );
+    assert_eq!(
+        bad_request(set(&mut deps, "admin", 50, 1)),
+        "threshold_pct must be within 51..=100"
+    );
+    assert_eq!(
+        bad_request(set(&mut deps, "admin", 60, 0)),
+        "min_verifiers must be at least 1"
+    );
+    set(&mut deps, "admin", 60, 1).unwrap();
+    assert_eq!(
+        query_json(&deps, json!({"consensus_params": {}})),
+        json!({"threshold_pct": 60, "min_verifiers": 1})
+    );
+    // 既存レコードは RecomputeGrades まで変わらない
+    assert_eq!(by_grade(&deps, "research_grade"), Vec::<u64>::new());
+
+    let res = exec(
+        &mut deps,
+        "admin",
+        json!({"recompute_grades": {"limit": 1}}),
+    )
+    .unwrap();
+    assert_eq!(attr(&res, "changed"), "1");
+    assert_eq!(attr(&res, "next_start_after"), a.to_string());
+    assert_eq!(by_grade(&deps, "research_grade"), vec![a]);
+    let res = exec(
+        &mut deps,
+        "admin",
+        json!({"recompute_grades": {"start_after": a, "limit": 1}}),
+    )
+    .unwrap();
+    assert_eq!(attr(&res, "changed"), "1");
+    assert_eq!(by_grade(&deps, "research_grade"), vec![a, b]);
+    let res = exec(&mut deps, "admin", json!({"recompute_grades": {}})).unwrap();
+    assert_eq!(attr(&res, "changed"), "0");
+    assert_eq!(attr(&res, "next_start_after"), "");
+}
@@ -0,0 +1,121 @@
+use super::*;
+
+/// カウンタから答える Count と、走査で数える Count（start 指定）が一致すること
+pub(super) fn assert_counts_consistent(deps: &TestDeps, species: &[&str], prefixes: &[&str]) {
+    let count = |filter: Value| -> u64 {
+        query_json(deps, json!({"count": filter}))["count"]
+            .as_u64()
+            .unwrap()
+    };
+    let mut filters = vec![json!({})];
+    filters.extend(species.iter().map(|s| json!({"species": s})));
+    filters.extend(prefixes.iter().map(|g| json!({"geohash_prefix": g})));
+    for filter in filters {
+        let mut scanned = filter.clone();
+        scanned["start"] = json!(0);
+        assert_eq!(count(filter.clone()), count(scanned), "{}", filter);
+    }
+}
+
+fn months(deps: &TestDeps, msg: Value) -> Vec<u64> {
+    let resp = query_json(deps, json!({"stats_monthly": msg}));
+    serde_json::from_value(resp["months"].clone()).unwrap()
+}
+
+// 2024-03-10, 2024-03-20, 2024-07-01 (UTC)
+const MAR_10: u64 = 1_710_028_800;
+const MAR_20: u64 = 1_710_892_800;
+const JUL_01: u64 = 1_719_792_000;
+
+#[test]
+fn counters_follow_store_and_hide() {
+    let mut deps = setup();
+    store(
+        &mut deps,
+        "alice",
+        MAR_10,
+        "Prunus mume",
+        "35.681",
+        "139.767",
+    ); // 1
+    store(
+        &mut deps,
+        "alice",
+        MAR_20,
+        "Prunus mume",
+        "34.702",
+        "135.495",
+    ); // 2
+    store(&mut deps, "bob", JUL_01, "Other", "35.681", "139.767"); // 3
+    assert_counts_consistent(&deps, &["prunus mume", "other"], &["x", "xn76", "xn0m"]);
+
+    let count = |deps: &TestDeps, filter: Value| {
+        query_json(deps, json!({"count": filter}))["count"].clone()
+    };
+    assert_eq!(count(&deps, json!({})), 3);
+    assert_eq!(count(&deps, json!({"species": "PRUNUS MUME"})), 2);
+    assert_eq!(count(&deps, json!({"geohash_prefix": "xn76"})), 2);
+    let mut want = vec![0; 12];
+    want[2] = 2;
+    want[6] = 1;
+    assert_eq!(months(&deps, json!({"year": 2024})), want);
+
+    exec(&mut deps, "admin", json!({"hide": {"id": 1}})).unwrap();
+    assert_counts_consistent(&deps, &["prunus mume", "other"], &["x", "xn76", "xn0m"]);
+    assert_eq!(count(&deps, json!({})), 2);
+    assert_eq!(count(&deps, json!({"species": "prunus mume"})), 1);
+    assert_eq!(count(&deps, json!({"geohash_prefix": "xn76"})), 1);
+    want[2] = 1;
+    assert_eq!(months(&deps, json!({"year": 2024})), want);
+    want[6] = 0;
+    assert_eq!(
+        months(&deps, json!({"year": 2024, "species": "prunus mume"})),
+        want
+    );
+
+    // 二重に隠すことはできない
+    assert_eq!(
+        bad_request(exec(&mut deps, "admin", json!({"hide": {"id": 1}}))),
+        "record is already hidden"
+    );
+    assert_eq!(count(&deps, json!({})), 2);
+}
+
+#[test]
+fn migrate_rebuilds_counters() {
+    let mut deps = setup();
+    store(
+        &mut deps,
+        "alice",
+        MAR_10,
+        "Prunus mume",
+        "35.681",
+        "139.767",
+    );
+    store(&mut deps, "alice", JUL_01, "Other", "34.702", "135.495");
+    exec(&mut deps, "admin", json!({"hide": {"id": 2}})).unwrap();
+    crate::migrate(
+        deps.as_mut(),
+        mock_env(),
+        crate::msg::MigrateMsg {
+            geohash_precision: Some(4),
+            limit: None,
+        },
+    )
+    .unwrap();
+    assert_counts_consistent(&deps, &["prunus mume", "other"], &["xn76", "xn0m"]);
+    assert_eq!(query_json(&deps, json!({"count": {}}))["count"], 1);
+}
+
+#[test]
+fn rejects_observed_at_beyond_counter_years() {
+    let mut deps = setup();
+    let payload = json!({"observed_at": u64::MAX, "species": "a"});
+    let err = exec(
+        &mut deps,
+        "alice",
+        json!({"store": {"payload": payload, "cid": CID}}),
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("out of range"), "{}", err);
+}
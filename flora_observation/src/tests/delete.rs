@@ -0,0 +1,149 @@
+use super::counters::assert_counts_consistent;
+use super::*;
+
+fn tombstones(deps: &TestDeps, msg: Value) -> Value {
+    query_json(deps, json!({"tombstones": msg}))
+}
+
+fn setup_records() -> TestDeps {
+    let mut deps = setup();
+    store(&mut deps, "alice", 10, "Prunus mume", "35.681", "139.767"); // 1
+    store(&mut deps, "bob", 20, "Prunus mume", "34.702", "135.495"); // 2
+    store(&mut deps, "alice", 30, "Other", "35.681", "139.767"); // 3
+    store(&mut deps, "bob", 40, "Other", "34.702", "135.495"); // 4
+    deps
+}
+
+#[test]
+fn withdraw_leaves_tombstone() {
+    let mut deps = setup_records();
+    assert_eq!(
+        exec(&mut deps, "bob", json!({"withdraw": {"id": 1}})),
+        Err(ContractError::Unauthorized)
+    );
+    let mut env = mock_env();
+    env.block.height = 777;
+    let msg: ExecuteMsg = serde_json::from_value(json!({"withdraw": {"id": 1}})).unwrap();
+    execute(deps.as_mut(), env, mock_info("alice", &[]), msg).unwrap();
+
+    assert!(query_json(&deps, json!({"get": {"id": 1}}))["record"].is_null());
+    let ts = tombstones(&deps, json!({}));
+    assert_eq!(
+        ts["tombstones"][0],
+        json!({
+            "id": 1, "sender": "alice", "cid": CID, "reason": "withdrawn", "by": "alice",
+            "block_time": mock_env().block.time.seconds(), "block_height": 777,
+        })
+    );
+    assert_eq!(
+        exec(&mut deps, "alice", json!({"withdraw": {"id": 1}})),
+        Err(ContractError::NotFound)
+    );
+
+    // 新しい id は再利用されない
+    assert_eq!(store(&mut deps, "alice", 50, "a", "0", "0"), 5);
+}
+
+#[test]
+fn deleted_records_leave_every_index_and_counter() {
+    let mut deps = setup_records();
+    exec(
+        &mut deps,
+        "alice",
+        json!({"update_record": {"id": 3, "payload": {"observed_at": 35, "species": "Other"}, "cid": CID}}),
+    )
+    .unwrap();
+    exec(&mut deps, "admin", json!({"hide": {"id": 4}})).unwrap();
+    exec(&mut deps, "alice", json!({"withdraw": {"id": 3}})).unwrap();
+    exec(&mut deps, "admin", json!({"purge": {"ids": [2, 4]}})).unwrap();
+
+    let list = |filter: Value| ids(&query_json(&deps, json!({"list": filter})));
+    assert_eq!(list(json!({})), vec![1]);
+    assert_eq!(list(json!({"species": "other"})), Vec::<u64>::new());
+    assert_eq!(list(json!({"geohash_prefix": "xn0m"})), Vec::<u64>::new());
+    let by_bob = query_json(&deps, json!({"list_by_sender": {"sender": "bob"}}));
+    assert_eq!(ids(&by_bob), Vec::<u64>::new());
+    assert_counts_consistent(&deps, &["prunus mume", "other"], &["xn76", "xn0m", "x"]);
+    assert_eq!(query_json(&deps, json!({"count": {}}))["count"], 1);
+    let history = query_json(&deps, json!({"record_history": {"id": 3}}));
+    assert_eq!(history["revisions"], json!([]));
+}
+
+#[test]
+fn purge_in_batches() {
+    let mut deps = setup_records();
+    assert_eq!(
+        exec(&mut deps, "alice", json!({"purge": {"ids": [1]}})),
+        Err(ContractError::Unauthorized)
+    );
+    assert_eq!(
+        bad_request(exec(&mut deps, "admin", json!({"purge": {"ids": []}}))),
+        "ids must contain 1..=100 entries"
+    );
+    let many: Vec<u64> = (1..=101).collect();
+    assert!(
+        bad_request(exec(&mut deps, "admin", json!({"purge": {"ids": many}}))).contains("1..=100")
+    );
+    assert_eq!(
+        exec(&mut deps, "admin", json!({"purge": {"ids": [99]}})),
+        Err(ContractError::NotFound)
+    );
+
+    exec(&mut deps, "alice", json!({"withdraw": {"id": 3}})).unwrap();
+    // 削除済み・重複指定は数えない
+    let res = exec(&mut deps, "admin", json!({"purge": {"ids": [1, 1, 3, 4]}})).unwrap();
+    assert_eq!(attr(&res, "count"), "2");
+
+    let ts = tombstones(&deps, json!({}));
+    let reasons: Vec<(u64, String, String)> = ts["tombstones"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| {
+            (
+                t["id"].as_u64().unwrap(),
+                t["reason"].as_str().unwrap().to_string(),
+                t["by"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        reasons,
+        vec![
+            (1, "purged".into(), "admin".into()),
+            (3, "withdrawn".into(), "alice".into()),
+            (4, "purged".into(), "admin".into()),
+        ]
+    );
+}
+
+#[test]
+fn tombstones_page_by_id() {
+    let mut deps = setup_records();
+    exec(&mut deps, "admin", json!({"purge": {"ids": [4, 2, 1]}})).unwrap();
+    let page = tombstones(&deps, json!({"limit": 2}));
+    let page_ids: Vec<u64> = page["tombstones"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["id"].as_u64().unwrap())
+        .collect();
+    assert_eq!(page_ids, vec![1, 2]);
+    assert_eq!(page["next_start_after"], 2);
+    let page = tombstones(&deps, json!({"limit": 2, "start_after": 2}));
+    assert_eq!(page["tombstones"][0]["id"], 4);
+    assert!(page["next_start_after"].is_null());
+}
+
+#[test]
+fn cursor_survives_withdraw_between_pages() {
+    let mut deps = setup_records();
+    let resp = query_json(&deps, json!({"list": {"limit": 2}}));
+    assert_eq!(ids(&resp), vec![1, 2]);
+    exec(&mut deps, "bob", json!({"withdraw": {"id": 2}})).unwrap();
+    let resp = query_json(
+        &deps,
+        json!({"list": {"limit": 2, "start_after": resp["next_start_after"]}}),
+    );
+    assert_eq!(ids(&resp), vec![3, 4]);
+}
@@ -0,0 +1,152 @@
+use cosmwasm_std::{
+    from_json, to_json_binary, ContractResult, SystemError, SystemResult, WasmQuery,
+};
+
+use super::*;
+
+const NFT: &str = "ticket";
+
+/// alice は t1, t2、bob は t3 を保有する cw721 をモックする
+fn setup_gate(mode: &str) -> TestDeps {
+    let mut deps = setup();
+    deps.querier.update_wasm(|q| {
+        let WasmQuery::Smart { contract_addr, msg } = q else {
+            panic!("unexpected query {q:?}");
+        };
+        if contract_addr != NFT {
+            return SystemResult::Err(SystemError::NoSuchContract {
+                addr: contract_addr.clone(),
+            });
+        }
+        let owners = [("t1", "alice"), ("t2", "alice"), ("t3", "bob")];
+        let msg: Value = from_json(msg).unwrap();
+        let res = if let Some(q) = msg.get("owner_of") {
+            let token = q["token_id"].as_str().unwrap();
+            match owners.iter().find(|(t, _)| *t == token) {
+                Some((_, owner)) => to_json_binary(&json!({"owner": owner, "approvals": []})),
+                None => return SystemResult::Ok(ContractResult::Err("token not found".into())),
+            }
+        } else {
+            let owner = msg["tokens"]["owner"].as_str().unwrap();
+            let tokens: Vec<&str> = owners
+                .iter()
+                .filter(|(_, o)| *o == owner)
+                .map(|(t, _)| *t)
+                .collect();
+            to_json_binary(&json!({ "tokens": tokens }))
+        };
+        SystemResult::Ok(ContractResult::Ok(res.unwrap()))
+    });
+    exec(
+        &mut deps,
+        "admin",
+        json!({"set_gate": {"gate": {"contract": NFT, "mode": mode}}}),
+    )
+    .unwrap();
+    deps
+}
+
+fn store_with(
+    deps: &mut TestDeps,
+    sender: &str,
+    token: Option<&str>,
+) -> Result<Response, ContractError> {
+    exec(
+        deps,
+        sender,
+        json!({"store": {
+            "payload": {"observed_at": 1, "species": "a"},
+            "cid": CID,
+            "gate_token_id": token,
+        }}),
+    )
+}
+
+fn gate_token(deps: &TestDeps, id: u64) -> Value {
+    query_json(deps, json!({"get": {"id": id}}))["record"]["gate_token_id"].clone()
+}
+
+#[test]
+fn any_mode_requires_a_token() {
+    let mut deps = setup_gate("any");
+    assert!(matches!(
+        store_with(&mut deps, "carol", None),
+        Err(ContractError::Unauthorized)
+    ));
+    let res = store_with(&mut deps, "alice", None).unwrap();
+    assert_eq!(attr(&res, "gate_token_id"), "t1");
+    assert_eq!(gate_token(&deps, 1), "t1");
+    // any では同じトークンで何度でも
+    store_with(&mut deps, "alice", None).unwrap();
+    assert_eq!(gate_token(&deps, 2), "t1");
+    // 他人のトークンの指定は拒否
+    assert!(matches!(
+        store_with(&mut deps, "alice", Some("t3")),
+        Err(ContractError::Unauthorized)
+    ));
+}
+
+#[test]
+fn specific_mode_checks_the_named_token() {
+    let mut deps = setup_gate("specific");
+    let err = store_with(&mut deps, "alice", None);
+    assert!(bad_request(err).contains("gate_token_id is required"));
+    assert!(matches!(
+        store_with(&mut deps, "alice", Some("t3")),
+        Err(ContractError::Unauthorized)
+    ));
+    assert!(store_with(&mut deps, "alice", Some("nope")).is_err());
+    store_with(&mut deps, "alice", Some("t2")).unwrap();
+    assert_eq!(gate_token(&deps, 1), "t2");
+}
+
+#[test]
+fn unused_mode_spends_each_token_once() {
+    let mut deps = setup_gate("unused");
+    store_with(&mut deps, "alice", None).unwrap();
+    let err = store_with(&mut deps, "alice", Some("t1"));
+    assert!(bad_request(err).contains("already been used"));
+    store_with(&mut deps, "alice", None).unwrap();
+    assert_eq!(gate_token(&deps, 2), "t2");
+    assert!(matches!(
+        store_with(&mut deps, "alice", None),
+        Err(ContractError::Unauthorized)
+    ));
+
+    // StoreBatch でも同じバッチ内で重複しない
+    let items = vec![
+        json!({"payload": {"observed_at": 1}, "cid": CID}),
+        json!({"payload": {"observed_at": 2}, "cid": CID}),
+    ];
+    let res = exec(
+        &mut deps,
+        "bob",
+        json!({"store_batch": {"items": items, "mode": "best_effort"}}),
+    )
+    .unwrap();
+    assert_eq!(attr(&res, "stored"), "1");
+    assert_eq!(attr(&res, "failed"), "1");
+    assert_eq!(gate_token(&deps, 3), "t3");
+}
+
+#[test]
+fn importer_and_cleared_gate_skip_the_check() {
+    let mut deps = setup_gate("any");
+    exec(
+        &mut deps,
+        "admin",
+        json!({"grant_role": {"role": "importer", "addr": "carol"}}),
+    )
+    .unwrap();
+    store_with(&mut deps, "carol", None).unwrap();
+    assert!(gate_token(&deps, 1).is_null());
+
+    assert!(matches!(
+        exec(&mut deps, "alice", json!({"set_gate": {}})),
+        Err(ContractError::Unauthorized)
+    ));
+    exec(&mut deps, "admin", json!({"set_gate": {}})).unwrap();
+    assert!(query_json(&deps, json!({"gate": {}})).is_null());
+    store_with(&mut deps, "dave", None).unwrap();
+    assert!(gate_token(&deps, 2).is_null());
+}
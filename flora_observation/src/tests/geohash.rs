@@ -0,0 +1,74 @@
+use super::*;
+use cosmwasm_std::{from_json, Binary};
+
+#[test]
+fn store_indexes_standard_geohash() {
+    let mut deps = setup();
+    let id = store(
+        &mut deps,
+        "alice",
+        1_741_564_800,
+        "Prunus mume",
+        "35.681",
+        "139.767",
+    );
+    let rec = query_json(&deps, json!({"get": {"id": id}}));
+    assert_eq!(rec["record"]["geohash_prefix"], "xn76ur");
+
+    let resp = query_json(&deps, json!({"list": {"geohash_prefix": "xn76ur"}}));
+    assert_eq!(ids(&resp), vec![id]);
+    let resp = query_json(&deps, json!({"count": {"geohash_prefix": "xn77"}}));
+    assert_eq!(resp["count"], 0);
+
+    // 座標が無い・範囲外の文字列は索引しない
+    let id = store_payload(
+        &mut deps,
+        "alice",
+        json!({"observed_at": 1, "place": {"lat": "abc", "lon": "1"}}),
+    );
+    let rec = query_json(&deps, json!({"get": {"id": id}}));
+    assert_eq!(rec["record"]["geohash_prefix"], "");
+}
+
+#[test]
+fn decimal_coordinates_must_be_strings() {
+    // コントラクトの JSON パーサは小数を読めない
+    let raw = br#"{"store":{"payload":{"observed_at":1,"place":{"lat":35.681,"lon":139.767}},"cid":"x"}}"#;
+    assert!(from_json::<ExecuteMsg>(&Binary::from(raw.as_slice())).is_err());
+    let raw = br#"{"store":{"payload":{"observed_at":1,"place":{"lat":"35.681","lon":"139.767"}},"cid":"x"}}"#;
+    assert!(from_json::<ExecuteMsg>(&Binary::from(raw.as_slice())).is_ok());
+}
+
+#[test]
+fn configurable_precision() {
+    let mut deps = setup_with(json!({"geohash_precision": 4}));
+    let id = store(&mut deps, "alice", 1, "x", "35.681", "139.767");
+    let rec = query_json(&deps, json!({"get": {"id": id}}));
+    assert_eq!(rec["record"]["geohash_prefix"], "xn76");
+
+    let mut bad = mock_dependencies();
+    let msg: InstantiateMsg = serde_json::from_value(json!({"geohash_precision": 13})).unwrap();
+    assert!(instantiate(bad.as_mut(), mock_env(), mock_info("admin", &[]), msg).is_err());
+
+    // migrate で桁数を変えると全件を作り直す
+    let msg = crate::msg::MigrateMsg {
+        geohash_precision: Some(7),
+        limit: None,
+    };
+    let res = crate::migrate(deps.as_mut(), mock_env(), msg).unwrap();
+    assert_eq!(attr(&res, "done"), "true");
+    let rec = query_json(&deps, json!({"get": {"id": id}}));
+    assert_eq!(rec["record"]["geohash_prefix"], "xn76urx");
+    let resp = query_json(&deps, json!({"list": {"geohash_prefix": "xn76urx"}}));
+    assert_eq!(ids(&resp), vec![id]);
+}
+
+#[test]
+fn decode_geohash_query() {
+    let deps = setup();
+    let resp = query_json(&deps, json!({"decode_geohash": {"geohash": "EZS42"}}));
+    assert_eq!(resp["geohash"], "ezs42");
+    assert_eq!(resp["min_lon"], "-5.625");
+    assert_eq!(resp["max_lat"], "42.626953125");
+    assert!(try_query(&deps, json!({"decode_geohash": {"geohash": "ezs4a"}})).is_err());
+}
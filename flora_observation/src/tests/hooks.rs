@@ -0,0 +1,139 @@
+use cosmwasm_std::{from_json, CosmosMsg, Reply, ReplyOn, SubMsgResult, WasmMsg};
+
+use super::*;
+
+fn add_hook(deps: &mut TestDeps, who: &str, addr: &str) -> Result<Response, ContractError> {
+    exec(deps, who, json!({"add_hook": {"addr": addr}}))
+}
+
+/// 通知の SubMsg を (宛先, 本文) で取り出す
+fn hook_calls(res: &Response) -> Vec<(String, Value)> {
+    res.messages
+        .iter()
+        .map(|m| {
+            assert_eq!(m.reply_on, ReplyOn::Error);
+            assert_eq!(m.gas_limit, Some(300_000));
+            let CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr, msg, ..
+            }) = &m.msg
+            else {
+                panic!("unexpected message {:?}", m.msg);
+            };
+            (contract_addr.clone(), from_json(msg).unwrap())
+        })
+        .collect()
+}
+
+#[test]
+fn hooks_are_admin_managed() {
+    let mut deps = setup();
+    assert!(matches!(
+        add_hook(&mut deps, "alice", "hook1"),
+        Err(ContractError::Unauthorized)
+    ));
+    add_hook(&mut deps, "admin", "hook2").unwrap();
+    add_hook(&mut deps, "admin", "hook1").unwrap();
+    let err = add_hook(&mut deps, "admin", "hook1");
+    assert!(bad_request(err).contains("already registered"));
+    assert_eq!(
+        query_json(&deps, json!({"hooks": {}}))["hooks"],
+        json!(["hook1", "hook2"])
+    );
+    assert_eq!(
+        query_json(&deps, json!({"hooks": {"start_after": "hook1"}}))["hooks"],
+        json!(["hook2"])
+    );
+
+    for i in 3..=10 {
+        add_hook(&mut deps, "admin", &format!("hook{i}")).unwrap();
+    }
+    let err = add_hook(&mut deps, "admin", "hook11");
+    assert!(bad_request(err).contains("at most 10"));
+
+    exec(
+        &mut deps,
+        "admin",
+        json!({"remove_hook": {"addr": "hook1"}}),
+    )
+    .unwrap();
+    assert!(matches!(
+        exec(
+            &mut deps,
+            "admin",
+            json!({"remove_hook": {"addr": "hook1"}})
+        ),
+        Err(ContractError::NotFound)
+    ));
+}
+
+#[test]
+fn store_verify_and_hide_notify_every_hook() {
+    let mut deps = setup();
+    let id = store(&mut deps, "alice", 10, "a", "0", "0");
+    add_hook(&mut deps, "admin", "hook1").unwrap();
+    add_hook(&mut deps, "admin", "hook2").unwrap();
+
+    let res = exec(
+        &mut deps,
+        "alice",
+        json!({"store": {"payload": {"observed_at": 20, "species": "b"}, "cid": CID}}),
+    )
+    .unwrap();
+    let calls = hook_calls(&res);
+    assert_eq!(calls.len(), 2);
+    assert_eq!(calls[0].0, "hook1");
+    assert_eq!(calls[1].0, "hook2");
+    let stored = &calls[0].1["observation_hook"]["stored"];
+    assert_eq!(stored["id"], id + 1);
+    assert_eq!(stored["species"], "b");
+    assert_eq!(stored["sender"], "alice");
+
+    let res = exec(
+        &mut deps,
+        "ver",
+        json!({"verify": {"id": id, "taxon_id": "t1", "confidence": 80}}),
+    )
+    .unwrap();
+    let calls = hook_calls(&res);
+    assert_eq!(calls.len(), 2);
+    let verified = &calls[0].1["observation_hook"]["verified"];
+    assert_eq!(verified["verifier"], "ver");
+    assert_eq!(verified["taxon_id"], "t1");
+
+    let res = exec(&mut deps, "admin", json!({"hide": {"id": id}})).unwrap();
+    let hidden = &hook_calls(&res)[1].1["observation_hook"]["hidden"];
+    assert_eq!(hidden["id"], id);
+    assert_eq!(hidden["by"], "admin");
+    assert_eq!(hidden["spam"], false);
+}
+
+#[test]
+fn failing_hook_does_not_revert_the_action() {
+    let mut deps = setup();
+    add_hook(&mut deps, "admin", "hook1").unwrap();
+    let res = exec(
+        &mut deps,
+        "alice",
+        json!({"store": {"payload": {"observed_at": 20}, "cid": CID}}),
+    )
+    .unwrap();
+    let reply_id = res.messages[0].id;
+
+    // 通知先の失敗は reply でエラーを返さずに受け止める
+    let res = crate::reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: reply_id,
+            result: SubMsgResult::Err("out of gas".into()),
+        },
+    )
+    .unwrap();
+    assert_eq!(attr(&res, "action"), "hook_failed");
+    assert_eq!(attr(&res, "error"), "out of gas");
+    assert!(res.messages.is_empty());
+
+    let rec = query_json(&deps, json!({"get": {"id": 1}}))["record"].clone();
+    assert_eq!(rec["id"], 1);
+    assert_eq!(query_json(&deps, json!({"count": {}}))["count"], 1);
+}
@@ -0,0 +1,183 @@
+use cosmwasm_std::Addr;
+use cw2::set_contract_version;
+
+use super::*;
+use crate::state::{
+    BY_GEOHASH, BY_TIME, LEGACY_ADMIN, LEGACY_RECORDS_RAW, LEGACY_VERIFIERS, NEXT_ID, RECORDS,
+};
+use crate::{CONTRACT_NAME, CONTRACT_VERSION};
+
+/// 初期版（cw2 なし・ADMIN / VERIFIERS・注記と検証をレコード内に持つ）の形で n 件保存する
+fn setup_baseline(n: u64) -> TestDeps {
+    let mut deps = mock_dependencies();
+    LEGACY_ADMIN
+        .save(&mut deps.storage, &Addr::unchecked("admin"))
+        .unwrap();
+    LEGACY_VERIFIERS
+        .save(&mut deps.storage, &Addr::unchecked("ver"), &true)
+        .unwrap();
+    for id in 1..=n {
+        let observed_at = 1_741_564_800 + id;
+        let rec = json!({
+            "id": id,
+            "sender": "alice",
+            "observed_at": observed_at,
+            "species": "prunus mume",
+            "geohash_prefix": "legacy",
+            "cid": CID,
+            "payload": {
+                "observed_at": observed_at,
+                "species": "Prunus mume",
+                "place": {"lat": "35.681", "lon": "139.767"},
+            },
+            "block_time": 1,
+            "block_height": 1,
+            "hidden": false,
+            "hidden_reason": null,
+            "annotations": [
+                {"at": 2, "by": "bob", "note": "old", "photo_cid": null, "tags": null},
+            ],
+            "verifications": [
+                {"at": 3, "verifier": "ver", "taxon_id": "t1", "confidence": 50},
+                {"at": 4, "verifier": "ver", "taxon_id": "t2", "confidence": 80},
+            ],
+        });
+        LEGACY_RECORDS_RAW
+            .save(&mut deps.storage, id, &rec)
+            .unwrap();
+        BY_TIME
+            .save(&mut deps.storage, (observed_at, id), &())
+            .unwrap();
+        BY_GEOHASH
+            .save(&mut deps.storage, ("legacy".to_string(), id), &())
+            .unwrap();
+    }
+    NEXT_ID.save(&mut deps.storage, &(n + 1)).unwrap();
+    deps
+}
+
+fn migrate(deps: &mut TestDeps, limit: u32) -> Result<Response, ContractError> {
+    crate::migrate(
+        deps.as_mut(),
+        mock_env(),
+        crate::msg::MigrateMsg {
+            geohash_precision: None,
+            limit: Some(limit),
+        },
+    )
+}
+
+fn status(deps: &TestDeps) -> Value {
+    query_json(deps, json!({"migration_status": {}}))
+}
+
+fn continue_migration(deps: &mut TestDeps) {
+    exec(deps, "admin", json!({"continue_migration": {"limit": 1}})).unwrap();
+}
+
+#[test]
+fn migrates_baseline_records_in_pages() {
+    let mut deps = setup_baseline(3);
+
+    let res = migrate(&mut deps, 1).unwrap();
+    assert_eq!(attr(&res, "from_version"), "0.0.0");
+    assert_eq!(attr(&res, "done"), "false");
+    let s = status(&deps);
+    assert_eq!(s["version"], "0.0.0");
+    assert_eq!(s["migration"]["steps"][0], "inline_lists");
+    // 役割は最初の 1 件で移る
+    let roles = query_json(&deps, json!({"roles": {"addr": "ver"}}));
+    assert!(roles.to_string().contains("verifier"));
+
+    // 移行中は ContinueMigration 以外を受け付けない
+    let err = exec(
+        &mut deps,
+        "alice",
+        json!({"store": {"payload": {"observed_at": 1}, "cid": CID}}),
+    );
+    assert!(bad_request(err).contains("migration"));
+    assert!(matches!(
+        exec(&mut deps, "alice", json!({"continue_migration": {}})),
+        Err(ContractError::Unauthorized)
+    ));
+
+    // InlineLists は 1 件ずつ進み、未処理のレコードは旧形式のまま
+    continue_migration(&mut deps);
+    let s = status(&deps);
+    assert_eq!(s["migration"]["steps"][0], "inline_lists");
+    assert_eq!(s["migration"]["cursor"], 1);
+    assert!(LEGACY_RECORDS_RAW.load(&deps.storage, 1).unwrap()["annotations"].is_null());
+    assert!(LEGACY_RECORDS_RAW.load(&deps.storage, 2).unwrap()["annotations"].is_array());
+    assert!(RECORDS.load(&deps.storage, 2).is_err());
+
+    // Reindex の途中まで進める
+    let mut calls = 0;
+    loop {
+        let s = status(&deps);
+        if s["migration"]["steps"][0] == "reindex" && s["migration"]["cursor"] == 1 {
+            break;
+        }
+        continue_migration(&mut deps);
+        calls += 1;
+        assert!(calls < 50);
+    }
+    assert_eq!(
+        RECORDS.load(&deps.storage, 1).unwrap().geohash_prefix,
+        "xn76ur"
+    );
+    assert_eq!(
+        RECORDS.load(&deps.storage, 2).unwrap().geohash_prefix,
+        "legacy"
+    );
+
+    while !status(&deps)["migration"].is_null() {
+        continue_migration(&mut deps);
+        calls += 1;
+        assert!(calls < 50);
+    }
+    let s = status(&deps);
+    assert_eq!(s["contract"], CONTRACT_NAME);
+    assert_eq!(s["version"], CONTRACT_VERSION);
+    assert!(LEGACY_ADMIN.may_load(&deps.storage).unwrap().is_none());
+
+    for id in 1..=3 {
+        let rec = query_json(&deps, json!({"get": {"id": id}}))["record"].clone();
+        assert_eq!(rec["geohash_prefix"], "xn76ur");
+        assert_eq!(rec["annotation_count"], 1);
+        assert_eq!(rec["verification_count"], 1);
+        // 同じ検証者の見解は最後のものだけが残る
+        let current = query_json(&deps, json!({"verifications": {"id": id}}));
+        let current = current["verifications"].as_array().unwrap();
+        assert_eq!(current.len(), 1);
+        assert_eq!(current[0]["entry"]["taxon_id"], "t2");
+        let anns = query_json(&deps, json!({"annotations": {"id": id}}));
+        assert_eq!(anns["annotations"].as_array().unwrap().len(), 1);
+    }
+    let resp = query_json(&deps, json!({"list": {"geohash_prefix": "xn76ur"}}));
+    assert_eq!(ids(&resp), vec![1, 2, 3]);
+    let resp = query_json(&deps, json!({"list": {"geohash_prefix": "legacy"}}));
+    assert!(ids(&resp).is_empty());
+
+    // 移行後は通常どおり受け付ける
+    assert_eq!(store(&mut deps, "alice", 10, "a", "0", "0"), 4);
+}
+
+#[test]
+fn refuses_downgrade() {
+    let mut deps = setup();
+
+    set_contract_version(&mut deps.storage, CONTRACT_NAME, "99.0.0").unwrap();
+    let err = migrate(&mut deps, 10);
+    assert!(bad_request(err).contains("cannot downgrade"));
+    assert!(status(&deps)["migration"].is_null());
+
+    set_contract_version(&mut deps.storage, "crates.io:other", "0.1.0").unwrap();
+    let err = migrate(&mut deps, 10);
+    assert!(bad_request(err).contains("cannot migrate from"));
+
+    // 同じバージョンへの再 migrate は受け付ける
+    set_contract_version(&mut deps.storage, CONTRACT_NAME, CONTRACT_VERSION).unwrap();
+    let res = migrate(&mut deps, 10).unwrap();
+    assert_eq!(attr(&res, "done"), "true");
+    assert!(status(&deps)["migration"].is_null());
+}
@@ -0,0 +1,122 @@
+//! execute / query を通したテスト。メッセージは JSON で組み立てる
+
+use cosmwasm_std::testing::{
+    mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+};
+use cosmwasm_std::{OwnedDeps, Response};
+use serde_json::{json, Value};
+
+use crate::error::ContractError;
+use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use crate::{execute, instantiate, query};
+
+mod annotations;
+mod batch;
+mod changes;
+mod config;
+mod consensus;
+mod counters;
+mod delete;
+mod gate;
+mod geohash;
+mod hooks;
+mod migrate;
+mod moderation;
+mod nft;
+mod phenology;
+mod phenophase;
+mod ranged;
+mod roles;
+mod schema;
+mod sender;
+mod spam;
+mod spatial;
+mod stats;
+mod tags;
+mod update;
+mod verification;
+
+type TestDeps = OwnedDeps<MockStorage, MockApi, MockQuerier>;
+
+const CID: &str = "bafybeihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku";
+
+/// admin は "admin"、検証者は "ver"
+fn setup() -> TestDeps {
+    setup_with(json!({"verifiers": ["ver"]}))
+}
+
+fn setup_with(msg: Value) -> TestDeps {
+    let mut deps = mock_dependencies();
+    let msg: InstantiateMsg = serde_json::from_value(msg).unwrap();
+    instantiate(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    deps
+}
+
+fn exec(deps: &mut TestDeps, sender: &str, msg: Value) -> Result<Response, ContractError> {
+    let msg: ExecuteMsg = serde_json::from_value(msg).unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), msg)
+}
+
+fn query_json(deps: &TestDeps, msg: Value) -> Value {
+    try_query(deps, msg).unwrap()
+}
+
+fn try_query(deps: &TestDeps, msg: Value) -> Result<Value, cosmwasm_std::StdError> {
+    let msg: QueryMsg = serde_json::from_value(msg).unwrap();
+    let bin = query(deps.as_ref(), mock_env(), msg)?;
+    Ok(serde_json::from_slice(bin.as_slice()).unwrap())
+}
+
+/// place 付きのレコードを保存して id を返す
+fn store(
+    deps: &mut TestDeps,
+    sender: &str,
+    observed_at: u64,
+    species: &str,
+    lat: &str,
+    lon: &str,
+) -> u64 {
+    let payload = json!({
+        "observed_at": observed_at,
+        "species": species,
+        "place": {"lat": lat, "lon": lon},
+    });
+    store_payload(deps, sender, payload)
+}
+
+fn store_payload(deps: &mut TestDeps, sender: &str, payload: Value) -> u64 {
+    let res = exec(
+        deps,
+        sender,
+        json!({"store": {"payload": payload, "cid": CID}}),
+    )
+    .unwrap();
+    attr(&res, "id").parse().unwrap()
+}
+
+fn attr(res: &Response, key: &str) -> String {
+    res.attributes
+        .iter()
+        .find(|a| a.key == key)
+        .unwrap_or_else(|| panic!("missing attribute {}", key))
+        .value
+        .clone()
+}
+
+/// BadRequest のメッセージ（それ以外のエラーなら panic）
+fn bad_request(res: Result<Response, ContractError>) -> String {
+    match res {
+        Err(ContractError::BadRequest { msg }) => msg,
+        other => panic!("expected BadRequest, got {:?}", other),
+    }
+}
+
+/// List 系の応答のレコード id
+fn ids(resp: &Value) -> Vec<u64> {
+    resp["records"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["id"].as_u64().unwrap())
+        .collect()
+}
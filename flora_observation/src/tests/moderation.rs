@@ -0,0 +1,245 @@
+use super::*;
+
+fn report(
+    deps: &mut TestDeps,
+    sender: &str,
+    id: u64,
+    category: &str,
+) -> Result<Response, ContractError> {
+    exec(
+        deps,
+        sender,
+        json!({"report": {"id": id, "category": category, "note": "see photo"}}),
+    )
+}
+
+fn open_reports(deps: &TestDeps) -> Vec<(u64, u64)> {
+    query_json(deps, json!({"open_reports": {}}))["reports"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| (r["id"].as_u64().unwrap(), r["record_id"].as_u64().unwrap()))
+        .collect()
+}
+
+fn history(deps: &TestDeps, id: u64) -> Vec<Value> {
+    query_json(deps, json!({"moderation_history": {"id": id}}))["entries"]
+        .as_array()
+        .unwrap()
+        .clone()
+}
+
+fn actions(deps: &TestDeps, id: u64) -> Vec<(String, String)> {
+    history(deps, id)
+        .iter()
+        .map(|e| {
+            (
+                e["action"].as_str().unwrap().to_string(),
+                e["actor"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect()
+}
+
+fn setup_moderation() -> TestDeps {
+    let mut deps = setup();
+    exec(
+        &mut deps,
+        "admin",
+        json!({"grant_role": {"role": "moderator", "addr": "mod"}}),
+    )
+    .unwrap();
+    store(&mut deps, "alice", 10, "a", "35.681", "139.767"); // 1
+    store(&mut deps, "alice", 20, "a", "35.681", "139.767"); // 2
+    deps
+}
+
+#[test]
+fn report_then_hide_actions_open_reports() {
+    let mut deps = setup_moderation();
+    report(&mut deps, "bob", 1, "spam").unwrap();
+    report(&mut deps, "carol", 1, "duplicate").unwrap();
+    report(&mut deps, "bob", 2, "other").unwrap();
+    assert_eq!(
+        bad_request(report(&mut deps, "bob", 1, "spam")),
+        "an open report from this sender already exists"
+    );
+    assert_eq!(
+        report(&mut deps, "bob", 9, "spam"),
+        Err(ContractError::NotFound)
+    );
+    assert_eq!(open_reports(&deps), vec![(1, 1), (2, 1), (3, 2)]);
+
+    assert_eq!(
+        exec(&mut deps, "bob", json!({"hide": {"id": 1}})),
+        Err(ContractError::Unauthorized)
+    );
+    let res = exec(
+        &mut deps,
+        "mod",
+        json!({"hide": {"id": 1, "reason": "spam"}}),
+    )
+    .unwrap();
+    assert_eq!(attr(&res, "resolved_reports"), "2");
+    assert_eq!(open_reports(&deps), vec![(3, 2)]);
+    let record = query_json(&deps, json!({"get": {"id": 1}}))["record"].clone();
+    assert_eq!(record["hidden"], true);
+    assert_eq!(record["hidden_reason"], "spam");
+
+    // 非表示のレコードには通報も再度の Hide もできない
+    assert_eq!(
+        bad_request(report(&mut deps, "dave", 1, "spam")),
+        "record is already hidden"
+    );
+    assert_eq!(
+        bad_request(exec(&mut deps, "mod", json!({"hide": {"id": 1}}))),
+        "record is already hidden"
+    );
+    assert_eq!(
+        actions(&deps, 1),
+        vec![
+            ("report".into(), "bob".into()),
+            ("report".into(), "carol".into()),
+            ("hide".into(), "mod".into()),
+        ]
+    );
+    // 対応済みになった通報者は同じレコードを再び通報できる
+    exec(&mut deps, "admin", json!({"unhide": {"id": 1}})).unwrap();
+    report(&mut deps, "bob", 1, "spam").unwrap();
+}
+
+#[test]
+fn unhide_restores_record() {
+    let mut deps = setup_moderation();
+    assert_eq!(
+        bad_request(exec(&mut deps, "mod", json!({"unhide": {"id": 1}}))),
+        "record is not hidden"
+    );
+    exec(&mut deps, "mod", json!({"hide": {"id": 1}})).unwrap();
+    assert_eq!(ids(&query_json(&deps, json!({"list": {}}))), vec![2]);
+    assert_eq!(
+        exec(&mut deps, "alice", json!({"unhide": {"id": 1}})),
+        Err(ContractError::Unauthorized)
+    );
+    exec(
+        &mut deps,
+        "mod",
+        json!({"unhide": {"id": 1, "reason": "appeal"}}),
+    )
+    .unwrap();
+    assert_eq!(ids(&query_json(&deps, json!({"list": {}}))), vec![1, 2]);
+    assert_eq!(query_json(&deps, json!({"count": {}}))["count"], 2);
+    let record = query_json(&deps, json!({"get": {"id": 1}}))["record"].clone();
+    assert_eq!(record["hidden"], false);
+    assert!(record["hidden_reason"].is_null());
+    let last = history(&deps, 1).pop().unwrap();
+    assert_eq!(last["action"], "unhide");
+    assert_eq!(last["reason"], "appeal");
+}
+
+#[test]
+fn dismiss_report() {
+    let mut deps = setup_moderation();
+    report(&mut deps, "bob", 1, "misidentified").unwrap();
+    assert_eq!(
+        exec(
+            &mut deps,
+            "bob",
+            json!({"dismiss_report": {"report_id": 1}})
+        ),
+        Err(ContractError::Unauthorized)
+    );
+    let res = exec(
+        &mut deps,
+        "mod",
+        json!({"dismiss_report": {"report_id": 1, "reason": "looks right"}}),
+    )
+    .unwrap();
+    assert_eq!(attr(&res, "id"), "1");
+    assert!(open_reports(&deps).is_empty());
+    assert_eq!(
+        bad_request(exec(
+            &mut deps,
+            "mod",
+            json!({"dismiss_report": {"report_id": 1}})
+        )),
+        "report is not open"
+    );
+    assert_eq!(
+        exec(
+            &mut deps,
+            "mod",
+            json!({"dismiss_report": {"report_id": 7}})
+        ),
+        Err(ContractError::NotFound)
+    );
+    // レコードは表示されたまま
+    assert_eq!(query_json(&deps, json!({"count": {}}))["count"], 2);
+
+    let entries = history(&deps, 1);
+    assert_eq!(entries[1]["action"], "dismiss_report");
+    assert_eq!(entries[1]["report_id"], 1);
+    assert_eq!(entries[1]["reason"], "looks right");
+    // 却下後は同じ通報者がまた通報できる
+    report(&mut deps, "bob", 1, "misidentified").unwrap();
+}
+
+#[test]
+fn deleting_closes_reports_and_logs_purge() {
+    let mut deps = setup_moderation();
+    report(&mut deps, "bob", 1, "spam").unwrap();
+    report(&mut deps, "bob", 2, "spam").unwrap();
+    exec(&mut deps, "alice", json!({"withdraw": {"id": 1}})).unwrap();
+    exec(&mut deps, "admin", json!({"purge": {"ids": [2]}})).unwrap();
+    assert!(open_reports(&deps).is_empty());
+    // 監査ログはレコードが消えても残る
+    assert_eq!(actions(&deps, 1), vec![("report".into(), "bob".into())]);
+    assert_eq!(
+        actions(&deps, 2),
+        vec![
+            ("report".into(), "bob".into()),
+            ("purge".into(), "admin".into()),
+        ]
+    );
+}
+
+#[test]
+fn audit_log_pages_by_seq() {
+    let mut deps = setup_moderation();
+    for who in ["bob", "carol", "dave"] {
+        report(&mut deps, who, 1, "other").unwrap();
+    }
+    report(&mut deps, "bob", 2, "other").unwrap();
+    exec(&mut deps, "mod", json!({"hide": {"id": 1}})).unwrap();
+
+    let page = query_json(&deps, json!({"moderation_history": {"id": 1, "limit": 2}}));
+    let seqs: Vec<u64> = page["entries"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["seq"].as_u64().unwrap())
+        .collect();
+    assert_eq!(seqs, vec![1, 2]);
+    let page = query_json(
+        &deps,
+        json!({"moderation_history": {"id": 1, "limit": 2, "start_after": page["next_start_after"]}}),
+    );
+    let seqs: Vec<u64> = page["entries"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["seq"].as_u64().unwrap())
+        .collect();
+    // seq 4 は record 2 の通報
+    assert_eq!(seqs, vec![3, 5]);
+
+    let page = query_json(&deps, json!({"open_reports": {"limit": 1}}));
+    assert_eq!(page["reports"][0]["id"], 4);
+    assert!(query_json(
+        &deps,
+        json!({"open_reports": {"limit": 1, "start_after": 4}})
+    )["reports"]
+        .as_array()
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,195 @@
+use cosmwasm_std::{from_json, Reply, ReplyOn, SubMsgResponse, SubMsgResult, WasmMsg};
+
+use super::*;
+
+/// s_mint を "minter" に設定
+fn setup_minter() -> TestDeps {
+    let mut deps = setup();
+    exec(
+        &mut deps,
+        "admin",
+        json!({"set_nft_minter": {"minter": {"contract": "minter"}}}),
+    )
+    .unwrap();
+    deps
+}
+
+/// Response の SubMsg から発行する token_id を取り出す
+fn minted_tokens(res: &Response) -> Vec<String> {
+    res.messages
+        .iter()
+        .map(|m| {
+            assert_eq!(m.reply_on, ReplyOn::Always);
+            let cosmwasm_std::CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr, msg, ..
+            }) = &m.msg
+            else {
+                panic!("unexpected message {:?}", m.msg);
+            };
+            assert_eq!(contract_addr, "minter");
+            let msg: Value = from_json(msg).unwrap();
+            msg["public_mint"]["token_id"].as_str().unwrap().to_string()
+        })
+        .collect()
+}
+
+fn reply_ok(deps: &mut TestDeps, id: u64) -> Response {
+    let reply = Reply {
+        id,
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: None,
+        }),
+    };
+    crate::reply(deps.as_mut(), mock_env(), reply).unwrap()
+}
+
+fn reply_err(deps: &mut TestDeps, id: u64) -> Response {
+    let reply = Reply {
+        id,
+        result: SubMsgResult::Err("mint fee required".into()),
+    };
+    crate::reply(deps.as_mut(), mock_env(), reply).unwrap()
+}
+
+fn by_token(deps: &TestDeps, token_id: &str) -> Value {
+    query_json(deps, json!({"record_by_token": {"token_id": token_id}}))["record"].clone()
+}
+
+#[test]
+fn store_mints_and_reply_links_token() {
+    let mut deps = setup_minter();
+    let res = exec(
+        &mut deps,
+        "alice",
+        json!({"store": {"payload": {"observed_at": 1}, "cid": CID}}),
+    )
+    .unwrap();
+    assert_eq!(minted_tokens(&res), vec!["obs-1"]);
+    let msg: Value = match &res.messages[0].msg {
+        cosmwasm_std::CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => from_json(msg).unwrap(),
+        _ => unreachable!(),
+    };
+    assert_eq!(msg["public_mint"]["owner"], "alice");
+    assert_eq!(msg["public_mint"]["token_uri"], format!("ipfs://{CID}"));
+    assert!(by_token(&deps, "obs-1").is_null());
+
+    let res = reply_ok(&mut deps, res.messages[0].id);
+    assert_eq!(attr(&res, "action"), "record_nft_minted");
+    assert_eq!(attr(&res, "token_id"), "obs-1");
+    let rec = by_token(&deps, "obs-1");
+    assert_eq!(rec["id"], 1);
+    assert_eq!(rec["nft_token_id"], "obs-1");
+
+    // 取り下げるとトークンからは引けなくなる
+    exec(&mut deps, "alice", json!({"withdraw": {"id": 1}})).unwrap();
+    assert!(by_token(&deps, "obs-1").is_null());
+}
+
+#[test]
+fn failed_mint_keeps_record_without_token() {
+    let mut deps = setup_minter();
+    let res = exec(
+        &mut deps,
+        "alice",
+        json!({"store": {"payload": {"observed_at": 1}, "cid": CID}}),
+    )
+    .unwrap();
+    let reply_id = res.messages[0].id;
+    let res = reply_err(&mut deps, reply_id);
+    assert_eq!(attr(&res, "action"), "record_nft_mint_failed");
+    assert_eq!(attr(&res, "id"), "1");
+
+    let rec = query_json(&deps, json!({"get": {"id": 1}}))["record"].clone();
+    assert!(rec["nft_token_id"].is_null());
+    assert!(by_token(&deps, "obs-1").is_null());
+    // 待ちは残らない
+    let err = crate::reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: reply_id,
+            result: SubMsgResult::Err("x".into()),
+        },
+    );
+    assert!(err.is_err());
+}
+
+#[test]
+fn batch_replies_follow_submission_order() {
+    let mut deps = setup_minter();
+    exec(
+        &mut deps,
+        "admin",
+        json!({"set_nft_minter": {"minter": {"contract": "minter", "token_id_prefix": "rec-"}}}),
+    )
+    .unwrap();
+    let items: Vec<Value> = (1..=3)
+        .map(|t| json!({"payload": {"observed_at": t}, "cid": CID}))
+        .collect();
+    let res = exec(
+        &mut deps,
+        "alice",
+        json!({"store_batch": {"items": items, "mode": "all_or_nothing"}}),
+    )
+    .unwrap();
+    assert_eq!(minted_tokens(&res), vec!["rec-1", "rec-2", "rec-3"]);
+
+    let id = res.messages[0].id;
+    reply_ok(&mut deps, id);
+    reply_err(&mut deps, id);
+    reply_ok(&mut deps, id);
+    assert_eq!(by_token(&deps, "rec-1")["id"], 1);
+    assert!(by_token(&deps, "rec-2").is_null());
+    assert_eq!(by_token(&deps, "rec-3")["id"], 3);
+    let rec = query_json(&deps, json!({"get": {"id": 2}}))["record"].clone();
+    assert!(rec["nft_token_id"].is_null());
+}
+
+#[test]
+fn minter_settings() {
+    let mut deps = setup();
+    let res = exec(
+        &mut deps,
+        "alice",
+        json!({"store": {"payload": {"observed_at": 1}, "cid": CID}}),
+    )
+    .unwrap();
+    assert!(res.messages.is_empty());
+
+    assert!(matches!(
+        exec(
+            &mut deps,
+            "alice",
+            json!({"set_nft_minter": {"minter": {"contract": "minter"}}}),
+        ),
+        Err(ContractError::Unauthorized)
+    ));
+    let err = exec(
+        &mut deps,
+        "admin",
+        json!({"set_nft_minter": {"minter": {"contract": "minter", "token_id_prefix": "a b"}}}),
+    );
+    assert!(bad_request(err).contains("whitespace"));
+    exec(
+        &mut deps,
+        "admin",
+        json!({"set_nft_minter": {"minter": {"contract": "minter"}}}),
+    )
+    .unwrap();
+    let cfg = query_json(&deps, json!({"nft_minter": {}}));
+    assert_eq!(cfg["token_id_prefix"], "obs-");
+
+    exec(&mut deps, "admin", json!({"set_nft_minter": {}})).unwrap();
+    assert!(query_json(&deps, json!({"nft_minter": {}})).is_null());
+
+    let err = crate::reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: 99,
+            result: SubMsgResult::Err("x".into()),
+        },
+    );
+    assert!(err.is_err());
+}
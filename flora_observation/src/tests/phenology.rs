@@ -0,0 +1,120 @@
+use super::*;
+use crate::calendar::{days_from_civil, SECONDS_PER_DAY};
+
+/// year の通算日 doy（1 始まり）の UTC hour 時
+fn at(year: i64, doy: i64, hour: i64) -> u64 {
+    ((days_from_civil(year, 1, 1) + doy - 1) * SECONDS_PER_DAY + hour * 3_600) as u64
+}
+
+fn summary(deps: &TestDeps, msg: Value) -> Vec<Value> {
+    let resp = query_json(deps, json!({"phenology_summary": msg}));
+    resp["years"].as_array().unwrap().clone()
+}
+
+fn flowering(deps: &mut TestDeps, observed_at: u64, species: &str) -> u64 {
+    store_payload(
+        deps,
+        "alice",
+        json!({"observed_at": observed_at, "species": species, "phenophase": "flowering"}),
+    )
+}
+
+#[test]
+fn first_median_last_per_year() {
+    let mut deps = setup();
+    // 2023: 通算日 40, 45, 90（中央 45）
+    for doy in [90, 40, 45] {
+        flowering(&mut deps, at(2023, doy, 12), "Prunus mume");
+    }
+    // 2024（閏年）: 60, 61, 70, 366（偶数件は小さい方の中央値 61）
+    for doy in [366, 60, 61, 70] {
+        flowering(&mut deps, at(2024, doy, 12), "Prunus mume");
+    }
+    // 別の種・別の季節は数えない
+    flowering(&mut deps, at(2023, 1, 12), "Other");
+    store_payload(
+        &mut deps,
+        "alice",
+        json!({"observed_at": at(2023, 2, 12), "species": "Prunus mume", "phenophase": "fruiting"}),
+    );
+
+    let years = summary(
+        &deps,
+        json!({"species": "prunus mume", "phenophase": "flowering", "years": [2024, 2023, 2023, 2022]}),
+    );
+    assert_eq!(
+        years,
+        vec![
+            json!({"year": 2022, "count": 0, "first_day": null, "median_day": null, "last_day": null}),
+            json!({"year": 2023, "count": 3, "first_day": 40, "median_day": 45, "last_day": 90}),
+            json!({"year": 2024, "count": 4, "first_day": 60, "median_day": 61, "last_day": 366}),
+        ]
+    );
+}
+
+#[test]
+fn days_follow_tz_offset() {
+    let mut deps = setup();
+    // 2023-12-31T20:00Z は JST で 2024 年の通算日 1
+    flowering(&mut deps, at(2023, 365, 20), "a");
+    let utc = summary(&deps, json!({"years": [2023, 2024]}));
+    assert_eq!(utc[0]["first_day"], 365);
+    assert_eq!(utc[1]["count"], 0);
+    let jst = summary(
+        &deps,
+        json!({"years": [2023, 2024], "tz_offset_minutes": 540}),
+    );
+    assert_eq!(jst[0]["count"], 0);
+    assert_eq!(jst[1]["first_day"], 1);
+}
+
+#[test]
+fn require_consensus_uses_latest_opinions() {
+    let mut deps = setup();
+    exec(
+        &mut deps,
+        "admin",
+        json!({"grant_role": {"role": "verifier", "addr": "ver2"}}),
+    )
+    .unwrap();
+    let agreed = flowering(&mut deps, at(2024, 100, 0), "a");
+    let disputed = flowering(&mut deps, at(2024, 120, 0), "a");
+    flowering(&mut deps, at(2024, 140, 0), "a"); // 未検証
+    let verify = |deps: &mut TestDeps, who: &str, id: u64, taxon: &str| {
+        exec(
+            deps,
+            who,
+            json!({"verify": {"id": id, "taxon_id": taxon, "confidence": 90}}),
+        )
+        .unwrap();
+    };
+    verify(&mut deps, "ver", agreed, "t1");
+    verify(&mut deps, "ver2", agreed, "t1");
+    verify(&mut deps, "ver", disputed, "t1");
+    verify(&mut deps, "ver2", disputed, "t2");
+
+    let all = summary(&deps, json!({"years": [2024]}));
+    assert_eq!(all[0]["count"], 3);
+    let agreed_only = summary(&deps, json!({"years": [2024], "require_consensus": true}));
+    assert_eq!(agreed_only[0]["count"], 1);
+    assert_eq!(agreed_only[0]["first_day"], 100);
+
+    // 見解を改めれば一致扱い（各検証者の最新のみ見る）
+    verify(&mut deps, "ver2", disputed, "t1");
+    let agreed_only = summary(&deps, json!({"years": [2024], "require_consensus": true}));
+    assert_eq!(agreed_only[0]["count"], 2);
+    assert_eq!(agreed_only[0]["last_day"], 120);
+}
+
+#[test]
+fn rejects_bad_year_lists() {
+    let deps = setup();
+    assert!(try_query(&deps, json!({"phenology_summary": {"years": []}})).is_err());
+    let many: Vec<u32> = (1970..2030).collect();
+    assert!(try_query(&deps, json!({"phenology_summary": {"years": many}})).is_err());
+    assert!(try_query(
+        &deps,
+        json!({"phenology_summary": {"years": [2024], "tz_offset_minutes": 900}})
+    )
+    .is_err());
+}
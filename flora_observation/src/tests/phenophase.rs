@@ -0,0 +1,88 @@
+use super::*;
+
+fn store_phase(deps: &mut TestDeps, observed_at: u64, species: &str, phenophase: Value) -> u64 {
+    store_payload(
+        deps,
+        "alice",
+        json!({
+            "observed_at": observed_at,
+            "species": species,
+            "phenophase": phenophase,
+            "place": {"lat": "35.681", "lon": "139.767"},
+        }),
+    )
+}
+
+fn setup_phases() -> TestDeps {
+    let mut deps = setup();
+    store_phase(&mut deps, 30, "Prunus mume", json!("Flowering")); // 1
+    store_phase(&mut deps, 10, "Prunus mume", json!({"name": " flowering "})); // 2
+    store_phase(&mut deps, 20, "Other", json!("flowering")); // 3
+    store_phase(&mut deps, 40, "Prunus mume", json!("fruiting")); // 4
+    store_phase(&mut deps, 50, "Prunus mume", json!("  ")); // 5: 空は無視
+    store(&mut deps, "alice", 60, "Prunus mume", "35.681", "139.767"); // 6
+    deps
+}
+
+#[test]
+fn phenophase_is_normalised_on_store() {
+    let deps = setup_phases();
+    let get =
+        |id: u64| query_json(&deps, json!({"get": {"id": id}}))["record"]["phenophase"].clone();
+    assert_eq!(get(1), json!("flowering"));
+    assert_eq!(get(2), json!("flowering"));
+    assert_eq!(get(4), json!("fruiting"));
+    assert!(get(5).is_null());
+    assert!(get(6).is_null());
+}
+
+#[test]
+fn filters_by_phenophase() {
+    let deps = setup_phases();
+    let list = |filter: Value| ids(&query_json(&deps, json!({"list": filter})));
+    let count = |filter: Value| query_json(&deps, json!({"count": filter}))["count"].clone();
+
+    // phenophase のみ: BY_PHENOPHASE_TIME を (observed_at, id) 順
+    assert_eq!(list(json!({"phenophase": "FLOWERING"})), vec![2, 3, 1]);
+    assert_eq!(
+        list(json!({"phenophase": "flowering", "start": 15})),
+        vec![3, 1]
+    );
+    assert_eq!(count(json!({"phenophase": "flowering"})), 3);
+    // species と組み合わせ
+    assert_eq!(
+        list(json!({"phenophase": "flowering", "species": "prunus mume"})),
+        vec![2, 1]
+    );
+    assert_eq!(
+        count(json!({"phenophase": "fruiting", "species": "other"})),
+        0
+    );
+    assert_eq!(
+        list(json!({"phenophase": "flowering", "geohash_prefix": "xn76"})),
+        vec![2, 3, 1]
+    );
+
+    // 1 件ずつたどる
+    let mut seen = vec![];
+    let mut after = Value::Null;
+    loop {
+        let resp = query_json(
+            &deps,
+            json!({"list": {"phenophase": "flowering", "limit": 1, "start_after": after}}),
+        );
+        seen.extend(ids(&resp));
+        after = resp["next_start_after"].clone();
+        if after.is_null() {
+            break;
+        }
+    }
+    assert_eq!(seen, vec![2, 3, 1]);
+
+    // 月次集計も phenophase で絞れる（1970-01 に全件）
+    let resp = query_json(
+        &deps,
+        json!({"stats_monthly": {"year": 1970, "phenophase": "flowering"}}),
+    );
+    assert_eq!(resp["months"][0], 3);
+}
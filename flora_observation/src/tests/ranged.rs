@@ -0,0 +1,169 @@
+use cosmwasm_std::Order;
+
+use super::*;
+use crate::state::{LEGACY_BY_SPECIES, RECORDS};
+
+fn list(deps: &TestDeps, filter: Value) -> Vec<u64> {
+    let mut msg = json!({"list": filter});
+    msg["list"]["limit"] = json!(100);
+    ids(&query_json(deps, msg))
+}
+
+/// 全ページを limit 件ずつたどった id 列
+fn list_paged(deps: &TestDeps, filter: Value, limit: u32) -> Vec<u64> {
+    let mut seen = vec![];
+    let mut after = Value::Null;
+    loop {
+        let mut msg = json!({"list": filter.clone()});
+        msg["list"]["limit"] = json!(limit);
+        msg["list"]["start_after"] = after;
+        let resp = query_json(deps, msg);
+        seen.extend(ids(&resp));
+        after = resp["next_start_after"].clone();
+        if after.is_null() {
+            return seen;
+        }
+    }
+}
+
+/// 東京 (xn76ur) と大阪 (xn0m7m) に観測時刻を前後させて保存
+fn setup_ranged() -> TestDeps {
+    let mut deps = setup();
+    store(&mut deps, "alice", 300, "Prunus mume", "35.681", "139.767"); // 1
+    store(&mut deps, "alice", 100, "Prunus mume", "34.702", "135.495"); // 2
+    store(&mut deps, "alice", 200, "Other", "35.681", "139.767"); // 3
+    store(&mut deps, "alice", 150, "prunus MUME", "35.681", "139.767"); // 4
+    store(&mut deps, "alice", 400, "Prunus mume", "34.702", "135.495"); // 5
+    deps
+}
+
+#[test]
+fn species_scan_is_time_ordered_and_ranged() {
+    let deps = setup_ranged();
+    // (observed_at, id) 順
+    assert_eq!(
+        list(&deps, json!({"species": "Prunus mume"})),
+        vec![2, 4, 1, 5]
+    );
+    assert_eq!(
+        list(
+            &deps,
+            json!({"species": "prunus mume", "start": 150, "end": 300})
+        ),
+        vec![4, 1]
+    );
+    assert_eq!(
+        list(
+            &deps,
+            json!({"species": "prunus mume", "start": 301, "end": 399})
+        ),
+        Vec::<u64>::new()
+    );
+    let count = query_json(
+        &deps,
+        json!({"count": {"species": "prunus mume", "start": 150, "end": 300}}),
+    );
+    assert_eq!(count["count"], 2);
+    // species + geohash_prefix は species の走査に geohash で絞り込む
+    assert_eq!(
+        list(
+            &deps,
+            json!({"species": "prunus mume", "geohash_prefix": "xn7"})
+        ),
+        vec![4, 1]
+    );
+}
+
+#[test]
+fn geohash_scan_is_ordered_by_cell_then_time() {
+    let deps = setup_ranged();
+    // セル (geohash) が先頭、その中で (observed_at, id)
+    assert_eq!(
+        list(&deps, json!({"geohash_prefix": "xn"})),
+        vec![2, 5, 4, 3, 1]
+    );
+    assert_eq!(
+        list(
+            &deps,
+            json!({"geohash_prefix": "xn", "start": 150, "end": 300})
+        ),
+        vec![4, 3, 1]
+    );
+    assert_eq!(
+        list(&deps, json!({"geohash_prefix": "xn76", "start": 250})),
+        vec![1]
+    );
+    // 期間指定なしは BY_TIME
+    assert_eq!(
+        list(&deps, json!({"start": 150, "end": 300})),
+        vec![4, 3, 1]
+    );
+}
+
+#[test]
+fn paging_follows_cursor() {
+    let deps = setup_ranged();
+    for filter in [
+        json!({}),
+        json!({"species": "prunus mume"}),
+        json!({"geohash_prefix": "xn"}),
+        json!({"geohash_prefix": "xn", "start": 150}),
+    ] {
+        let all = list(&deps, filter.clone());
+        assert_eq!(list_paged(&deps, filter.clone(), 1), all);
+        assert_eq!(list_paged(&deps, filter, 2), all);
+    }
+
+    let resp = query_json(
+        &deps,
+        json!({"list": {"species": "prunus mume", "limit": 1}}),
+    );
+    assert_eq!(
+        resp["next_start_after"],
+        json!({"id": 2, "observed_at": 100, "geohash": "xn0m7m"})
+    );
+}
+
+#[test]
+fn cursor_survives_removed_record() {
+    let mut deps = setup_ranged();
+    let resp = query_json(
+        &deps,
+        json!({"list": {"species": "prunus mume", "limit": 2}}),
+    );
+    assert_eq!(ids(&resp), vec![2, 4]);
+    let after = resp["next_start_after"].clone();
+
+    // カーソル位置のレコードが消えても続きから読める
+    RECORDS.remove(&mut deps.storage, 4);
+    let resp = query_json(
+        &deps,
+        json!({"list": {"species": "prunus mume", "limit": 2, "start_after": after}}),
+    );
+    assert_eq!(ids(&resp), vec![1, 5]);
+}
+
+#[test]
+fn migrate_drops_legacy_species_index() {
+    let mut deps = setup_ranged();
+    LEGACY_BY_SPECIES
+        .save(&mut deps.storage, ("prunus mume".to_string(), 1), &())
+        .unwrap();
+    crate::migrate(
+        deps.as_mut(),
+        mock_env(),
+        crate::msg::MigrateMsg {
+            geohash_precision: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let left = LEGACY_BY_SPECIES
+        .keys(&deps.storage, None, None, Order::Ascending)
+        .count();
+    assert_eq!(left, 0);
+    assert_eq!(
+        list(&deps, json!({"species": "prunus mume"})),
+        vec![2, 4, 1, 5]
+    );
+}
@@ -0,0 +1,247 @@
+use super::*;
+
+/// block time を now 秒にして実行
+fn exec_at(
+    deps: &mut TestDeps,
+    now: u64,
+    sender: &str,
+    msg: Value,
+) -> Result<Response, ContractError> {
+    let mut env = mock_env();
+    env.block.time = cosmwasm_std::Timestamp::from_seconds(now);
+    let msg: ExecuteMsg = serde_json::from_value(msg).unwrap();
+    execute(deps.as_mut(), env, mock_info(sender, &[]), msg)
+}
+
+fn query_at(deps: &TestDeps, now: u64, msg: Value) -> Value {
+    let mut env = mock_env();
+    env.block.time = cosmwasm_std::Timestamp::from_seconds(now);
+    let msg: QueryMsg = serde_json::from_value(msg).unwrap();
+    serde_json::from_slice(query(deps.as_ref(), env, msg).unwrap().as_slice()).unwrap()
+}
+
+fn grant(role: &str, addr: &str, expires_at: Option<u64>) -> Value {
+    json!({"grant_role": {"role": role, "addr": addr, "expires_at": expires_at}})
+}
+
+fn revoke(role: &str, addr: &str) -> Value {
+    json!({"revoke_role": {"role": role, "addr": addr}})
+}
+
+fn roles_of(deps: &TestDeps, now: u64, addr: &str) -> Vec<String> {
+    query_at(deps, now, json!({"roles": {"addr": addr}}))["roles"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["role"].as_str().unwrap().to_string())
+        .collect()
+}
+
+fn verify_msg(id: u64) -> Value {
+    json!({"verify": {"id": id, "taxon_id": "t1", "confidence": 80}})
+}
+
+const NOW: u64 = 1_000_000;
+
+#[test]
+fn grant_and_expire() {
+    let mut deps = setup();
+    let id = store(&mut deps, "alice", 1, "a", "0", "0");
+    assert_eq!(
+        exec_at(&mut deps, NOW, "alice", grant("verifier", "bob", None)),
+        Err(ContractError::Unauthorized)
+    );
+    assert_eq!(
+        bad_request(exec_at(
+            &mut deps,
+            NOW,
+            "admin",
+            grant("verifier", "bob", Some(NOW))
+        )),
+        "expires_at must be in the future"
+    );
+    exec_at(
+        &mut deps,
+        NOW,
+        "admin",
+        grant("verifier", "bob", Some(NOW + 100)),
+    )
+    .unwrap();
+    exec_at(&mut deps, NOW, "admin", grant("moderator", "bob", None)).unwrap();
+    assert_eq!(roles_of(&deps, NOW, "bob"), vec!["moderator", "verifier"]);
+
+    exec_at(&mut deps, NOW + 99, "bob", verify_msg(id)).unwrap();
+    assert_eq!(
+        exec_at(&mut deps, NOW + 100, "bob", verify_msg(id)),
+        Err(ContractError::Unauthorized)
+    );
+    assert_eq!(roles_of(&deps, NOW + 100, "bob"), vec!["moderator"]);
+
+    // 期限切れのメンバーは一覧に出ない
+    let members = |now: u64| -> Vec<String> {
+        query_at(
+            &deps,
+            now,
+            json!({"list_role_members": {"role": "verifier"}}),
+        )["members"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["addr"].as_str().unwrap().to_string())
+            .collect()
+    };
+    assert_eq!(members(NOW), vec!["bob", "ver"]);
+    assert_eq!(members(NOW + 100), vec!["ver"]);
+}
+
+#[test]
+fn revoke_removes_role() {
+    let mut deps = setup();
+    let id = store(&mut deps, "alice", 1, "a", "0", "0");
+    assert_eq!(
+        exec_at(&mut deps, NOW, "admin", revoke("verifier", "bob")),
+        Err(ContractError::NotFound)
+    );
+    assert_eq!(
+        exec_at(&mut deps, NOW, "ver", revoke("verifier", "ver")),
+        Err(ContractError::Unauthorized)
+    );
+    exec_at(&mut deps, NOW, "admin", revoke("verifier", "ver")).unwrap();
+    assert_eq!(
+        exec_at(&mut deps, NOW, "ver", verify_msg(id)),
+        Err(ContractError::Unauthorized)
+    );
+    assert!(roles_of(&deps, NOW, "ver").is_empty());
+}
+
+#[test]
+fn last_permanent_admin_is_kept() {
+    let mut deps = setup();
+    assert_eq!(
+        bad_request(exec_at(&mut deps, NOW, "admin", revoke("admin", "admin"))),
+        "cannot revoke the last permanent admin"
+    );
+    assert_eq!(
+        bad_request(exec_at(
+            &mut deps,
+            NOW,
+            "admin",
+            grant("admin", "admin", Some(NOW + 10))
+        )),
+        "cannot make the last permanent admin expire"
+    );
+
+    // 期限付きの admin は数えない
+    exec_at(
+        &mut deps,
+        NOW,
+        "admin",
+        grant("admin", "temp", Some(NOW + 10)),
+    )
+    .unwrap();
+    assert!(exec_at(&mut deps, NOW, "temp", revoke("admin", "admin")).is_err());
+    exec_at(&mut deps, NOW, "temp", grant("moderator", "mod", None)).unwrap();
+    assert_eq!(
+        exec_at(
+            &mut deps,
+            NOW + 10,
+            "temp",
+            grant("moderator", "mod2", None)
+        ),
+        Err(ContractError::Unauthorized)
+    );
+
+    // 無期限の admin がもう 1 人いれば外せる
+    exec_at(&mut deps, NOW, "admin", grant("admin", "admin2", None)).unwrap();
+    exec_at(&mut deps, NOW, "admin2", revoke("admin", "admin")).unwrap();
+    assert!(roles_of(&deps, NOW, "admin").is_empty());
+    assert_eq!(
+        exec_at(&mut deps, NOW, "admin", grant("moderator", "x12", None)),
+        Err(ContractError::Unauthorized)
+    );
+}
+
+#[test]
+fn admin_handover() {
+    let mut deps = setup();
+    assert_eq!(
+        exec_at(&mut deps, NOW, "newadmin", json!({"accept_admin": {}})),
+        Err(ContractError::NoPendingAdmin)
+    );
+    assert_eq!(
+        exec_at(
+            &mut deps,
+            NOW,
+            "bob",
+            json!({"propose_admin": {"new_admin": "bob"}})
+        ),
+        Err(ContractError::Unauthorized)
+    );
+    exec_at(
+        &mut deps,
+        NOW,
+        "admin",
+        json!({"propose_admin": {"new_admin": "newadmin"}}),
+    )
+    .unwrap();
+    assert_eq!(
+        query_at(&deps, NOW, json!({"pending_admin": {}}))["pending"],
+        json!({"proposer": "admin", "candidate": "newadmin"})
+    );
+    assert_eq!(
+        exec_at(&mut deps, NOW, "bob", json!({"accept_admin": {}})),
+        Err(ContractError::Unauthorized)
+    );
+    let res = exec_at(&mut deps, NOW, "newadmin", json!({"accept_admin": {}})).unwrap();
+    assert_eq!(attr(&res, "previous"), "admin");
+    assert_eq!(roles_of(&deps, NOW, "newadmin"), vec!["admin"]);
+    assert!(roles_of(&deps, NOW, "admin").is_empty());
+    assert!(query_at(&deps, NOW, json!({"pending_admin": {}}))["pending"].is_null());
+}
+
+#[test]
+fn handover_from_removed_admin_is_void() {
+    let mut deps = setup();
+    exec_at(&mut deps, NOW, "admin", grant("admin", "admin2", None)).unwrap();
+    exec_at(
+        &mut deps,
+        NOW,
+        "admin",
+        grant("admin", "temp", Some(NOW + 10)),
+    )
+    .unwrap();
+
+    // 外された admin の引き継ぎは取り消される
+    exec_at(
+        &mut deps,
+        NOW,
+        "admin2",
+        json!({"propose_admin": {"new_admin": "cand"}}),
+    )
+    .unwrap();
+    exec_at(&mut deps, NOW, "admin", revoke("admin", "admin2")).unwrap();
+    assert!(query_at(&deps, NOW, json!({"pending_admin": {}}))["pending"].is_null());
+    assert_eq!(
+        exec_at(&mut deps, NOW, "cand", json!({"accept_admin": {}})),
+        Err(ContractError::NoPendingAdmin)
+    );
+
+    // 期限切れの admin の引き継ぎは受けられない
+    exec_at(
+        &mut deps,
+        NOW,
+        "temp",
+        json!({"propose_admin": {"new_admin": "cand"}}),
+    )
+    .unwrap();
+    assert_eq!(
+        bad_request(exec_at(
+            &mut deps,
+            NOW + 10,
+            "cand",
+            json!({"accept_admin": {}})
+        )),
+        "proposer is no longer an admin"
+    );
+    assert!(roles_of(&deps, NOW + 10, "cand").is_empty());
+}
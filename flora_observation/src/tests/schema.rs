@@ -0,0 +1,137 @@
+use super::*;
+
+fn set_schema_as(
+    deps: &mut TestDeps,
+    sender: &str,
+    schema: Value,
+) -> Result<Response, ContractError> {
+    exec(
+        deps,
+        sender,
+        json!({"set_payload_schema": {"schema": schema}}),
+    )
+}
+
+fn set_schema(deps: &mut TestDeps, schema: Value) -> Result<Response, ContractError> {
+    set_schema_as(deps, "admin", schema)
+}
+
+fn try_store(deps: &mut TestDeps, payload: Value) -> Result<Response, ContractError> {
+    exec(
+        deps,
+        "alice",
+        json!({"store": {"payload": payload, "cid": CID}}),
+    )
+}
+
+fn rule(path: &str, required: bool) -> Value {
+    json!({"path": path, "required": required})
+}
+
+/// 観察フォーム相当のスキーマ
+fn form_schema() -> Value {
+    let mut lat = rule("place.lat", true);
+    lat["kind"] = json!("decimal");
+    lat["min"] = json!("-90");
+    lat["max"] = json!("90");
+    let mut species = rule("species", true);
+    species["kind"] = json!("string");
+    let mut stage = rule("stage", false);
+    stage["one_of"] = json!(["bud", "flower", "fruit"]);
+    let mut count = rule("count", false);
+    count["kind"] = json!("integer");
+    count["min"] = json!("1");
+    json!({"fields": [lat, species, stage, count]})
+}
+
+#[test]
+fn store_is_checked_against_schema() {
+    let mut deps = setup();
+    set_schema(&mut deps, form_schema()).unwrap();
+    let ok = json!({
+        "observed_at": 1, "species": "a", "place": {"lat": "35.681", "lon": "139.767"},
+        "stage": "flower", "count": 3,
+    });
+    try_store(&mut deps, ok.clone()).unwrap();
+
+    let cases = [
+        ("/place/lat", Value::Null, "payload.place.lat is required"),
+        (
+            "/place/lat",
+            json!("north"),
+            "payload.place.lat must be decimal",
+        ),
+        (
+            "/place/lat",
+            json!("90.5"),
+            "payload.place.lat must be <= 90",
+        ),
+        ("/species", json!(7), "payload.species must be string"),
+        (
+            "/stage",
+            json!("seed"),
+            "payload.stage must be one of: bud, flower, fruit",
+        ),
+        ("/count", json!(1.5), "payload.count must be integer"),
+        ("/count", json!(0), "payload.count must be >= 1"),
+    ];
+    for (pointer, value, want) in cases {
+        let mut payload = ok.clone();
+        *payload.pointer_mut(pointer).unwrap() = value;
+        assert_eq!(
+            bad_request(try_store(&mut deps, payload)),
+            want,
+            "{}",
+            pointer
+        );
+    }
+
+    // 任意項目は省略できる
+    let mut payload = ok.clone();
+    payload.as_object_mut().unwrap().remove("stage");
+    payload.as_object_mut().unwrap().remove("count");
+    try_store(&mut deps, payload).unwrap();
+
+    // 解除すると observed_at だけが必須
+    set_schema(&mut deps, Value::Null).unwrap();
+    try_store(&mut deps, json!({"observed_at": 1})).unwrap();
+    let resp = query_json(&deps, json!({"payload_schema": {}}));
+    assert!(resp["schema"].is_null());
+}
+
+#[test]
+fn schema_itself_is_validated() {
+    let mut deps = setup();
+    let err = |deps: &mut TestDeps, fields: Value| {
+        bad_request(set_schema(deps, json!({"fields": fields})))
+    };
+    assert_eq!(
+        err(&mut deps, json!([rule("place..lat", true)])),
+        "fields[0].path is invalid"
+    );
+    assert_eq!(
+        err(&mut deps, json!([rule("a", true), rule("a", false)])),
+        "duplicate rule for a"
+    );
+    let mut r = rule("a", true);
+    r["min"] = json!("2");
+    r["max"] = json!("1");
+    assert_eq!(err(&mut deps, json!([r])), "a: min must be <= max");
+    let mut r = rule("a", true);
+    r["max"] = json!("x");
+    assert_eq!(
+        err(&mut deps, json!([r])),
+        "a: max must be a decimal string"
+    );
+    let mut r = rule("a", true);
+    r["one_of"] = json!([]);
+    assert_eq!(err(&mut deps, json!([r])), "a: one_of must not be empty");
+
+    assert_eq!(
+        set_schema_as(&mut deps, "alice", form_schema()),
+        Err(ContractError::Unauthorized)
+    );
+    set_schema(&mut deps, form_schema()).unwrap();
+    let resp = query_json(&deps, json!({"payload_schema": {}}));
+    assert_eq!(resp["schema"]["fields"].as_array().unwrap().len(), 4);
+}
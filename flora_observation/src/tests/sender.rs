@@ -0,0 +1,75 @@
+use super::*;
+
+fn by_sender(deps: &TestDeps, msg: Value) -> Value {
+    query_json(deps, json!({"list_by_sender": msg}))
+}
+
+/// alice と bob の記録を観測時刻を前後させて保存
+fn setup_senders() -> TestDeps {
+    let mut deps = setup();
+    store(&mut deps, "alice", 30, "a", "35.681", "139.767"); // 1
+    store(&mut deps, "bob", 20, "a", "35.681", "139.767"); // 2
+    store(&mut deps, "alice", 10, "a", "35.681", "139.767"); // 3
+    store(&mut deps, "alice", 20, "b", "35.681", "139.767"); // 4
+    store(&mut deps, "alice", 40, "b", "35.681", "139.767"); // 5
+    deps
+}
+
+#[test]
+fn lists_by_sender_in_time_order() {
+    let deps = setup_senders();
+    assert_eq!(
+        ids(&by_sender(&deps, json!({"sender": "alice"}))),
+        vec![3, 4, 1, 5]
+    );
+    assert_eq!(ids(&by_sender(&deps, json!({"sender": "bob"}))), vec![2]);
+    assert_eq!(
+        ids(&by_sender(
+            &deps,
+            json!({"sender": "alice", "order": "desc"})
+        )),
+        vec![5, 1, 4, 3]
+    );
+    assert_eq!(
+        ids(&by_sender(
+            &deps,
+            json!({"sender": "alice", "start": 20, "end": 30})
+        )),
+        vec![4, 1]
+    );
+    assert_eq!(
+        ids(&by_sender(&deps, json!({"sender": "carol"}))),
+        Vec::<u64>::new()
+    );
+}
+
+#[test]
+fn pages_by_sender_in_both_orders() {
+    let deps = setup_senders();
+    for (order, want) in [("asc", vec![3, 4, 1, 5]), ("desc", vec![5, 1, 4, 3])] {
+        let mut seen = vec![];
+        let mut after = Value::Null;
+        loop {
+            let resp = by_sender(
+                &deps,
+                json!({"sender": "alice", "order": order, "limit": 1, "start_after": after}),
+            );
+            seen.extend(ids(&resp));
+            after = resp["next_start_after"].clone();
+            if after.is_null() {
+                break;
+            }
+        }
+        assert_eq!(seen, want);
+    }
+
+    let resp = by_sender(&deps, json!({"sender": "alice", "limit": 4}));
+    assert_eq!(ids(&resp), vec![3, 4, 1, 5]);
+    assert_eq!(resp["next_start_after"]["id"], 5);
+    let resp = by_sender(
+        &deps,
+        json!({"sender": "alice", "limit": 4, "start_after": resp["next_start_after"]}),
+    );
+    assert_eq!(ids(&resp), Vec::<u64>::new());
+    assert!(resp["next_start_after"].is_null());
+}
@@ -0,0 +1,450 @@
+use cosmwasm_std::{coin, BankMsg, Coin, CosmosMsg, Timestamp};
+
+use super::*;
+
+const DENOM: &str = "uflora";
+const PERIOD: u64 = 1_000;
+
+/// block time を now 秒にし、funds を付けて実行
+fn exec_at(
+    deps: &mut TestDeps,
+    now: u64,
+    sender: &str,
+    funds: &[Coin],
+    msg: Value,
+) -> Result<Response, ContractError> {
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(now);
+    let msg: ExecuteMsg = serde_json::from_value(msg).unwrap();
+    execute(deps.as_mut(), env, mock_info(sender, funds), msg)
+}
+
+fn query_at(deps: &TestDeps, now: u64, msg: Value) -> Value {
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(now);
+    let msg: QueryMsg = serde_json::from_value(msg).unwrap();
+    serde_json::from_slice(query(deps.as_ref(), env, msg).unwrap().as_slice()).unwrap()
+}
+
+fn store_msg(observed_at: u64) -> Value {
+    json!({"store": {
+        "payload": {"observed_at": observed_at, "species": "a", "place": {"lat": "0", "lon": "0"}},
+        "cid": CID,
+    }})
+}
+
+fn batch_msg(items: &[&str], mode: &str) -> Value {
+    let items: Vec<Value> = items
+        .iter()
+        .enumerate()
+        .map(|(i, cid)| {
+            json!({
+                "payload": {"observed_at": i, "species": "a"},
+                "cid": cid,
+            })
+        })
+        .collect();
+    json!({"store_batch": {"items": items, "mode": mode}})
+}
+
+/// 1 件 10uflora、期間 1000 秒のデポジットだけを設定
+fn setup_deposit() -> TestDeps {
+    let mut deps = setup();
+    exec(
+        &mut deps,
+        "admin",
+        json!({"set_spam_policy": {"deposit": {
+            "amount": {"denom": DENOM, "amount": "10"},
+            "challenge_period_secs": PERIOD,
+            "treasury": "treasury",
+        }}}),
+    )
+    .unwrap();
+    deps
+}
+
+fn deposits(deps: &TestDeps, owner: &str) -> Vec<Value> {
+    query_json(deps, json!({"deposits": {"owner": owner}}))["deposits"]
+        .as_array()
+        .unwrap()
+        .clone()
+}
+
+/// Response の BankMsg::Send を (宛先, 額) で並べる
+fn sends(res: &Response) -> Vec<(String, Vec<Coin>)> {
+    res.messages
+        .iter()
+        .map(|m| match &m.msg {
+            CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+                (to_address.clone(), amount.clone())
+            }
+            other => panic!("unexpected message {other:?}"),
+        })
+        .collect()
+}
+
+fn claim(deps: &mut TestDeps, now: u64, sender: &str, owner: Option<&str>) -> Response {
+    exec_at(
+        deps,
+        now,
+        sender,
+        &[],
+        json!({"claim_deposits": {"owner": owner}}),
+    )
+    .unwrap()
+}
+
+#[test]
+fn quota_window_slides() {
+    let mut deps = setup();
+    exec(
+        &mut deps,
+        "admin",
+        json!({"set_spam_policy": {"quota": {"max_stores": 2, "window_secs": 100}}}),
+    )
+    .unwrap();
+
+    exec_at(&mut deps, 1_000, "alice", &[], store_msg(1)).unwrap();
+    exec_at(&mut deps, 1_050, "alice", &[], store_msg(2)).unwrap();
+    let err = exec_at(&mut deps, 1_060, "alice", &[], store_msg(3));
+    assert!(bad_request(err).contains("store quota exceeded"));
+    let usage = query_at(&deps, 1_060, json!({"quota_usage": {"addr": "alice"}}));
+    assert_eq!(usage["used"], 2);
+    assert_eq!(usage["max_stores"], 2);
+    // 他の投稿者は別枠
+    exec_at(&mut deps, 1_060, "bob", &[], store_msg(4)).unwrap();
+
+    // ちょうど window_secs 経った投稿は数えない
+    let usage = query_at(&deps, 1_100, json!({"quota_usage": {"addr": "alice"}}));
+    assert_eq!(usage["used"], 1);
+    exec_at(&mut deps, 1_100, "alice", &[], store_msg(5)).unwrap();
+    let err = exec_at(&mut deps, 1_120, "alice", &[], store_msg(6));
+    assert!(bad_request(err).contains("store quota exceeded"));
+
+    // importer はクォータの対象外
+    exec(
+        &mut deps,
+        "admin",
+        json!({"grant_role": {"role": "importer", "addr": "alice"}}),
+    )
+    .unwrap();
+    exec_at(&mut deps, 1_120, "alice", &[], store_msg(7)).unwrap();
+
+    let err = exec(
+        &mut deps,
+        "admin",
+        json!({"set_spam_policy": {"quota": {"max_stores": 0, "window_secs": 100}}}),
+    );
+    assert!(bad_request(err).contains("positive"));
+}
+
+#[test]
+fn batch_respects_quota() {
+    let mut deps = setup();
+    exec(
+        &mut deps,
+        "admin",
+        json!({"set_spam_policy": {"quota": {"max_stores": 2, "window_secs": 100}}}),
+    )
+    .unwrap();
+    let err = exec_at(
+        &mut deps,
+        1_000,
+        "alice",
+        &[],
+        batch_msg(&[CID, CID, CID], "all_or_nothing"),
+    );
+    assert!(bad_request(err).contains("store quota exceeded"));
+
+    let res = exec_at(
+        &mut deps,
+        1_000,
+        "alice",
+        &[],
+        batch_msg(&[CID, CID, CID], "best_effort"),
+    )
+    .unwrap();
+    assert_eq!(attr(&res, "stored"), "2");
+    assert!(attr(&res, "error.2").contains("store quota exceeded"));
+}
+
+#[test]
+fn deposit_must_match_exactly() {
+    let mut deps = setup_deposit();
+    for funds in [vec![], vec![coin(9, DENOM)], vec![coin(10, "other")]] {
+        let err = exec_at(&mut deps, 1_000, "alice", &funds, store_msg(1));
+        assert!(bad_request(err).contains("deposit of exactly 10uflora"));
+    }
+    exec_at(&mut deps, 1_000, "alice", &[coin(10, DENOM)], store_msg(1)).unwrap();
+    let held = deposits(&deps, "alice");
+    assert_eq!(held.len(), 1);
+    assert_eq!(held[0]["record_id"], 1);
+    assert_eq!(held[0]["release_at"], 1_000 + PERIOD);
+    assert_eq!(held[0]["treasury"], "treasury");
+    assert_eq!(held[0]["forfeited"], false);
+
+    let err = exec(
+        &mut deps,
+        "admin",
+        json!({"set_spam_policy": {"deposit": {
+            "amount": {"denom": DENOM, "amount": "10"},
+            "challenge_period_secs": u64::MAX,
+            "treasury": "treasury",
+        }}}),
+    );
+    assert!(bad_request(err).contains("challenge_period_secs"));
+}
+
+#[test]
+fn claim_after_window() {
+    let mut deps = setup_deposit();
+    exec_at(&mut deps, 1_000, "alice", &[coin(10, DENOM)], store_msg(1)).unwrap();
+    exec_at(&mut deps, 1_200, "alice", &[coin(10, DENOM)], store_msg(2)).unwrap();
+
+    let err = exec_at(
+        &mut deps,
+        1_999,
+        "alice",
+        &[],
+        json!({"claim_deposits": {}}),
+    );
+    assert!(bad_request(err).contains("no deposits to claim"));
+
+    let res = claim(&mut deps, 2_000, "alice", None);
+    assert_eq!(
+        sends(&res),
+        vec![("alice".to_string(), vec![coin(10, DENOM)])]
+    );
+    assert_eq!(attr(&res, "count"), "1");
+    assert_eq!(deposits(&deps, "alice").len(), 1);
+
+    // 誰が精算しても返金先は owner
+    let res = claim(&mut deps, 5_000, "bob", Some("alice"));
+    assert_eq!(
+        sends(&res),
+        vec![("alice".to_string(), vec![coin(10, DENOM)])]
+    );
+    assert!(deposits(&deps, "alice").is_empty());
+}
+
+#[test]
+fn claim_sums_per_recipient() {
+    let mut deps = setup_deposit();
+    for t in [1_000, 1_001, 1_002] {
+        exec_at(&mut deps, t, "alice", &[coin(10, DENOM)], store_msg(t)).unwrap();
+    }
+    exec_at(
+        &mut deps,
+        1_100,
+        "admin",
+        &[],
+        json!({"hide": {"id": 2, "spam": true}}),
+    )
+    .unwrap();
+
+    let res = claim(&mut deps, 3_000, "alice", None);
+    assert_eq!(
+        sends(&res),
+        vec![
+            ("alice".to_string(), vec![coin(20, DENOM)]),
+            ("treasury".to_string(), vec![coin(10, DENOM)]),
+        ]
+    );
+    assert_eq!(attr(&res, "count"), "3");
+    assert_eq!(attr(&res, "forfeited"), "1");
+}
+
+#[test]
+fn withdraw_keeps_deposit_locked() {
+    let mut deps = setup_deposit();
+    exec_at(&mut deps, 1_000, "alice", &[coin(10, DENOM)], store_msg(1)).unwrap();
+    exec_at(&mut deps, 1_000, "alice", &[coin(10, DENOM)], store_msg(2)).unwrap();
+
+    let res = exec_at(
+        &mut deps,
+        1_010,
+        "alice",
+        &[],
+        json!({"withdraw": {"id": 1}}),
+    )
+    .unwrap();
+    assert!(res.messages.is_empty());
+    let held = deposits(&deps, "alice");
+    assert_eq!(held.len(), 2);
+    assert_eq!(held[0]["forfeited"], false);
+
+    // 未対応の通報がある記録の取り下げは没収扱い
+    exec(
+        &mut deps,
+        "bob",
+        json!({"report": {"id": 2, "category": "spam"}}),
+    )
+    .unwrap();
+    let res = exec_at(
+        &mut deps,
+        1_010,
+        "alice",
+        &[],
+        json!({"withdraw": {"id": 2}}),
+    )
+    .unwrap();
+    assert!(res.messages.is_empty());
+    assert_eq!(deposits(&deps, "alice")[1]["forfeited"], true);
+
+    let res = claim(&mut deps, 2_000, "alice", None);
+    assert_eq!(
+        sends(&res),
+        vec![
+            ("alice".to_string(), vec![coin(10, DENOM)]),
+            ("treasury".to_string(), vec![coin(10, DENOM)]),
+        ]
+    );
+}
+
+#[test]
+fn purge_forfeits_at_release() {
+    let mut deps = setup_deposit();
+    exec_at(&mut deps, 1_000, "alice", &[coin(10, DENOM)], store_msg(1)).unwrap();
+    let res = exec_at(
+        &mut deps,
+        1_010,
+        "admin",
+        &[],
+        json!({"purge": {"ids": [1]}}),
+    )
+    .unwrap();
+    assert!(res.messages.is_empty());
+    assert_eq!(deposits(&deps, "alice")[0]["forfeited"], true);
+
+    // treasury が自分で精算できる
+    let res = claim(&mut deps, 2_000, "treasury", Some("alice"));
+    assert_eq!(
+        sends(&res),
+        vec![("treasury".to_string(), vec![coin(10, DENOM)])]
+    );
+}
+
+#[test]
+fn spam_hide_forfeit_is_reversed_by_unhide() {
+    let mut deps = setup_deposit();
+    exec_at(&mut deps, 1_000, "alice", &[coin(10, DENOM)], store_msg(1)).unwrap();
+
+    let res = exec_at(
+        &mut deps,
+        1_100,
+        "admin",
+        &[],
+        json!({"hide": {"id": 1, "spam": true}}),
+    )
+    .unwrap();
+    assert!(res.messages.is_empty());
+    assert_eq!(attr(&res, "deposit_forfeited"), "true");
+    assert_eq!(deposits(&deps, "alice")[0]["forfeited"], true);
+
+    let res = exec_at(&mut deps, 1_200, "admin", &[], json!({"unhide": {"id": 1}})).unwrap();
+    assert_eq!(attr(&res, "deposit_restored"), "true");
+    assert_eq!(deposits(&deps, "alice")[0]["forfeited"], false);
+
+    // spam でない Hide は没収しない
+    let res = exec_at(&mut deps, 1_300, "admin", &[], json!({"hide": {"id": 1}})).unwrap();
+    assert_eq!(attr(&res, "deposit_forfeited"), "false");
+    exec_at(&mut deps, 1_400, "admin", &[], json!({"unhide": {"id": 1}})).unwrap();
+
+    // 期間が過ぎた後の spam Hide・Unhide は精算先を変えない
+    exec_at(
+        &mut deps,
+        1_500,
+        "admin",
+        &[],
+        json!({"hide": {"id": 1, "spam": true}}),
+    )
+    .unwrap();
+    let res = exec_at(&mut deps, 2_000, "admin", &[], json!({"unhide": {"id": 1}})).unwrap();
+    assert_eq!(attr(&res, "deposit_restored"), "false");
+    let res = claim(&mut deps, 2_000, "alice", None);
+    assert_eq!(
+        sends(&res),
+        vec![("treasury".to_string(), vec![coin(10, DENOM)])]
+    );
+
+    exec_at(&mut deps, 3_000, "alice", &[coin(10, DENOM)], store_msg(2)).unwrap();
+    let res = exec_at(
+        &mut deps,
+        4_000,
+        "admin",
+        &[],
+        json!({"hide": {"id": 2, "spam": true}}),
+    )
+    .unwrap();
+    assert_eq!(attr(&res, "deposit_forfeited"), "false");
+}
+
+#[test]
+fn batch_refunds_failed_items() {
+    let mut deps = setup_deposit();
+    let err = exec_at(
+        &mut deps,
+        1_000,
+        "alice",
+        &[coin(20, DENOM)],
+        batch_msg(&[CID, "bad", CID], "best_effort"),
+    );
+    assert!(bad_request(err).contains("deposit of exactly 30uflora"));
+
+    let res = exec_at(
+        &mut deps,
+        1_000,
+        "alice",
+        &[coin(30, DENOM)],
+        batch_msg(&[CID, "bad", CID], "best_effort"),
+    )
+    .unwrap();
+    assert_eq!(attr(&res, "stored"), "2");
+    assert_eq!(
+        sends(&res),
+        vec![("alice".to_string(), vec![coin(10, DENOM)])]
+    );
+    assert_eq!(deposits(&deps, "alice").len(), 2);
+
+    let res = exec_at(
+        &mut deps,
+        1_000,
+        "alice",
+        &[coin(20, DENOM)],
+        batch_msg(&[CID, CID], "all_or_nothing"),
+    )
+    .unwrap();
+    assert!(res.messages.is_empty());
+    assert_eq!(deposits(&deps, "alice").len(), 4);
+}
+
+#[test]
+fn deposit_total_overflow_is_rejected() {
+    let mut deps = setup();
+    exec(
+        &mut deps,
+        "admin",
+        json!({"set_spam_policy": {"deposit": {
+            "amount": {"denom": DENOM, "amount": u128::MAX.to_string()},
+            "challenge_period_secs": PERIOD,
+            "treasury": "treasury",
+        }}}),
+    )
+    .unwrap();
+    let err = exec_at(
+        &mut deps,
+        1_000,
+        "alice",
+        &[coin(u128::MAX, DENOM)],
+        batch_msg(&[CID, CID], "best_effort"),
+    );
+    assert!(bad_request(err).contains("overflows"));
+    exec_at(
+        &mut deps,
+        1_000,
+        "alice",
+        &[coin(u128::MAX, DENOM)],
+        store_msg(1),
+    )
+    .unwrap();
+}
@@ -0,0 +1,89 @@
+use super::*;
+
+fn area(deps: &TestDeps, msg: Value) -> Vec<u64> {
+    let mut out = ids(&query_json(deps, msg));
+    out.sort();
+    out
+}
+
+/// 日付変更線の両側・赤道 0 度・東京付近・大阪
+fn setup_places() -> TestDeps {
+    let mut deps = setup();
+    store(&mut deps, "alice", 1, "a", "0.0", "179.95");
+    store(&mut deps, "alice", 2, "a", "0.0", "-179.95");
+    store(&mut deps, "alice", 3, "a", "0.0", "0.0");
+    store(&mut deps, "alice", 4, "Prunus mume", "35.681", "139.767");
+    store(&mut deps, "alice", 5, "Other", "35.690", "139.700");
+    store(&mut deps, "alice", 6, "Prunus mume", "34.702", "135.495");
+    deps
+}
+
+fn in_box(min_lat: &str, min_lon: &str, max_lat: &str, max_lon: &str) -> Value {
+    json!({"list_in_box": {
+        "min_lat": min_lat, "min_lon": min_lon, "max_lat": max_lat, "max_lon": max_lon,
+    }})
+}
+
+#[test]
+fn list_in_box() {
+    let deps = setup_places();
+    assert_eq!(area(&deps, in_box("35", "139", "36", "140")), vec![4, 5]);
+    assert_eq!(area(&deps, in_box("34", "135", "36", "140")), vec![4, 5, 6]);
+    let mut q = in_box("34", "135", "36", "140");
+    q["list_in_box"]["species"] = json!("prunus mume");
+    assert_eq!(area(&deps, q), vec![4, 6]);
+    let mut q = in_box("34", "135", "36", "140");
+    q["list_in_box"]["start"] = json!(5);
+    assert_eq!(area(&deps, q), vec![5, 6]);
+
+    assert!(try_query(&deps, in_box("36", "139", "35", "140")).is_err());
+    assert!(try_query(&deps, in_box("35", "139", "91", "140")).is_err());
+    assert!(try_query(&deps, in_box("35", "139", "x", "140")).is_err());
+}
+
+#[test]
+fn list_in_box_across_antimeridian() {
+    let deps = setup_places();
+    // min_lon > max_lon は日付変更線をまたぐ
+    assert_eq!(
+        area(&deps, in_box("-1", "179.9", "1", "-179.9")),
+        vec![1, 2]
+    );
+    assert_eq!(area(&deps, in_box("-1", "179.9", "1", "180")), vec![1]);
+    assert_eq!(area(&deps, in_box("-1", "-180", "1", "-179.9")), vec![2]);
+
+    // 1 件ずつたどっても重複・取りこぼしがない
+    let mut seen = vec![];
+    let mut after = Value::Null;
+    loop {
+        let mut q = in_box("-1", "179.9", "1", "-179.9");
+        q["list_in_box"]["limit"] = json!(1);
+        q["list_in_box"]["start_after"] = after;
+        let resp = query_json(&deps, q);
+        seen.extend(ids(&resp));
+        after = resp["next_start_after"].clone();
+        if after.is_null() {
+            break;
+        }
+    }
+    seen.sort();
+    assert_eq!(seen, vec![1, 2]);
+}
+
+#[test]
+fn list_near() {
+    let deps = setup_places();
+    let near = |lat: &str, lon: &str, radius_m: u32| json!({"list_near": {"lat": lat, "lon": lon, "radius_m": radius_m}});
+    assert_eq!(area(&deps, near("35.681", "139.767", 1_000)), vec![4]);
+    assert_eq!(area(&deps, near("35.681", "139.767", 10_000)), vec![4, 5]);
+    assert_eq!(
+        area(&deps, near("35.681", "139.767", 450_000)),
+        vec![4, 5, 6]
+    );
+    // 日付変更線をまたぐ円
+    assert_eq!(area(&deps, near("0", "180", 20_000)), vec![1, 2]);
+    assert_eq!(area(&deps, near("0", "-179.99", 5_000)), vec![2]);
+
+    assert!(try_query(&deps, near("35", "139", 0)).is_err());
+    assert!(try_query(&deps, near("95", "139", 10)).is_err());
+}
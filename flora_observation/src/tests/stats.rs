@@ -0,0 +1,118 @@
+use super::*;
+use crate::calendar::{days_from_civil, SECONDS_PER_DAY};
+
+fn ts(y: i64, m: u32, d: u32, hour: i64) -> u64 {
+    (days_from_civil(y, m, d) * SECONDS_PER_DAY + hour * 3_600) as u64
+}
+
+fn setup_stats() -> TestDeps {
+    let mut deps = setup();
+    // UTC では 2024-12-31、JST では 2025-01-01
+    store(
+        &mut deps,
+        "alice",
+        ts(2024, 12, 31, 20),
+        "Prunus mume",
+        "35.681",
+        "139.767",
+    );
+    store(
+        &mut deps,
+        "alice",
+        ts(2024, 2, 29, 12),
+        "Prunus mume",
+        "35.681",
+        "139.767",
+    );
+    // ISO 2020-W53
+    store(
+        &mut deps,
+        "alice",
+        ts(2021, 1, 1, 12),
+        "Prunus mume",
+        "35.681",
+        "139.767",
+    );
+    // UTC では日曜（2020-W53）、JST では月曜（2021-W01）
+    store(
+        &mut deps,
+        "alice",
+        ts(2021, 1, 3, 20),
+        "Prunus mume",
+        "35.681",
+        "139.767",
+    );
+    store(
+        &mut deps,
+        "alice",
+        ts(2024, 6, 15, 0),
+        "Other",
+        "34.702",
+        "135.495",
+    );
+    deps
+}
+
+fn monthly(deps: &TestDeps, species: Option<&str>, year: u32, tz: i32) -> Vec<u64> {
+    let q = json!({"stats_monthly": {"species": species, "year": year, "tz_offset_minutes": tz}});
+    serde_json::from_value(query_json(deps, q)["months"].clone()).unwrap()
+}
+
+#[test]
+fn monthly_buckets_follow_tz_offset() {
+    let deps = setup_stats();
+    let m = monthly(&deps, None, 2024, 0);
+    assert_eq!((m[1], m[5], m[11]), (1, 1, 1));
+    assert_eq!(m.iter().sum::<u64>(), 3);
+
+    let m = monthly(&deps, None, 2024, 540);
+    assert_eq!(m[11], 0);
+    assert_eq!(monthly(&deps, None, 2025, 540)[0], 1);
+    assert_eq!(monthly(&deps, None, 2025, 0)[0], 0);
+
+    let m = monthly(&deps, Some("other"), 2024, 0);
+    assert_eq!(m.iter().sum::<u64>(), 1);
+    assert_eq!(m[5], 1);
+
+    let q = json!({"stats_monthly": {"year": 2024, "tz_offset_minutes": 841}});
+    assert!(try_query(&deps, q).is_err());
+}
+
+#[test]
+fn daily_buckets_cover_leap_day() {
+    let deps = setup_stats();
+    let days = |year: u32, month: u32, tz: i32| -> Vec<u64> {
+        let q = json!({"stats_daily": {"year": year, "month": month, "tz_offset_minutes": tz}});
+        serde_json::from_value(query_json(&deps, q)["days"].clone()).unwrap()
+    };
+    let feb = days(2024, 2, 0);
+    assert_eq!(feb.len(), 29);
+    assert_eq!(feb[28], 1);
+    assert_eq!(days(2023, 2, 0).len(), 28);
+    assert_eq!(days(2024, 12, 0)[30], 1);
+    assert_eq!(days(2024, 12, 540)[30], 0);
+    assert_eq!(days(2025, 1, 540)[0], 1);
+
+    let q = json!({"stats_daily": {"year": 2024, "month": 13}});
+    assert!(try_query(&deps, q).is_err());
+}
+
+#[test]
+fn weekly_buckets_use_iso_weeks() {
+    let deps = setup_stats();
+    let weeks = |year: u32, tz: i32| -> Vec<u64> {
+        let q = json!({"stats_weekly": {"year": year, "tz_offset_minutes": tz}});
+        serde_json::from_value(query_json(&deps, q)["weeks"].clone()).unwrap()
+    };
+    let w = weeks(2020, 0);
+    assert_eq!(w.len(), 53);
+    assert_eq!(w[52], 2);
+    assert_eq!(weeks(2020, 540)[52], 1);
+    let w = weeks(2021, 540);
+    assert_eq!(w.len(), 52);
+    assert_eq!(w[0], 1);
+    assert_eq!(weeks(2021, 0)[0], 0);
+    // 2024-12-31 は ISO 2025-W01
+    assert_eq!(weeks(2025, 0)[0], 1);
+    assert_eq!(weeks(2024, 0).iter().sum::<u64>(), 2);
+}
@@ -0,0 +1,199 @@
+use super::*;
+
+/// 注記を追加して seq を返す
+fn tag(deps: &mut TestDeps, who: &str, id: u64, tags: &[&str]) -> u64 {
+    let res = exec(
+        deps,
+        who,
+        json!({"append_annotation": {"id": id, "tags": tags}}),
+    )
+    .unwrap();
+    attr(&res, "seq").parse().unwrap()
+}
+
+fn remove_tag(
+    deps: &mut TestDeps,
+    who: &str,
+    id: u64,
+    seq: u64,
+    t: &str,
+) -> Result<Response, ContractError> {
+    exec(
+        deps,
+        who,
+        json!({"remove_tag": {"id": id, "seq": seq, "tag": t}}),
+    )
+}
+
+fn by_tag(deps: &TestDeps, t: &str) -> Vec<u64> {
+    ids(&query_json(deps, json!({"list_by_tag": {"tag": t}})))
+}
+
+fn tag_counts(deps: &TestDeps, msg: Value) -> Vec<(String, u64)> {
+    let resp = query_json(deps, json!({ "tag_counts": msg }));
+    resp["tags"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| {
+            (
+                t["tag"].as_str().unwrap().to_string(),
+                t["count"].as_u64().unwrap(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn tags_are_normalized() {
+    let mut deps = setup();
+    let id = store(&mut deps, "alice", 10, "Prunus mume", "35.681", "139.767");
+    let seq = tag(
+        &mut deps,
+        "alice",
+        id,
+        &["#BioBlitz2025", " bioblitz2025 ", "Spring"],
+    );
+
+    let anns = query_json(&deps, json!({"annotations": {"id": id}}));
+    assert_eq!(
+        anns["annotations"][0]["annotation"]["tags"],
+        json!(["bioblitz2025", "spring"])
+    );
+    assert_eq!(anns["annotations"][0]["seq"], seq);
+    assert_eq!(by_tag(&deps, "#BIOBLITZ2025"), vec![id]);
+    assert_eq!(
+        tag_counts(&deps, json!({})),
+        vec![("bioblitz2025".to_string(), 1), ("spring".to_string(), 1)]
+    );
+
+    for bad in ["#", "two words", ""] {
+        let err = exec(
+            &mut deps,
+            "alice",
+            json!({"append_annotation": {"id": id, "tags": [bad]}}),
+        );
+        assert!(bad_request(err).contains("invalid tag"), "{bad:?}");
+    }
+    let many: Vec<String> = (0..17).map(|i| format!("t{i}")).collect();
+    let err = exec(
+        &mut deps,
+        "alice",
+        json!({"append_annotation": {"id": id, "tags": many}}),
+    );
+    assert!(bad_request(err).contains("at most"));
+}
+
+#[test]
+fn remove_tag_counts_references() {
+    let mut deps = setup();
+    let id = store(&mut deps, "alice", 10, "Prunus mume", "35.681", "139.767");
+    let first = tag(&mut deps, "alice", id, &["bloom"]);
+    let second = tag(&mut deps, "bob", id, &["bloom", "river"]);
+    assert_eq!(
+        tag_counts(&deps, json!({"prefix": "bloom"})),
+        vec![("bloom".to_string(), 1)]
+    );
+
+    // 他人の注記のタグは外せない
+    assert!(matches!(
+        remove_tag(&mut deps, "alice", id, second, "bloom"),
+        Err(ContractError::Unauthorized)
+    ));
+    assert!(matches!(
+        remove_tag(&mut deps, "alice", id, first, "river"),
+        Err(ContractError::NotFound)
+    ));
+
+    // 参照が残っている間は索引に残る
+    remove_tag(&mut deps, "alice", id, first, "#Bloom").unwrap();
+    assert_eq!(by_tag(&deps, "bloom"), vec![id]);
+    assert_eq!(
+        tag_counts(&deps, json!({"prefix": "bloom"})),
+        vec![("bloom".to_string(), 1)]
+    );
+    let anns = query_json(&deps, json!({"annotations": {"id": id}}));
+    assert!(anns["annotations"][0]["annotation"]["tags"].is_null());
+
+    remove_tag(&mut deps, "bob", id, second, "bloom").unwrap();
+    assert_eq!(by_tag(&deps, "bloom"), Vec::<u64>::new());
+    assert_eq!(tag_counts(&deps, json!({})), vec![("river".to_string(), 1)]);
+    assert!(matches!(
+        remove_tag(&mut deps, "bob", id, second, "bloom"),
+        Err(ContractError::NotFound)
+    ));
+}
+
+#[test]
+fn hidden_records_leave_tag_results() {
+    let mut deps = setup();
+    let a = store(&mut deps, "alice", 10, "Prunus mume", "35.681", "139.767");
+    let b = store(&mut deps, "alice", 20, "Prunus mume", "35.681", "139.767");
+    tag(&mut deps, "alice", a, &["bloom"]);
+    tag(&mut deps, "alice", b, &["bloom"]);
+    assert_eq!(tag_counts(&deps, json!({})), vec![("bloom".to_string(), 2)]);
+
+    exec(&mut deps, "admin", json!({"hide": {"id": a}})).unwrap();
+    assert_eq!(by_tag(&deps, "bloom"), vec![b]);
+    assert_eq!(tag_counts(&deps, json!({})), vec![("bloom".to_string(), 1)]);
+
+    exec(&mut deps, "admin", json!({"unhide": {"id": a}})).unwrap();
+    assert_eq!(by_tag(&deps, "bloom"), vec![a, b]);
+    assert_eq!(tag_counts(&deps, json!({})), vec![("bloom".to_string(), 2)]);
+}
+
+#[test]
+fn list_by_tag_pages_with_cursor() {
+    let mut deps = setup();
+    let ids_all: Vec<u64> = (0..5)
+        .map(|i| {
+            let id = store(
+                &mut deps,
+                "alice",
+                10 + i,
+                "Prunus mume",
+                "35.681",
+                "139.767",
+            );
+            tag(&mut deps, "alice", id, &["bloom"]);
+            id
+        })
+        .collect();
+
+    let first = query_json(&deps, json!({"list_by_tag": {"tag": "bloom", "limit": 2}}));
+    assert_eq!(ids(&first), ids_all[..2].to_vec());
+    let cursor = first["next_start_after"].clone();
+    assert_eq!(cursor["id"], ids_all[1]);
+
+    // 前ページの next_start_after をそのまま渡す
+    let second = query_json(
+        &deps,
+        json!({"list_by_tag": {"tag": "bloom", "start_after": cursor, "limit": 10}}),
+    );
+    assert_eq!(ids(&second), ids_all[2..].to_vec());
+    assert!(second["next_start_after"].is_null());
+}
+
+#[test]
+fn tag_counts_prefix_and_paging() {
+    let mut deps = setup();
+    let id = store(&mut deps, "alice", 10, "Prunus mume", "35.681", "139.767");
+    tag(
+        &mut deps,
+        "alice",
+        id,
+        &["bloom", "bloom2025", "blossom", "river"],
+    );
+
+    assert_eq!(
+        tag_counts(&deps, json!({"prefix": "#BLOOM"})),
+        vec![("bloom".to_string(), 1), ("bloom2025".to_string(), 1)]
+    );
+    let page = query_json(&deps, json!({"tag_counts": {"prefix": "b", "limit": 2}}));
+    assert_eq!(page["next_start_after"], "bloom2025");
+    let rest = tag_counts(
+        &deps,
+        json!({"prefix": "b", "start_after": "bloom2025", "limit": 2}),
+    );
+    assert_eq!(rest, vec![("blossom".to_string(), 1)]);
+}
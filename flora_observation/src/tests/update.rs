@@ -0,0 +1,166 @@
+use super::counters::assert_counts_consistent;
+use super::*;
+
+const CID2: &str = "bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi";
+
+fn place_payload(observed_at: u64, species: &str, lat: &str, lon: &str) -> Value {
+    json!({"observed_at": observed_at, "species": species, "place": {"lat": lat, "lon": lon}})
+}
+
+fn update(
+    deps: &mut TestDeps,
+    sender: &str,
+    id: u64,
+    payload: Value,
+) -> Result<Response, ContractError> {
+    exec(
+        deps,
+        sender,
+        json!({"update_record": {"id": id, "payload": payload, "cid": CID2}}),
+    )
+}
+
+#[test]
+fn update_keeps_revision_history() {
+    let mut deps = setup();
+    let id = store(&mut deps, "alice", 10, "Prunus mume", "35.681", "139.767");
+    let res = update(
+        &mut deps,
+        "alice",
+        id,
+        place_payload(20, "Prunus mume", "35.681", "139.767"),
+    )
+    .unwrap();
+    assert_eq!(attr(&res, "revision"), "1");
+    update(
+        &mut deps,
+        "alice",
+        id,
+        place_payload(30, "Other", "34.702", "135.495"),
+    )
+    .unwrap();
+
+    let rec = query_json(&deps, json!({"get": {"id": id}}))["record"].clone();
+    assert_eq!(rec["observed_at"], 30);
+    assert_eq!(rec["species"], "other");
+    assert_eq!(rec["cid"], CID2);
+    assert_eq!(rec["edited"]["revision"], 2);
+    assert_eq!(rec["edited"]["by"], "alice");
+
+    let history = query_json(&deps, json!({"record_history": {"id": id}}));
+    let revs = history["revisions"].as_array().unwrap();
+    assert_eq!(revs.len(), 2);
+    assert_eq!(revs[0]["revision"], 0);
+    assert_eq!(revs[0]["payload"]["observed_at"], 10);
+    assert_eq!(revs[0]["cid"], CID);
+    assert_eq!(revs[1]["revision"], 1);
+    assert_eq!(revs[1]["payload"]["observed_at"], 20);
+
+    let page = query_json(
+        &deps,
+        json!({"record_history": {"id": id, "start_after": 0, "limit": 1}}),
+    );
+    assert_eq!(page["revisions"][0]["revision"], 1);
+    assert_eq!(page["revisions"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn update_moves_indexes_and_counters() {
+    let mut deps = setup();
+    let id = store(&mut deps, "alice", 10, "Prunus mume", "35.681", "139.767");
+    store(&mut deps, "bob", 15, "Prunus mume", "35.681", "139.767");
+    update(
+        &mut deps,
+        "alice",
+        id,
+        place_payload(20, "Other", "34.702", "135.495"),
+    )
+    .unwrap();
+
+    let list = |deps: &TestDeps, filter: Value| ids(&query_json(deps, json!({"list": filter})));
+    assert_eq!(list(&deps, json!({"species": "prunus mume"})), vec![2]);
+    assert_eq!(list(&deps, json!({"species": "other"})), vec![1]);
+    assert_eq!(list(&deps, json!({"geohash_prefix": "xn76"})), vec![2]);
+    assert_eq!(list(&deps, json!({"geohash_prefix": "xn0m"})), vec![1]);
+    assert_eq!(list(&deps, json!({"start": 16})), vec![1]);
+    let sender = query_json(
+        &deps,
+        json!({"list_by_sender": {"sender": "alice", "start": 20}}),
+    );
+    assert_eq!(ids(&sender), vec![1]);
+    assert_counts_consistent(&deps, &["prunus mume", "other"], &["xn76", "xn0m", "x"]);
+    assert_eq!(
+        query_json(&deps, json!({"count": {"species": "other"}}))["count"],
+        1
+    );
+}
+
+#[test]
+fn cursor_survives_edit_between_pages() {
+    let mut deps = setup();
+    for t in [10, 20, 30] {
+        store(&mut deps, "alice", t, "a", "35.681", "139.767");
+    }
+    let resp = query_json(&deps, json!({"list": {"limit": 2}}));
+    assert_eq!(ids(&resp), vec![1, 2]);
+    // ページ末尾のレコードを後ろへ動かしても、続きは元の位置から
+    update(
+        &mut deps,
+        "alice",
+        2,
+        place_payload(40, "a", "35.681", "139.767"),
+    )
+    .unwrap();
+    let resp = query_json(
+        &deps,
+        json!({"list": {"limit": 2, "start_after": resp["next_start_after"]}}),
+    );
+    assert_eq!(ids(&resp), vec![3, 2]);
+}
+
+#[test]
+fn update_permissions() {
+    let mut deps = setup();
+    let id = store(&mut deps, "alice", 10, "a", "35.681", "139.767");
+    let payload = place_payload(20, "a", "35.681", "139.767");
+
+    assert_eq!(
+        update(&mut deps, "bob", id, payload.clone()),
+        Err(ContractError::Unauthorized)
+    );
+    // admin は admin_can_edit を有効にしたときだけ
+    assert_eq!(
+        update(&mut deps, "admin", id, payload.clone()),
+        Err(ContractError::Unauthorized)
+    );
+    assert_eq!(
+        exec(
+            &mut deps,
+            "alice",
+            json!({"set_admin_can_edit": {"enabled": true}})
+        ),
+        Err(ContractError::Unauthorized)
+    );
+    exec(
+        &mut deps,
+        "admin",
+        json!({"set_admin_can_edit": {"enabled": true}}),
+    )
+    .unwrap();
+    let res = update(&mut deps, "admin", id, payload.clone()).unwrap();
+    assert_eq!(attr(&res, "editor"), "admin");
+
+    assert_eq!(
+        update(&mut deps, "alice", 99, payload.clone()),
+        Err(ContractError::NotFound)
+    );
+    assert_eq!(
+        bad_request(update(&mut deps, "alice", id, json!({"species": "a"}))),
+        "payload.observed_at (u64 seconds) is required"
+    );
+    exec(&mut deps, "admin", json!({"hide": {"id": id}})).unwrap();
+    assert_eq!(
+        bad_request(update(&mut deps, "alice", id, payload)),
+        "hidden records cannot be updated"
+    );
+}
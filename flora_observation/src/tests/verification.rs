@@ -0,0 +1,189 @@
+use super::*;
+
+fn verify(deps: &mut TestDeps, who: &str, id: u64, taxon: &str, confidence: u8) -> Response {
+    exec(
+        deps,
+        who,
+        json!({"verify": {"id": id, "taxon_id": taxon, "confidence": confidence}}),
+    )
+    .unwrap()
+}
+
+fn retract(deps: &mut TestDeps, who: &str, id: u64) -> Result<Response, ContractError> {
+    exec(deps, who, json!({"retract_verification": {"id": id}}))
+}
+
+fn history(deps: &TestDeps, id: u64, start_after: Option<u64>, limit: u32) -> Value {
+    query_json(
+        deps,
+        json!({"verification_history": {"id": id, "start_after": start_after, "limit": limit}}),
+    )
+}
+
+fn setup_two() -> TestDeps {
+    let mut deps = setup();
+    exec(
+        &mut deps,
+        "admin",
+        json!({"grant_role": {"role": "verifier", "addr": "ver2"}}),
+    )
+    .unwrap();
+    deps
+}
+
+#[test]
+fn reverify_replaces_and_archives() {
+    let mut deps = setup();
+    let id = store(&mut deps, "alice", 10, "Prunus mume", "35.681", "139.767");
+    verify(&mut deps, "ver", id, "t1", 60);
+    verify(&mut deps, "ver", id, "t2", 90);
+
+    let rec = query_json(&deps, json!({"get": {"id": id}}))["record"].clone();
+    assert_eq!(rec["verification_count"], 1);
+    let current = query_json(&deps, json!({"verifications": {"id": id}}));
+    let current = current["verifications"].as_array().unwrap();
+    assert_eq!(current.len(), 1);
+    assert_eq!(current[0]["entry"]["taxon_id"], "t2");
+    assert_eq!(current[0]["entry"]["confidence"], 90);
+
+    let h = history(&deps, id, None, 10);
+    let entries = h["entries"].as_array().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["entry"]["taxon_id"], "t1");
+    assert_eq!(entries[0]["ended_by"], "superseded");
+}
+
+#[test]
+fn retract_regrades_and_archives() {
+    let mut deps = setup_two();
+    let id = store(&mut deps, "alice", 10, "Prunus mume", "35.681", "139.767");
+    verify(&mut deps, "ver", id, "t1", 90);
+    let res = verify(&mut deps, "ver2", id, "t1", 80);
+    assert_eq!(attr(&res, "quality_grade"), "research_grade");
+
+    let res = retract(&mut deps, "ver2", id).unwrap();
+    assert_eq!(attr(&res, "quality_grade"), "needs_id");
+    let rec = query_json(&deps, json!({"get": {"id": id}}))["record"].clone();
+    assert!(rec["consensus_taxon"].is_null());
+    assert_eq!(rec["verification_count"], 1);
+    let listed = query_json(&deps, json!({"list": {"quality_grade": "needs_id"}}));
+    assert_eq!(ids(&listed), vec![id]);
+
+    let h = history(&deps, id, None, 10);
+    assert_eq!(h["entries"][0]["entry"]["verifier"], "ver2");
+    assert_eq!(h["entries"][0]["ended_by"], "retracted");
+
+    // 取り消した検証者の一覧からも消える
+    let by = query_json(&deps, json!({"verifications_by": {"verifier": "ver2"}}));
+    assert!(by["verifications"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn retract_without_opinion() {
+    let mut deps = setup();
+    let id = store(&mut deps, "alice", 10, "Prunus mume", "35.681", "139.767");
+    assert!(matches!(
+        retract(&mut deps, "ver", id),
+        Err(ContractError::NotFound)
+    ));
+    assert!(matches!(
+        retract(&mut deps, "ver", 99),
+        Err(ContractError::NotFound)
+    ));
+    verify(&mut deps, "ver", id, "t1", 90);
+    retract(&mut deps, "ver", id).unwrap();
+    assert!(matches!(
+        retract(&mut deps, "ver", id),
+        Err(ContractError::NotFound)
+    ));
+}
+
+#[test]
+fn verify_rejects_bad_input() {
+    let mut deps = setup();
+    let id = store(&mut deps, "alice", 10, "Prunus mume", "35.681", "139.767");
+    let err = exec(
+        &mut deps,
+        "ver",
+        json!({"verify": {"id": id, "taxon_id": "t1", "confidence": 101}}),
+    );
+    assert!(bad_request(err).contains("confidence"));
+    let err = exec(
+        &mut deps,
+        "ver",
+        json!({"verify": {"id": id, "taxon_id": " ", "confidence": 50}}),
+    );
+    assert!(bad_request(err).contains("taxon_id"));
+    verify(&mut deps, "ver", id, "t1", 100);
+}
+
+#[test]
+fn verifications_by_agrees_and_paging() {
+    let mut deps = setup_two();
+    exec(
+        &mut deps,
+        "admin",
+        json!({"grant_role": {"role": "verifier", "addr": "ver3"}}),
+    )
+    .unwrap();
+    let a = store(&mut deps, "alice", 10, "Prunus mume", "35.681", "139.767");
+    let b = store(&mut deps, "alice", 20, "Prunus mume", "35.681", "139.767");
+    let c = store(&mut deps, "alice", 30, "Prunus mume", "35.681", "139.767");
+    // a: 合意に同意、b: 合意と食い違う、c: 合意なし
+    verify(&mut deps, "ver", a, "t1", 90);
+    verify(&mut deps, "ver2", a, "t1", 90);
+    verify(&mut deps, "ver", b, "t2", 10);
+    verify(&mut deps, "ver2", b, "t1", 100);
+    verify(&mut deps, "ver3", b, "t1", 100);
+    verify(&mut deps, "ver", c, "t1", 50);
+
+    let page = query_json(
+        &deps,
+        json!({"verifications_by": {"verifier": "ver", "limit": 2}}),
+    );
+    let got = page["verifications"].as_array().unwrap();
+    assert_eq!(got.len(), 2);
+    assert_eq!(got[0]["record_id"], a);
+    assert_eq!(got[0]["agrees"], true);
+    assert_eq!(got[1]["record_id"], b);
+    assert_eq!(got[1]["consensus_taxon"], "t1");
+    assert_eq!(got[1]["agrees"], false);
+    assert_eq!(page["next_start_after"], b);
+
+    let page = query_json(
+        &deps,
+        json!({"verifications_by": {"verifier": "ver", "start_after": b, "limit": 2}}),
+    );
+    let got = page["verifications"].as_array().unwrap();
+    assert_eq!(got.len(), 1);
+    assert_eq!(got[0]["record_id"], c);
+    assert!(got[0]["agrees"].is_null());
+    assert!(page["next_start_after"].is_null());
+}
+
+#[test]
+fn history_paging() {
+    let mut deps = setup();
+    let id = store(&mut deps, "alice", 10, "Prunus mume", "35.681", "139.767");
+    for taxon in ["t1", "t2", "t3", "t4"] {
+        verify(&mut deps, "ver", id, taxon, 50);
+    }
+    retract(&mut deps, "ver", id).unwrap();
+
+    let first = history(&deps, id, None, 3);
+    let entries = first["entries"].as_array().unwrap();
+    let taxa: Vec<&str> = entries
+        .iter()
+        .map(|e| e["entry"]["taxon_id"].as_str().unwrap())
+        .collect();
+    assert_eq!(taxa, vec!["t1", "t2", "t3"]);
+    let next = first["next_start_after"].as_u64().unwrap();
+    assert_eq!(next, entries[2]["seq"].as_u64().unwrap());
+
+    let rest = history(&deps, id, Some(next), 3);
+    let entries = rest["entries"].as_array().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["entry"]["taxon_id"], "t4");
+    assert_eq!(entries[0]["ended_by"], "retracted");
+    assert!(rest["next_start_after"].is_null());
+}